@@ -1,30 +1,18 @@
 // Iteration on part 2, add IO and addressing modes
 
+use intcode::load;
 use intcode::IntcodeComp;
-use std::fs::File;
-use std::io::Read;
+use std::error::Error;
 
-fn main() -> std::io::Result<()> {
-    // Open file
-    let mut file = File::open("./TEST.txt")?;
-    let mut buf = String::new();
-    file.read_to_string(&mut buf)?;
-
-    let ops = buf.split(','); // split on comma
-
-    let mut mem_space: Vec<i64> = Vec::new(); // Could use with_capacity here for a speed optimization
-    for i in ops {
-        // Parse as int and push all found values into mem_space, ignore not-ints with a warning(split gives us one white space at the end so one is expected)
-        match i.parse::<i64>() {
-            Ok(num) => mem_space.push(num),
-            Err(e) => println!("Warning: {}, string: {}", e, i),
-        }
-    }
+fn main() -> Result<(), Box<dyn Error>> {
+    let mem_space = load::from_file("./TEST.txt")?;
 
     // Part 1
     let mut comp = IntcodeComp::new(&mem_space);
     comp.input(1);
-    comp.run_all();
+    if let Err(e) = comp.run_all() {
+        println!("Diagnostic program faulted: {}", e);
+    }
     let mut o: Vec<i64> = Vec::new();
     while let Some(val) = comp.output() {
         o.push(val);
@@ -34,7 +22,9 @@ fn main() -> std::io::Result<()> {
     // Part 2
     comp = IntcodeComp::new(&mem_space);
     comp.input(5);
-    comp.run_all();
+    if let Err(e) = comp.run_all() {
+        println!("Diagnostic program faulted: {}", e);
+    }
     o.clear();
     while let Some(val) = comp.output() {
         o.push(val);
@@ -1,71 +1,39 @@
+use intcode::network::{NetState, Topology};
 use intcode::prog_from_file;
-use intcode::IntcodeComp;
+use intcode::threaded::{permutations, sweep, ThreadedNetwork};
 
 // Run a pipeline of 5 intcode computers and find the inputs that maximise their outputs
 fn maximise_thrusters(prog: &[i64], min_phase: i64, max_phase: i64, feedback: bool) -> i64 {
-    let run_pipeline = |phases: &[i64]| -> i64 {
-        let mut output = 0;
-        let mut comps: Vec<IntcodeComp> = Vec::with_capacity(phases.len());
+    let run_pipeline = |phases: Vec<i64>| -> i64 {
+        // Amplifiers are chained one into the next, with feedback the last one drives the first again
+        //  every amplifier gets a thread of its own
+        let n = phases.len();
+        let topology = if feedback {
+            Topology::ring(n)
+        } else {
+            Topology::pipeline(n)
+        };
+        let mut net = ThreadedNetwork::new(prog, n, topology);
 
-        // Initialize an instance of intcode computer for each passed phase
-        for &val in phases {
-            let mut c = IntcodeComp::new(prog);
-            c.input(val);
-            comps.push(c);
+        // Each amplifier takes its phase first, then the first one gets the initial signal of 0
+        for (i, &val) in phases.iter().enumerate() {
+            net.send(i, val);
         }
+        net.send(0, 0);
 
-        // Execute feedback loop until cores terminate
-        let mut running = true;
-        while running {
-            // Run each computer in sequence
-            for comp in comps.iter_mut() {
-                // Output contains last data from the last stage
-                comp.input(output);
-
-                // If comp terminated, signal that loop should end after this run
-                if !comp.eval_async() {
-                    running = false;
-                }
-
-                // Always take output if present
-                output = match comp.output() {
-                    Some(val) => val,
-                    None => panic!("Output expected but none present"),
-                };
-            }
-            if !feedback {
-                break;
-            }
+        // Thrusters get whatever the last amplifier said last
+        match net.run() {
+            (NetState::Halted, nodes) => nodes[n - 1].last.unwrap(),
+            (state, _) => panic!("Amplifiers stopped early: {:?}", state),
         }
-        output
     };
 
-    let mut best_output: i64 = 0;
-
-    // Each phase setting is used exactly once
-    // there's definitely a more idomatic way to do this I might come back and clean this up when I discover it
-    for a in min_phase..=max_phase {
-        for b in min_phase..=max_phase {
-            if b != a {
-                for c in min_phase..=max_phase {
-                    if c != b && c != a {
-                        for d in min_phase..=max_phase {
-                            if d != a && d != b && d != c {
-                                for e in min_phase..=max_phase {
-                                    if e != a && e != b && e != c && e != d {
-                                        let out = run_pipeline(&[a, b, c, d, e]);
-                                        best_output =
-                                            if out > best_output { out } else { best_output };
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    best_output
+    // Each phase setting is used exactly once, try every ordering spread over all the cores
+    let phases: Vec<i64> = (min_phase..=max_phase).collect();
+    sweep(permutations(&phases), run_pipeline)
+        .into_iter()
+        .max()
+        .unwrap_or(0)
 }
 
 #[test]
@@ -8,7 +8,9 @@ fn main() -> std::io::Result<()> {
 
     // Per spec, input 1 to program and print outputs
     comp.input(1);
-    comp.run_all();
+    if let Err(e) = comp.run_all() {
+        println!("BOOST program faulted: {}", e);
+    }
 
     let mut comp_out: Vec<i64> = Vec::new();
     while let Some(val) = comp.output() {
@@ -19,7 +21,9 @@ fn main() -> std::io::Result<()> {
     // Reinit for second program run
     comp = IntcodeComp::new(&prog);
     comp.input(2); // Select sensor boost mode
-    comp.run_all();
+    if let Err(e) = comp.run_all() {
+        println!("BOOST program faulted: {}", e);
+    }
     comp_out = Vec::new();
     while let Some(val) = comp.output() {
         comp_out.push(val);
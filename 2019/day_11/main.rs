@@ -1,7 +1,9 @@
+use intcode::display::{Display, Palette};
+use intcode::load;
 use intcode::IntcodeComp;
-use std::collections::HashMap;
-use std::fmt;
-use std::fs;
+use intcode::RunState;
+use intcode::{InputSource, OutputSink};
+use std::error::Error;
 
 #[derive(Copy, Clone)]
 enum Color {
@@ -20,98 +22,19 @@ impl From<i64> for Color {
     }
 }
 
-// State of the ship, simply the default color and a map of the tiles that have been painted
-struct Ship {
-    def_color: Color,                  // Default color
-    tiles: HashMap<(i32, i32), Color>, // hash map, maps coord tuple to colors
-}
-
-// Ship implementation provides getters and setters for individual tiles and a format method
-impl Ship {
-    fn new(def: Color, first_tile: Option<Color>) -> Ship {
-        let mut t = HashMap::new();
-        match first_tile {
-            Some(c) => t.insert((0, 0), c),
-            None => None,
-        };
-        Ship {
-            def_color: def,
-            tiles: t,
-        }
-    }
-
-    // Part 1 solution
-    fn tiles_painted(&self) -> usize {
-        self.tiles.len()
-    }
-
-    // get color of a tile, return painted value if it exists else default
-    fn get_color(&self, coord: (i32, i32)) -> Color {
-        match self.tiles.get(&coord) {
-            Some(c) => *c,
-            None => self.def_color,
-        }
-    }
-
-    // paint a tile
-    fn set_color(&mut self, coord: (i32, i32), color: Color) {
-        self.tiles.insert(coord, color);
-    }
-
-    // Derive size of grid by searching points that have been painted
-    fn dimensions(&self) -> (i32, i32, i32, i32) {
-        let mut x_min = std::i32::MAX;
-        let mut x_max = std::i32::MIN;
-        let mut y_min = std::i32::MAX;
-        let mut y_max = std::i32::MIN;
-        // Simple brute force max search
-        for k in self.tiles.keys() {
-            if k.0 > x_max {
-                x_max = k.0
-            };
-            if k.1 > y_max {
-                y_max = k.1
-            };
-            if k.0 < x_min {
-                x_min = k.0
-            };
-            if k.1 < y_min {
-                y_min = k.1
-            };
-        }
-        (x_min, x_max, y_min, y_max)
-    }
-}
-
-impl fmt::Display for Ship {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Define our "character set"
-        let render = |x: Color| match x {
-            Color::White => '#',
-            Color::Black => ' ',
-        };
-        // Derive dimensions of traversed space
-        let (x_min, x_max, y_min, y_max) = self.dimensions();
-
-        // Print Rows from top to bottom, columns left to right
-        let mut i = y_max;
-        while i >= y_min {
-            for col in x_min..=x_max {
-                write!(
-                    f,
-                    "{}",
-                    match self.tiles.get(&(col, i)) {
-                        Some(&c) => render(c),
-                        None => render(self.def_color),
-                    }
-                )?;
-            }
-            writeln!(f)?;
-            i -= 1;
-        }
-
-        Ok(())
+// The ship's hull, black unless painted. Painting starts at (0, 0) and y grows upwards
+fn ship(first_tile: Option<Color>) -> Display {
+    let mut hull = Display::new();
+    hull.set_background(Color::Black as i64);
+    hull.set_palette(Palette::new(&[
+        (Color::Black as i64, ' '),
+        (Color::White as i64, '#'),
+    ]));
+    hull.set_y_up(true);
+    if let Some(c) = first_tile {
+        hull.set(0, 0, c as i64);
     }
+    hull
 }
 
 // Define directions a bot might be facing and enum for turn directions
@@ -166,21 +89,22 @@ impl Direction {
     }
 }
 
-// Painter bot class
+// Painter bot class, the bot is the intcode program's only device: its camera is the input and
+//  the paint sprayer and wheels share the output
 struct Bot<'a> {
     facing: Direction,
-    coord: (i32, i32),
-    cpu: IntcodeComp,
-    ship: &'a mut Ship, // ref to ship we are painting
+    coord: (i64, i64),
+    painted: bool,         // Outputs alternate, color to paint then direction to turn
+    ship: &'a mut Display, // ref to ship we are painting
 }
 
 impl Bot<'_> {
     // Initial state of bot, Ship has same lifetime as returned object
-    fn new<'a>(s: &'a mut Ship, prog: &[i64]) -> Bot<'a> {
+    fn new(s: &mut Display) -> Bot<'_> {
         Bot {
             coord: (0, 0),
-            cpu: IntcodeComp::new(prog),
             facing: Direction::North,
+            painted: false,
             ship: s,
         }
     }
@@ -198,60 +122,55 @@ impl Bot<'_> {
     }
 
     // Paint until robot halts, no return, just mutate state of ship
-    fn paint(&mut self) {
-        // Flow is always input first, then CPU outputs the color to paint the current tile and the direction to move
-        self.cpu.input(self.ship.get_color(self.coord) as i64);
-        while self.cpu.eval_async() || self.cpu.output_available() > 0 {
-            // unwrap() here, if CPU pauses for input and does not provide 2 outputs this is a logic error
-            self.ship
-                .set_color(self.coord, Color::from(self.cpu.output().unwrap()));
-            self.facing = self.facing.turn(Turn::from(self.cpu.output().unwrap()));
+    fn paint(&mut self, prog: &[i64]) {
+        if let RunState::Error(e) = IntcodeComp::new(prog).attach(self) {
+            println!("Painter program faulted: {}", e);
+        }
+    }
+}
 
-            // Take one step forward
-            self.step_forward();
+// Camera, read current tile
+impl InputSource for Bot<'_> {
+    fn next_input(&mut self) -> Option<i64> {
+        Some(self.ship.get(self.coord.0, self.coord.1))
+    }
+}
 
-            // Read current tile
-            self.cpu.input(self.ship.get_color(self.coord) as i64);
+// Paint the current tile, then turn and take one step forward
+impl OutputSink for Bot<'_> {
+    fn push_output(&mut self, val: i64) {
+        if !self.painted {
+            self.ship
+                .set(self.coord.0, self.coord.1, Color::from(val) as i64);
+        } else {
+            self.facing = self.facing.turn(Turn::from(val));
+            self.step_forward();
         }
+        self.painted = !self.painted;
     }
 }
 
-fn main() -> std::io::Result<()> {
-    let buf = &fs::read("./painter.txt")?;
-    // Convert to string, Trim whitespace, Split on commas, Parse as i64, Collect as vec
-    let prog: Vec<i64> = std::str::from_utf8(buf)
-        .unwrap()
-        .trim()
-        .split(',')
-        .map(|x| x.parse::<i64>().unwrap())
-        .collect();
+fn main() -> Result<(), Box<dyn Error>> {
+    let prog = load::from_file("./painter.txt")?;
     {
-        let mut s = Ship::new(Color::Black, None);
+        let mut s = ship(None);
         // Limit scope so that ships borrow is released and we can inspect it's state after the robot is done
         {
-            let mut b = Bot::new(&mut s, &prog);
-            b.paint();
+            let mut b = Bot::new(&mut s);
+            b.paint(&prog);
         }
 
-        println!(
-            "Ship painted as follows({} tiles):\n{}",
-            s.tiles_painted(),
-            s
-        );
+        println!("Ship painted as follows({} tiles):\n{}", s.len(), s);
     }
     {
-        let mut s = Ship::new(Color::Black, Some(Color::White));
+        let mut s = ship(Some(Color::White));
         // Limit scope so that ships borrow is released and we can inspect it's state after the robot is done
         {
-            let mut b = Bot::new(&mut s, &prog);
-            b.paint();
+            let mut b = Bot::new(&mut s);
+            b.paint(&prog);
         }
 
-        println!(
-            "Ship painted as follows({} tiles):\n{}",
-            s.tiles_painted(),
-            s
-        );
+        println!("Ship painted as follows({} tiles):\n{}", s.len(), s);
     }
 
     Ok(())
@@ -1,194 +1,368 @@
+use intcode::display::{Display, Palette, Rect, Update};
 use intcode::prog_from_file;
+use intcode::trace::Trace;
+#[cfg(feature = "tui")]
+use intcode::tui::{Key, Terminal, Tui};
 use intcode::IntcodeComp;
-use std::convert::From;
-use std::convert::TryInto;
-use std::fmt;
+use intcode::RunState;
+use intcode::{InputSource, OutputSink};
+use std::collections::VecDeque;
+use std::env;
+use std::error::Error;
 use std::io::Read;
+use std::process;
+#[cfg(feature = "tui")]
+use std::time::Duration;
 
-#[derive(Hash, PartialEq, Eq, Debug)]
-struct Coord {
-    x: i64,
-    y: i64,
+// Tile ids the game draws with
+const EMPTY: i64 = 0;
+const WALL: i64 = 1;
+const BLOCK: i64 = 2;
+const PADDLE: i64 = 3;
+const BALL: i64 = 4;
+
+// The game's screen, scores are sent to (-1, 0)
+fn arcade_display() -> Display {
+    let mut display = Display::new();
+    display.set_palette(Palette::new(&[
+        (EMPTY, ' '),
+        (WALL, '|'),
+        (BLOCK, '#'),
+        (PADDLE, '-'),
+        (BALL, '0'),
+    ]));
+    display.add_channel("score", -1, 0);
+    display
 }
 
-#[derive(PartialEq, Clone)]
-enum Tile {
-    Empty,
-    Wall,
-    Block,
-    Paddle,
-    Ball,
+// Plays the game without anyone at the joystick, following the ball and paddle as they're drawn
+#[derive(Default)]
+struct Autopilot {
+    ball: Option<(i64, i64)>,
+    velocity: (i64, i64), // How far the ball moved last frame
+    paddle: Option<(i64, i64)>,
 }
 
-impl From<i64> for Tile {
-    fn from(src: i64) -> Self {
-        match src {
-            0 => Tile::Empty,
-            1 => Tile::Wall,
-            2 => Tile::Block,
-            3 => Tile::Paddle,
-            4 => Tile::Ball,
-            _ => panic!("{} not a valid tile", src),
+impl Autopilot {
+    fn watch(&mut self, update: Update) {
+        match update {
+            Update::Pixel {
+                x, y, new: BALL, ..
+            } => {
+                if let Some((bx, by)) = self.ball {
+                    self.velocity = (x - bx, y - by);
+                }
+                self.ball = Some((x, y));
+            }
+            Update::Pixel {
+                x, y, new: PADDLE, ..
+            } => self.paddle = Some((x, y)),
+            _ => (),
         }
     }
-}
 
-impl fmt::Display for Tile {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use Tile::*;
-        let out = match self {
-            Empty => " ",
-            Wall => "|",
-            Block => "#",
-            Paddle => "-",
-            Ball => "0",
+    // Where the ball will be when it gets down to the paddle, bouncing off the side walls on the way.
+    //  Blocks can still knock it off course, so this is worked out again every frame
+    fn landing(&self, walls: Option<Rect>) -> Option<i64> {
+        let ((bx, by), (_, py)) = (self.ball?, self.paddle?);
+        let (vx, vy) = self.velocity;
+        if vy <= 0 || by >= py {
+            return None;
+        }
+        let walls = walls?;
+        // Open columns are min_x + 1 to max_x - 1, fold the straight line path back into them
+        let (left, span) = (walls.min_x + 1, walls.width() as i64 - 3);
+        if span <= 0 {
+            return None;
+        }
+        let x = (bx - left + vx * (py - 1 - by)).rem_euclid(2 * span);
+        Some(left + if x > span { 2 * span - x } else { x })
+    }
+
+    // Move the paddle towards where the ball is going, or just under it when it's going up
+    fn joystick(&self, walls: Option<Rect>) -> i64 {
+        let target = match (self.landing(walls), self.ball) {
+            (Some(x), _) => x,
+            (None, Some((bx, _))) => bx,
+            (None, None) => return 0,
         };
-        write!(f, "{}", out)?;
-        Ok(())
+        match self.paddle {
+            Some((px, _)) => (target - px).signum(),
+            None => 0,
+        }
+    }
+}
+
+// Everything the game program can see and touch, the display is its output and the joystick is its input
+struct Screen {
+    display: Display,
+    deferred_clear: Option<(i64, i64)>, // Ball position to clear once the frame has been rendered
+    pilot: Option<Autopilot>,           // Plays instead of stdin, without rendering anything
+    #[cfg(feature = "tui")]
+    tui: Option<Tui<Terminal>>, // Draws and reads the keyboard instead, the pilot can still play
+}
+
+impl Screen {
+    fn score(&self) -> i64 {
+        self.display.channel("score").unwrap_or(0)
+    }
+}
+
+// There's a bug in the game program that clears the ball position immediately before taking input
+//  So there is special logic in here to defer clearing the ball until after input is taken
+impl OutputSink for Screen {
+    fn push_output(&mut self, val: i64) {
+        let update = self.display.push(val);
+        if let (Some(pilot), Some(u)) = (&mut self.pilot, update) {
+            pilot.watch(u);
+        }
+        if let Some(Update::Pixel {
+            x,
+            y,
+            old: BALL,
+            new: EMPTY,
+        }) = update
+        {
+            // Put the ball back until the frame is rendered
+            self.display.set(x, y, BALL);
+            self.deferred_clear = Some((x, y));
+        }
+    }
+}
+
+// Game asks for joystick input once per frame, render the frame then ask the player
+impl InputSource for Screen {
+    fn next_input(&mut self) -> Option<i64> {
+        #[cfg(feature = "tui")]
+        if let Some(tui) = &mut self.tui {
+            // The stick is left alone unless a key is pressed or the pilot moves it
+            let idle = match &self.pilot {
+                Some(pilot) => pilot.joystick(self.display.bounds()),
+                None => 0,
+            };
+            let joystick = tui.frame(&mut self.display, Some(idle));
+            if let Some((x, y)) = self.deferred_clear.take() {
+                self.display.set(x, y, EMPTY);
+            }
+            return joystick;
+        }
+        if let Some(pilot) = &self.pilot {
+            let joystick = pilot.joystick(self.display.bounds());
+            if let Some((x, y)) = self.deferred_clear.take() {
+                self.display.set(x, y, EMPTY);
+            }
+            return Some(joystick);
+        }
+
+        // Render frame then score
+        print!("{}", self.display);
+        println!(
+            "Score: {}\r\nEnter Input, A|a for left, D|d for right, S|s for stay",
+            self.score()
+        );
+        if let Some((x, y)) = self.deferred_clear.take() {
+            self.display.set(x, y, EMPTY);
+        }
+
+        // Loop until an input byte from stdin makes sense, stop playing if stdin closes
+        let mut buf = [0; 1];
+        loop {
+            match std::io::stdin().read(&mut buf) {
+                Ok(1) => match buf[0] as char {
+                    'a' | 'A' => return Some(-1),
+                    's' | 'S' => return Some(0),
+                    'd' | 'D' => return Some(1),
+                    _c => continue,
+                },
+                Ok(0) => return None,
+                _n => continue,
+            }
+        }
     }
 }
 
 struct Game {
-    frame: Vec<Tile>,
+    screen: Screen,
     cpu: IntcodeComp,
-    score: i64,
-    height: usize,
-    width: usize,
 }
 
 impl Game {
-    pub fn new(width: usize, height: usize, prog: &Vec<i64>) -> Game {
-        // frame buffer is just a list of pixels, modulo index for rows
-        let frame: Vec<Tile> = vec![Tile::Empty; width * height];
-        let cpu = IntcodeComp::new(prog);
-        let score: i64 = 0;
+    pub fn new(prog: &[i64]) -> Game {
         Game {
-            frame,
-            cpu,
-            score,
-            height,
-            width,
+            screen: Screen {
+                display: arcade_display(),
+                deferred_clear: None,
+                pilot: None,
+                #[cfg(feature = "tui")]
+                tui: None,
+            },
+            cpu: IntcodeComp::new(prog),
         }
     }
 
-    // There's a bug in the game program that clears the ball position immediately before taking input
-    //  So there is special logic in here to defer clearing the ball until after input is taken
-    pub fn game_loop(&mut self, _ai: bool) {
-        let mut ball_pos = Coord { x: 0, y: 0 };
-        let mut defer_clear = false;
-        loop {
-            match self.cpu.eval_async() {
-                true => {
-                    // Update framebuf
-                    while self.cpu.output_available() > 3 {
-                        let x = self.cpu.output().unwrap();
-                        let y = self.cpu.output().unwrap();
-                        if x < 0 || y == 0 {
-                            // -1,0 is score update
-                            self.score = self.cpu.output().unwrap();
-                        } else {
-                            // Else coordinates should fit in frame buffer
-                            let id = Tile::from(self.cpu.output().unwrap());
-                            assert!(x < self.width as i64);
-                            assert!(y < self.height as i64);
-
-                            // Track ball position and defer clearing so that ball is rendered
-                            if self.frame[(x as usize) + (y as usize) * self.width] == Tile::Ball
-                                && id == Tile::Empty
-                            {
-                                defer_clear = true;
-                                ball_pos.x = x;
-                                ball_pos.y = y;
-                            } else {
-                                self.frame[(x as usize) + (y as usize) * self.width] = id;
-                            }
-                        }
-                    }
-
-                    // Render framebuf then score
-                    for y in 0..self.height {
-                        for x in 0..self.width {
-                            print!("{}", self.frame[x + y * self.width])
-                        }
-                        println!();
-                    }
-                    println!(
-                        "Score: {}\r\nEnter Input, A|a for left, D|d for right, S|s for stay",
-                        self.score
-                    );
-                    if defer_clear {
-                        self.frame[(ball_pos.x as usize) + (ball_pos.y as usize) * self.width] =
-                            Tile::Empty;
-                        defer_clear = false;
-                    }
-
-                    // Loop until an input byte from stdin makes sense
-                    let mut buf = [0; 1];
-                    let key = loop {
-                        match std::io::stdin().read(&mut buf) {
-                            Ok(1) => match buf[0] as char {
-                                'a' | 'A' => break -1,
-                                's' | 'S' => break 0,
-                                'd' | 'D' => break 1,
-                                _c => continue,
-                            },
-                            _n => continue,
-                        }
-                    };
-
-                    // Feed input to program
-                    self.cpu.input(key);
-                }
-                // Program breaks without asking for input when game is over
-                false => {
-                    break;
-                }
-            }
+    // Program breaks without asking for input when game is over. With ai the autopilot plays and
+    //  nothing is shown until the end
+    pub fn game_loop(&mut self, ai: bool) -> RunState {
+        if ai {
+            self.screen.pilot = Some(Autopilot::default());
         }
+        let state = self.cpu.attach(&mut self.screen);
+        report(&state);
+        state
     }
-}
 
-fn main() {
-    let mut prog = prog_from_file("game.txt");
-    let mut comp = IntcodeComp::new(&prog);
-    comp.run_all();
-
-    // Output is in units of 3, X coord, Y coord, and id for what is at that tile
-    // Count total squares for part 1 check
-    let mut squares = 0;
-    let mut height = 0;
-    let mut width = 0;
-    while comp.output_available() > 3 {
-        let x = comp.output().unwrap();
-        let y = comp.output().unwrap();
-        let id = Tile::from(comp.output().unwrap());
-
-        // Total squares
-        if id == Tile::Block {
-            squares += 1
-        };
+    // Full screen in the terminal, the arrow keys (or a and d) steer. With ai the autopilot plays but
+    //  can still be paused, stepped and overruled
+    #[cfg(feature = "tui")]
+    pub fn play_tui(&mut self, ai: bool) -> std::io::Result<RunState> {
+        let mut tui = Tui::new(Terminal::new()?);
+        tui.bind(Key::Left, -1)
+            .bind(Key::Right, 1)
+            .bind(Key::Down, 0)
+            .bind(Key::Char('a'), -1)
+            .bind(Key::Char('s'), 0)
+            .bind(Key::Char('d'), 1);
+        tui.set_frame_delay(Duration::from_millis(if ai { 20 } else { 150 }));
+        if ai {
+            self.screen.pilot = Some(Autopilot::default());
+        }
+        self.screen.tui = Some(tui);
+        let state = self.cpu.attach(&mut self.screen);
+        // Dropping the tui puts the terminal back
+        let mut tui = self.screen.tui.take().unwrap();
+        tui.finish(&mut self.screen.display, &state)?;
+        Ok(state)
+    }
 
-        // Also keep track of window size for part 2, rather than hardcode window size
-        //  Add one here because these metrics need to be a count of lines, and need to count line 0
-        if width < x + 1 {
-            width = x + 1
-        };
-        if height < y + 1 {
-            height = y + 1
-        };
+    // Play as usual, saving every key pressed and what the game did with it to path
+    pub fn record(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        let (state, trace) = self.cpu.record(&mut self.screen);
+        report(&state);
+        trace.save(path)?;
+        println!("Recorded {} moves to {}", trace.inputs.len(), path);
+        Ok(())
+    }
+
+    // Play a recorded session back without a player, failing if the game does anything different
+    pub fn replay(&mut self, path: &str) -> Result<RunState, Box<dyn Error>> {
+        let trace = Trace::load(path)?;
+        Ok(self.cpu.replay(&trace, &mut self.screen)?)
     }
-    drop(comp);
+}
 
-    println!("Number of squares: {}", squares);
+fn report(state: &RunState) {
+    match state {
+        RunState::Halted => (),
+        RunState::AwaitingInput => println!("Input closed, quitting"),
+        state => println!("Game stopped unexpectedly: {:?}", state),
+    }
+}
 
+// Run the program once without a quarter to draw the screen and count the blocks
+fn survey(prog: &[i64]) -> usize {
+    let mut comp = IntcodeComp::new(prog);
+    let mut display = arcade_display();
+    let state = comp.run_with(&mut VecDeque::new(), &mut display);
+    assert_eq!(state, RunState::Halted, "Game program didn't finish");
+    display.count(BLOCK)
+}
+
+fn free_play(prog: &[i64]) -> Game {
     // Set address 0 to 2 for free play, and reinitialize computer
+    let mut prog = prog.to_vec();
     prog[0] = 2;
-    let mut g = Game::new(
-        (width as usize).try_into().unwrap(),
-        (height as usize).try_into().unwrap(),
-        &prog,
-    );
-
-    // Play the game
-    g.game_loop(false);
+    Game::new(&prog)
+}
+
+// day_13 [--auto | --tui [--auto] | --record FILE | --replay FILE]
+fn main() {
+    let prog = prog_from_file("game.txt");
+    println!("Number of squares: {}", survey(&prog));
+
+    let mut g = free_play(&prog);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match (
+        args.first().map(String::as_str),
+        args.get(1).map(String::as_str),
+    ) {
+        (None, _) => {
+            // Play the game
+            g.game_loop(false);
+            Ok(())
+        }
+        (Some("--auto"), None) => {
+            g.game_loop(true);
+            print!("{}", g.screen.display);
+            println!(
+                "Final score: {}, {} blocks left",
+                g.screen.score(),
+                g.screen.display.count(BLOCK)
+            );
+            Ok(())
+        }
+        #[cfg(feature = "tui")]
+        (Some("--tui"), ai @ (None | Some("--auto"))) => {
+            g.play_tui(ai.is_some()).map_err(|e| e.into()).map(|state| {
+                report(&state);
+                println!("Final score: {}", g.screen.score());
+            })
+        }
+        #[cfg(not(feature = "tui"))]
+        (Some("--tui"), _) => {
+            Err("built without the terminal UI, rebuild with --features tui".into())
+        }
+        (Some("--record"), Some(path)) => g.record(path),
+        (Some("--replay"), Some(path)) => g.replay(path).map(|state| {
+            report(&state);
+            println!("Replay matches, final score {}", g.screen.score());
+        }),
+        _ => Err("usage: day_13 [--auto | --tui [--auto] | --record FILE | --replay FILE]".into()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+#[test]
+fn recorded_session_replays() {
+    let prog = prog_from_file("game.txt");
+    assert_eq!(survey(&prog), 298);
+    // A short game that ends with the ball getting past the paddle
+    let mut g = free_play(&prog);
+    let state = g
+        .replay("session.trace")
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(state, RunState::Halted);
+}
+
+#[test]
+fn autopilot_clears_the_board() {
+    let mut g = free_play(&prog_from_file("game.txt"));
+    assert_eq!(g.game_loop(true), RunState::Halted);
+    assert_eq!(g.screen.display.count(BLOCK), 0);
+    assert_eq!(g.screen.score(), 13956);
+}
+
+#[test]
+fn landing_bounces_off_walls() {
+    let walls = Some(Rect {
+        min_x: 0,
+        min_y: 0,
+        max_x: 39,
+        max_y: 22,
+    });
+    let mut pilot = Autopilot {
+        ball: Some((36, 10)),
+        velocity: (1, 1),
+        paddle: Some((20, 20)),
+    };
+    // 9 rows to fall, 2 across to column 38 beside the wall and 7 back
+    assert_eq!(pilot.landing(walls), Some(31));
+    assert_eq!(pilot.joystick(walls), 1);
+    // On the way up it just follows
+    pilot.velocity = (1, -1);
+    assert_eq!(pilot.landing(walls), None);
+    pilot.ball = Some((3, 10));
+    assert_eq!(pilot.joystick(walls), -1);
 }
@@ -0,0 +1,134 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use intcode::network::{NetState, Network, Topology};
+use intcode::threaded::permutations;
+use intcode::{decode_at, prog_from_file, IntcodeComp, Opcodes};
+
+// Interpreter benchmarks, cargo bench from the intcode directory
+//  Each workload runs with the decode cache on and off, decode itself is compared against the old
+//  digit splitting decoder that allocated on every instruction. The cache only kicks in once a
+//  machine has been running for a while so the short day 2 runs look the same either way
+
+// The decoder as it was, kept only as a baseline
+fn legacy_decode(mem: &[i64], addr: usize) -> Option<(Opcodes, [i64; 3])> {
+    fn decompose(n: i64, digits: &mut Vec<u8>) {
+        digits.push((n % 10) as u8);
+        if n >= 10 {
+            decompose(n / 10, digits)
+        }
+    }
+    let word = *mem.get(addr)?;
+    if word < 0 {
+        return None;
+    }
+    let mut digits: Vec<u8> = Vec::with_capacity(5);
+    decompose(word, &mut digits);
+    let mut it = digits.iter();
+    let mut opcode = *it.next().unwrap() as usize;
+    opcode += match it.next() {
+        Some(&i) => (i * 10) as usize,
+        None => 0,
+    };
+    let mut modes = [0; 3];
+    for m in modes.iter_mut() {
+        *m = it.next().map_or(0, |&d| d as i64);
+    }
+    Some((Opcodes::from_usize(opcode)?, modes))
+}
+
+fn run(prog: &[i64], inputs: &[i64], cache: bool) -> Vec<i64> {
+    let mut comp = IntcodeComp::new(prog);
+    comp.set_decode_cache(cache);
+    for &i in inputs {
+        comp.input(i);
+    }
+    comp.eval_async();
+    let mut out = Vec::new();
+    while let Some(v) = comp.output() {
+        out.push(v);
+    }
+    out
+}
+
+// Day 2 part 2, every noun and verb until the answer turns up
+fn noun_verb(prog: &[i64], cache: bool) -> i64 {
+    for noun in 0..100 {
+        for verb in 0..100 {
+            let mut mem = prog.to_vec();
+            mem[1] = noun;
+            mem[2] = verb;
+            let mut comp = IntcodeComp::new(&mem);
+            comp.set_decode_cache(cache);
+            comp.eval_async();
+            if comp._int_mem()[0] == 19_690_720 {
+                return 100 * noun + verb;
+            }
+        }
+    }
+    0
+}
+
+// Day 7 part 2, 120 feedback loops of 5 machines each on one thread
+fn amplifiers(prog: &[i64], cache: bool) -> i64 {
+    let mut best = 0;
+    for phases in permutations(&[5, 6, 7, 8, 9]) {
+        let nodes = (0..5)
+            .map(|_| {
+                let mut comp = IntcodeComp::new(prog);
+                comp.set_decode_cache(cache);
+                comp
+            })
+            .collect();
+        let mut net = Network::from_nodes(nodes, Topology::ring(5));
+        for (i, &p) in phases.iter().enumerate() {
+            net.send(i, p);
+        }
+        net.send(0, 0);
+        assert_eq!(net.run(), NetState::Halted);
+        best = best.max(net.last_output(4).unwrap());
+    }
+    best
+}
+
+fn decode(c: &mut Criterion) {
+    let prog = prog_from_file("../day_09/BOOST.txt");
+    let mut group = c.benchmark_group("decode");
+    group.bench_function("legacy", |b| {
+        b.iter(|| {
+            for addr in 0..prog.len() {
+                black_box(legacy_decode(black_box(&prog), addr));
+            }
+        })
+    });
+    group.bench_function("current", |b| {
+        b.iter(|| {
+            for addr in 0..prog.len() {
+                let _ = black_box(decode_at(black_box(&prog), addr));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn workloads(c: &mut Criterion) {
+    let boost = prog_from_file("../day_09/BOOST.txt");
+    let gravity = prog_from_file("../day_02/input.txt");
+    let acs = prog_from_file("../day_07/ACS.txt");
+
+    for &(name, cache) in &[("cached", true), ("uncached", false)] {
+        let mut group = c.benchmark_group(name);
+        group.sample_size(20);
+        group.bench_function("day_09_boost", |b| {
+            b.iter(|| assert_eq!(run(&boost, &[2], cache), vec![82760]))
+        });
+        group.bench_function("day_02_noun_verb", |b| {
+            b.iter(|| black_box(noun_verb(&gravity, cache)))
+        });
+        group.bench_function("day_07_feedback", |b| {
+            b.iter(|| assert_eq!(amplifiers(&acs, cache), 1336480))
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, decode, workloads);
+criterion_main!(benches);
@@ -0,0 +1,143 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::io::{InputSource, OutputSink};
+use crate::{IntcodeComp, RunState};
+
+// Output of a program that speaks ASCII, anything outside 0..=127 is a numeric result (usually the answer)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsciiItem {
+    Text(String),
+    Number(i64),
+}
+
+impl fmt::Display for AsciiItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsciiItem::Text(t) => write!(f, "{}", t),
+            AsciiItem::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+fn is_ascii(val: i64) -> bool {
+    (0..=127).contains(&val)
+}
+
+impl IntcodeComp {
+    // Queue every byte of s as input
+    pub fn input_str(&mut self, s: &str) {
+        for b in s.bytes() {
+            self.input(b as i64);
+        }
+    }
+
+    // Queue s followed by a newline, the usual way to answer a prompt
+    pub fn input_line(&mut self, s: &str) {
+        self.input_str(s);
+        self.input(b'\n' as i64);
+    }
+
+    // Pop one complete line of output (without its newline), or a numeric result if that is what comes next
+    //  A numeric result also ends a line, the text before it comes out first and the number on the next call.
+    //  Text that isn't terminated either way yet is left queued and None is returned
+    pub fn read_line(&mut self) -> Option<AsciiItem> {
+        let queue = self.output_queue();
+        match queue.front() {
+            Some(&v) if !is_ascii(v) => {
+                return self.output().map(AsciiItem::Number);
+            }
+            None => return None,
+            _ => (),
+        }
+        let end = queue
+            .iter()
+            .position(|&v| v == b'\n' as i64 || !is_ascii(v))?;
+        let newline = queue[end] == b'\n' as i64;
+        let line: String = (0..end)
+            .map(|_| self.output().unwrap() as u8 as char)
+            .collect();
+        if newline {
+            self.output();
+        }
+        Some(AsciiItem::Text(line))
+    }
+
+    // Pop everything queued, consecutive characters are merged into one Text item
+    pub fn drain_string(&mut self) -> Vec<AsciiItem> {
+        let mut items = Vec::new();
+        let mut text = String::new();
+        while let Some(v) = self.output() {
+            if is_ascii(v) {
+                text.push(v as u8 as char);
+            } else {
+                if !text.is_empty() {
+                    items.push(AsciiItem::Text(std::mem::take(&mut text)));
+                }
+                items.push(AsciiItem::Number(v));
+            }
+        }
+        if !text.is_empty() {
+            items.push(AsciiItem::Text(text));
+        }
+        items
+    }
+}
+
+// Connects a program to the terminal, output characters are printed as they arrive and input is read
+//  from stdin a line at a time whenever the program asks for a byte
+pub struct AsciiTerminal {
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl AsciiTerminal {
+    pub fn new() -> AsciiTerminal {
+        AsciiTerminal {
+            line: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Default for AsciiTerminal {
+    fn default() -> Self {
+        AsciiTerminal::new()
+    }
+}
+
+impl InputSource for AsciiTerminal {
+    fn next_input(&mut self) -> Option<i64> {
+        if self.pos >= self.line.len() {
+            io::stdout().flush().ok()?;
+            let mut line = String::new();
+            match io::stdin().lock().read_line(&mut line) {
+                Ok(0) | Err(_) => return None, // EOF
+                Ok(_) => (),
+            }
+            // Normalise line endings, programs only understand \n
+            let line = line.trim_end_matches(['\r', '\n']);
+            self.line = format!("{}\n", line).into_bytes();
+            self.pos = 0;
+        }
+        self.pos += 1;
+        Some(self.line[self.pos - 1] as i64)
+    }
+}
+
+impl OutputSink for AsciiTerminal {
+    fn push_output(&mut self, val: i64) {
+        if is_ascii(val) {
+            print!("{}", val as u8 as char);
+        } else {
+            println!("\n[numeric result: {}]", val);
+        }
+    }
+}
+
+// Play a text adventure, returns once the program halts, faults or stdin is closed
+pub fn run_ascii(comp: &mut IntcodeComp) -> RunState {
+    let state = comp.attach(&mut AsciiTerminal::new());
+    io::stdout().flush().ok();
+    state
+}
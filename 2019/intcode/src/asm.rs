@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::decode::{AddressMode, Opcodes};
+
+// Assembler for the listing syntax produced by the disassembler
+//  ; comment to end of line
+//  label:                  names the address of whatever follows
+//  ADD [12], #5, rb+3      positional, immediate and relative operands, mnemonics are case insensitive
+//  JNZ #1, #loop           any number can be replaced by a label, optionally with an offset (loop+2)
+//  .data 0, -1, end        raw words
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    UnknownDirective(String),
+    OperandCount { expected: usize, found: usize },
+    BadOperand(String),
+    ImmediateDest(usize),
+    BadLabel(String),
+    DuplicateLabel(String),
+    UnknownLabel(String),
+}
+
+// Assembly failure, line is 1-based
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use AsmErrorKind::*;
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            UnknownMnemonic(m) => write!(f, "unknown mnemonic {}", m),
+            UnknownDirective(d) => write!(f, "unknown directive {}", d),
+            OperandCount { expected, found } => {
+                write!(f, "expected {} operands, found {}", expected, found)
+            }
+            BadOperand(o) => write!(f, "can't parse operand {}", o),
+            ImmediateDest(i) => write!(f, "operand {} is a destination and can't be immediate", i),
+            BadLabel(l) => write!(f, "{} is not a valid label name", l),
+            DuplicateLabel(l) => write!(f, "label {} defined twice", l),
+            UnknownLabel(l) => write!(f, "label {} is not defined", l),
+        }
+    }
+}
+
+impl Error for AsmError {}
+
+// A number or a label reference with an offset, labels are only resolved once every address is known
+#[derive(Debug)]
+enum Value {
+    Num(i64),
+    Label(String, i64),
+}
+
+// What one line of source puts in memory, words are resolved in the second pass
+enum Item {
+    Inst(Opcodes, Vec<(AddressMode, Value)>),
+    Data(Vec<Value>),
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+// Parse "12", "-3", "loop", "loop+2" or "loop-1"
+fn parse_value(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Ok(n) = text.parse::<i64>() {
+        return Some(Value::Num(n));
+    }
+    let (name, offset) = match text.find(['+', '-']) {
+        Some(i) => (
+            text[..i].trim(),
+            text[i..].replace(' ', "").parse::<i64>().ok()?,
+        ),
+        None => (text, 0),
+    };
+    if is_label(name) {
+        Some(Value::Label(name.to_string(), offset))
+    } else {
+        None
+    }
+}
+
+fn parse_operand(text: &str) -> Option<(AddressMode, Value)> {
+    let text = text.trim();
+    if let Some(rest) = text.strip_prefix('#') {
+        Some((AddressMode::Immediate, parse_value(rest)?))
+    } else if text.starts_with('[') && text.ends_with(']') {
+        Some((
+            AddressMode::Positional,
+            parse_value(&text[1..text.len() - 1])?,
+        ))
+    } else if let Some(rest) = text.strip_prefix("rb") {
+        // rb+3, rb-3, rb + label or just rb for an offset of 0
+        let rest = rest.trim();
+        let value = if rest.is_empty() {
+            Value::Num(0)
+        } else if let Some(r) = rest.strip_prefix('+') {
+            parse_value(r)?
+        } else if rest.starts_with('-') {
+            match parse_value(rest)? {
+                Value::Num(n) => Value::Num(n),
+                Value::Label(..) => return None, // negated labels aren't supported
+            }
+        } else {
+            return None;
+        };
+        Some((AddressMode::Relative, value))
+    } else {
+        None
+    }
+}
+
+// Split on commas, ignoring an empty operand list
+fn split_operands(text: &str) -> Vec<&str> {
+    if text.trim().is_empty() {
+        Vec::new()
+    } else {
+        text.split(',').collect()
+    }
+}
+
+// Turn assembly source into a program image ready for IntcodeComp::new
+pub fn assemble(src: &str) -> Result<Vec<i64>, AsmError> {
+    // First pass, parse every line and assign addresses to labels
+    let mut labels: HashMap<String, i64> = HashMap::new();
+    let mut items: Vec<(usize, Item)> = Vec::new();
+    let mut addr: i64 = 0;
+    for (num, line) in src.lines().enumerate() {
+        let num = num + 1;
+        let err = |kind| AsmError { line: num, kind };
+        let mut text = match line.find(';') {
+            Some(i) => &line[..i],
+            None => line,
+        }
+        .trim();
+
+        // Any number of labels may prefix a line
+        while let Some(i) = text.find(':') {
+            let name = text[..i].trim();
+            if !is_label(name) {
+                return Err(err(AsmErrorKind::BadLabel(name.to_string())));
+            }
+            if labels.insert(name.to_string(), addr).is_some() {
+                return Err(err(AsmErrorKind::DuplicateLabel(name.to_string())));
+            }
+            text = text[i + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (head, rest) = match text.find(char::is_whitespace) {
+            Some(i) => (&text[..i], &text[i..]),
+            None => (text, ""),
+        };
+        let item = if head.starts_with('.') {
+            if !head.eq_ignore_ascii_case(".data") {
+                return Err(err(AsmErrorKind::UnknownDirective(head.to_string())));
+            }
+            let mut words = Vec::new();
+            for word in split_operands(rest) {
+                match parse_value(word) {
+                    Some(v) => words.push(v),
+                    None => return Err(err(AsmErrorKind::BadOperand(word.trim().to_string()))),
+                }
+            }
+            addr += words.len() as i64;
+            Item::Data(words)
+        } else {
+            let op = match Opcodes::from_mnemonic(head) {
+                Some(op) => op,
+                None => return Err(err(AsmErrorKind::UnknownMnemonic(head.to_string()))),
+            };
+            let texts = split_operands(rest);
+            if texts.len() != op.arity() {
+                return Err(err(AsmErrorKind::OperandCount {
+                    expected: op.arity(),
+                    found: texts.len(),
+                }));
+            }
+            let mut operands = Vec::with_capacity(texts.len());
+            for (i, text) in texts.iter().enumerate() {
+                match parse_operand(text) {
+                    Some((AddressMode::Immediate, _)) if op.dest() == Some(i + 1) => {
+                        return Err(err(AsmErrorKind::ImmediateDest(i + 1)))
+                    }
+                    Some(operand) => operands.push(operand),
+                    None => return Err(err(AsmErrorKind::BadOperand(text.trim().to_string()))),
+                }
+            }
+            addr += 1 + op.arity() as i64;
+            Item::Inst(op, operands)
+        };
+        items.push((num, item));
+    }
+
+    // Second pass, emit words now that every label has an address
+    let mut prog = Vec::with_capacity(addr as usize);
+    for (num, item) in items {
+        let resolve = |v: &Value| match v {
+            Value::Num(n) => Ok(*n),
+            Value::Label(name, offset) => match labels.get(name) {
+                Some(a) => Ok(a + offset),
+                None => Err(AsmError {
+                    line: num,
+                    kind: AsmErrorKind::UnknownLabel(name.clone()),
+                }),
+            },
+        };
+        match item {
+            Item::Inst(op, operands) => {
+                let modes: Vec<AddressMode> = operands.iter().map(|(m, _)| *m).collect();
+                prog.push(op.encode(&modes));
+                for (_, v) in &operands {
+                    prog.push(resolve(v)?);
+                }
+            }
+            Item::Data(words) => {
+                for v in &words {
+                    prog.push(resolve(v)?);
+                }
+            }
+        }
+    }
+    Ok(prog)
+}
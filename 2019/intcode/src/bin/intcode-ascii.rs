@@ -0,0 +1,21 @@
+use intcode::ascii::run_ascii;
+use intcode::{prog_from_file, IntcodeComp, RunState};
+
+// Run an ASCII speaking intcode program interactively, usage: intcode-ascii <program file>
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(p) => p,
+        None => {
+            eprintln!("usage: intcode-ascii <program file>");
+            std::process::exit(2);
+        }
+    };
+    let mut comp = IntcodeComp::new(&prog_from_file(&path));
+    match run_ascii(&mut comp) {
+        RunState::Halted | RunState::AwaitingInput => (),
+        state => {
+            eprintln!("program stopped: {:?}", state);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use intcode::cfg::Cfg;
+use intcode::prog_from_file;
+
+// Print the control flow graph of an intcode program as Graphviz DOT, usage: intcode-cfg <program file>
+//  e.g. intcode-cfg painter.txt | dot -Tsvg > painter.svg
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(p) => p,
+        None => {
+            eprintln!("usage: intcode-cfg <program file>");
+            std::process::exit(2);
+        }
+    };
+    print!("{}", Cfg::build(&prog_from_file(&path)).to_dot());
+}
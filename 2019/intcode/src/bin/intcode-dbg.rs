@@ -0,0 +1,218 @@
+use intcode::{prog_from_file, IntcodeComp, RunState, StopOn};
+use std::io::{self, BufRead, Write};
+
+// Interactive debugger for intcode programs, usage: intcode-dbg <program file>
+const HELP: &str = "\
+s [n]        step n instructions (default 1)
+u [n]        step back n instructions (default 1)
+U <addr>     step back to the last write of addr
+c            continue until halt, input starvation, a breakpoint or a watchpoint
+b <addr>     toggle a breakpoint
+w <addr>     toggle a watchpoint on memory writes
+i <v> [v..]  queue input values
+o            drain and print the output queue
+r            show pc, relative base and the I/O queues
+x <addr> [n] examine n memory cells (default 8, at most 4096)
+l [addr] [n] disassemble n instructions from addr (default pc, 8)
+p            start profiling, or stop and print the profile
+j [n]        start recording the last n instructions for stepping back (default 1000000), or stop
+h            this help
+q            quit";
+
+// Most cells x shows at once, and how many go on a line
+const MAX_EXAMINE: usize = 4096;
+const ROW: usize = 8;
+
+fn parse_num<T: std::str::FromStr>(arg: Option<&str>, default: T) -> Result<T, String> {
+    match arg {
+        Some(a) => a.parse().map_err(|_| format!("bad number: {}", a)),
+        None => Ok(default),
+    }
+}
+
+fn show_registers(comp: &IntcodeComp) {
+    println!(
+        "pc={} rb={} in={:?} out={:?}",
+        comp.program_counter(),
+        comp.rel_base(),
+        comp.input_queue(),
+        comp.output_queue()
+    );
+}
+
+fn show_state(comp: &IntcodeComp, state: &RunState) {
+    match state {
+        RunState::Breakpoint(addr) => println!("breakpoint at {}", addr),
+        RunState::Watchpoint { addr, old, new } => {
+            println!("watchpoint: [{}] {} -> {}", addr, old, new)
+        }
+        RunState::Error(e) => println!("fault: {}", e),
+        RunState::Running => (),
+        s => println!("{:?}", s),
+    }
+    list(comp, comp.program_counter(), 1);
+}
+
+fn list(comp: &IntcodeComp, mut addr: usize, count: usize) {
+    for _ in 0..count {
+        let marker = if addr == comp.program_counter() {
+            "=>"
+        } else {
+            "  "
+        };
+        let len = match comp.decode(addr) {
+            Ok(inst) => {
+                println!("{} {:>6}: {}", marker, addr, inst);
+                inst.width()
+            }
+            Err(_) => {
+                println!("{} {:>6}: .data {}", marker, addr, comp.read_mem(addr));
+                1
+            }
+        };
+        // Stop at the top of the address space rather than wrapping
+        addr = match addr.checked_add(len) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+}
+
+fn command(comp: &mut IntcodeComp, line: &str) -> Result<bool, String> {
+    let mut args = line.split_whitespace();
+    let cmd = match args.next() {
+        Some(c) => c,
+        None => return Ok(true),
+    };
+    match cmd {
+        "s" => {
+            let n = parse_num(args.next(), 1)?;
+            let state = comp.run_until(StopOn::Instructions(n));
+            show_state(comp, &state);
+        }
+        "u" => {
+            let n = parse_num(args.next(), 1)?;
+            if comp.step_back(n) < n {
+                println!("start of journal, j to start recording");
+            }
+            show_state(comp, &RunState::Running);
+        }
+        "U" => {
+            let addr: usize = match args.next() {
+                Some(a) => parse_num(Some(a), 0)?,
+                None => return Err("U needs an address".to_string()),
+            };
+            match comp.back_to_write(addr) {
+                Some(_) => show_state(comp, &RunState::Running),
+                None => println!("no write to {} in the journal", addr),
+            }
+        }
+        "c" => {
+            let state = comp.eval_async();
+            show_state(comp, &state);
+        }
+        "b" => {
+            let addr = parse_num(args.next(), comp.program_counter())?;
+            if !comp.remove_breakpoint(addr) {
+                comp.add_breakpoint(addr);
+            }
+            println!("breakpoints: {:?}", comp.breakpoints());
+        }
+        "w" => {
+            let addr: usize = match args.next() {
+                Some(a) => parse_num(Some(a), 0)?,
+                None => return Err("w needs an address".to_string()),
+            };
+            if !comp.remove_watchpoint(addr) {
+                comp.add_watchpoint(addr);
+            }
+            println!("watchpoints: {:?}", comp.watchpoints());
+        }
+        "i" => {
+            for a in args {
+                comp.input(parse_num(Some(a), 0)?);
+            }
+            show_registers(comp);
+        }
+        "o" => {
+            let mut out = Vec::new();
+            while let Some(val) = comp.output() {
+                out.push(val);
+            }
+            println!("{:?}", out);
+        }
+        "r" => show_registers(comp),
+        "x" => {
+            let addr: usize = parse_num(args.next(), comp.program_counter())?;
+            let count: usize = parse_num(args.next(), 8)?;
+            if count > MAX_EXAMINE {
+                return Err(format!(
+                    "too many cells: {}, at most {}",
+                    count, MAX_EXAMINE
+                ));
+            }
+            let end = match addr.checked_add(count) {
+                Some(end) => end,
+                None => return Err(format!("bad range: {} cells from {}", count, addr)),
+            };
+            for row in (addr..end).step_by(ROW) {
+                let cells: Vec<i64> = (row..row.saturating_add(ROW).min(end))
+                    .map(|a| comp.read_mem(a))
+                    .collect();
+                println!("{:>6}: {:?}", row, cells);
+            }
+        }
+        "l" => {
+            let addr = parse_num(args.next(), comp.program_counter())?;
+            list(comp, addr, parse_num(args.next(), 8)?);
+        }
+        "p" => match comp.disable_profiling() {
+            Some(profile) => print!("{}", profile),
+            None => {
+                comp.enable_profiling();
+                println!("profiling");
+            }
+        },
+        "j" => {
+            if comp.journaling() {
+                comp.disable_journal();
+                println!("journal off");
+            } else {
+                comp.enable_journal(parse_num(args.next(), 1_000_000)?);
+                println!("journal on");
+            }
+        }
+        "h" => println!("{}", HELP),
+        "q" => return Ok(false),
+        c => return Err(format!("unknown command {}, h for help", c)),
+    }
+    Ok(true)
+}
+
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(p) => p,
+        None => {
+            eprintln!("usage: intcode-dbg <program file>");
+            std::process::exit(2);
+        }
+    };
+    let mut comp = IntcodeComp::new(&prog_from_file(&path));
+    show_state(&comp, &RunState::Running);
+
+    let stdin = io::stdin();
+    loop {
+        print!("(dbg) ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break, // EOF
+            Ok(_) => (),
+        }
+        match command(&mut comp, &line) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(e) => println!("{}", e),
+        }
+    }
+}
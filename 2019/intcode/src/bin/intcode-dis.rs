@@ -0,0 +1,14 @@
+use intcode::disasm::disassemble;
+use intcode::prog_from_file;
+
+// Print an annotated listing of an intcode program, usage: intcode-dis <program file>
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(p) => p,
+        None => {
+            eprintln!("usage: intcode-dis <program file>");
+            std::process::exit(2);
+        }
+    };
+    print!("{}", disassemble(&prog_from_file(&path)));
+}
@@ -0,0 +1,253 @@
+use intcode::ascii::AsciiItem;
+use intcode::{load, IntcodeComp, RunState};
+use std::fs;
+use std::process::exit;
+
+const USAGE: &str = "usage: intcode <program file, - for stdin> [options] [inputs...]
+  inputs are queued in the order given
+  <number>              one input value
+  -t, --text TEXT       TEXT as ASCII input followed by a newline
+  -f, --inputs FILE     every value in FILE, separated by commas or whitespace (- for stdin)
+  -s, --set ADDR=VALUE  patch a memory cell before running, can be repeated
+  -p, --peek ADDR       print a memory cell after the run, can be repeated
+  -m, --memory WORDS    memory limit in words, patches and the program's own writes count
+  -o, --output FORMAT   list (one value per line, the default), ascii or json
+exit status: 0 halted, 1 faulted, 2 bad arguments or program, 3 waiting for more input";
+
+// Exit statuses, 2 also covers anything wrong with the command line
+const FAULTED: i32 = 1;
+const BAD_ARGS: i32 = 2;
+const STARVED: i32 = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    List,
+    Ascii,
+    Json,
+}
+
+struct Options {
+    path: String,
+    inputs: Vec<i64>,
+    patches: Vec<(usize, i64)>,
+    peeks: Vec<usize>,
+    memory: Option<usize>,
+    format: Format,
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("intcode: {}\n{}", msg, USAGE);
+    exit(BAD_ARGS);
+}
+
+fn parse_num(s: &str) -> i64 {
+    s.trim()
+        .parse()
+        .unwrap_or_else(|_| fail(&format!("{} is not a number", s)))
+}
+
+fn parse_addr(s: &str) -> usize {
+    s.trim()
+        .parse()
+        .unwrap_or_else(|_| fail(&format!("{} is not an address", s)))
+}
+
+// Values from an input file, one line at a time so a bad one can be pointed at
+fn read_inputs(path: &str, inputs: &mut Vec<i64>) {
+    let text = if path == "-" {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        fs::read_to_string(path)
+    }
+    .unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+    for (num, line) in text.lines().enumerate() {
+        for val in line.split([',', ' ', '\t']).filter(|v| !v.is_empty()) {
+            match val.parse() {
+                Ok(v) => inputs.push(v),
+                Err(_) => fail(&format!(
+                    "{}: line {}: {} is not a number",
+                    path,
+                    num + 1,
+                    val
+                )),
+            }
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Options {
+    let mut opts = Options {
+        path: String::new(),
+        inputs: Vec::new(),
+        patches: Vec::new(),
+        peeks: Vec::new(),
+        memory: None,
+        format: Format::List,
+    };
+    let mut args = args.iter();
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            }
+            "-t" | "--text" => {
+                opts.inputs.extend(value().bytes().map(i64::from));
+                opts.inputs.push(i64::from(b'\n'));
+            }
+            "-f" | "--inputs" => read_inputs(value(), &mut opts.inputs),
+            "-s" | "--set" => {
+                let patch = value();
+                let (addr, val) = patch
+                    .split_once('=')
+                    .unwrap_or_else(|| fail(&format!("{} is not ADDR=VALUE", patch)));
+                opts.patches.push((parse_addr(addr), parse_num(val)));
+            }
+            "-p" | "--peek" => opts.peeks.push(parse_addr(value())),
+            "-m" | "--memory" => opts.memory = Some(parse_addr(value())),
+            "-o" | "--output" => {
+                opts.format = match value().as_str() {
+                    "list" => Format::List,
+                    "ascii" => Format::Ascii,
+                    "json" => Format::Json,
+                    f => fail(&format!("unknown output format {}", f)),
+                }
+            }
+            // The first thing that isn't an option is the program, negative numbers aren't options
+            a if path.is_none() && (a == "-" || !a.starts_with('-')) => path = Some(a.to_string()),
+            a if path.is_some() && a.parse::<i64>().is_ok() => opts.inputs.push(parse_num(a)),
+            a => fail(&format!("unexpected argument {}", a)),
+        }
+    }
+    opts.path = path.unwrap_or_else(|| fail("no program given"));
+    opts
+}
+
+// Just enough JSON escaping for error messages
+fn json_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let opts = parse_args(&args);
+
+    let loaded = if opts.path == "-" {
+        load::from_stdin()
+    } else {
+        load::from_file(&opts.path)
+    };
+    let prog = loaded.unwrap_or_else(|e| {
+        eprintln!("intcode: {}: {}", opts.path, e);
+        exit(BAD_ARGS);
+    });
+
+    let mut comp = IntcodeComp::new(&prog);
+    if let Some(words) = opts.memory {
+        comp.set_memory_limit(words);
+    }
+    // Far patches get a page of their own rather than stretching the program out to reach them
+    for &(addr, val) in &opts.patches {
+        if comp.write_mem(addr, val).is_err() {
+            eprintln!(
+                "intcode: can't set address {}, it's past the memory limit of {} words",
+                addr,
+                comp.memory_limit()
+            );
+            exit(BAD_ARGS);
+        }
+    }
+    for &i in &opts.inputs {
+        comp.input(i);
+    }
+    let state = comp.eval_async();
+    let (status, error) = match &state {
+        RunState::Halted => (0, None),
+        RunState::AwaitingInput => (STARVED, None),
+        RunState::Error(e) => (FAULTED, Some(e.to_string())),
+        // Nothing that could stop it any other way is switched on
+        s => (FAULTED, Some(format!("stopped unexpectedly: {:?}", s))),
+    };
+
+    let peeks: Vec<(usize, i64)> = opts.peeks.iter().map(|&a| (a, comp.read_mem(a))).collect();
+    match opts.format {
+        Format::List => {
+            while let Some(v) = comp.output() {
+                println!("{}", v);
+            }
+        }
+        Format::Ascii => {
+            // Numbers get a line of their own
+            let mut at_line_start = true;
+            for item in comp.drain_string() {
+                match item {
+                    AsciiItem::Text(t) => {
+                        print!("{}", t);
+                        at_line_start = t.ends_with('\n');
+                    }
+                    AsciiItem::Number(n) => {
+                        if !at_line_start {
+                            println!();
+                        }
+                        println!("{}", n);
+                        at_line_start = true;
+                    }
+                }
+            }
+            if !at_line_start {
+                println!();
+            }
+        }
+        Format::Json => {
+            let outputs: Vec<String> = std::iter::from_fn(|| comp.output())
+                .map(|v| v.to_string())
+                .collect();
+            let state = match status {
+                0 => "halted",
+                STARVED => "awaiting_input",
+                _ => "error",
+            };
+            print!(
+                "{{\"state\":\"{}\",\"outputs\":[{}]",
+                state,
+                outputs.join(",")
+            );
+            if !peeks.is_empty() {
+                let cells: Vec<String> = peeks
+                    .iter()
+                    .map(|(a, v)| format!("\"{}\":{}", a, v))
+                    .collect();
+                print!(",\"memory\":{{{}}}", cells.join(","));
+            }
+            if let Some(e) = &error {
+                print!(",\"error\":{}", json_str(e));
+            }
+            println!("}}");
+            exit(status);
+        }
+    }
+    for (addr, val) in peeks {
+        println!("[{}] = {}", addr, val);
+    }
+    match (status, error) {
+        (STARVED, _) => eprintln!("intcode: program is waiting for more input"),
+        (_, Some(e)) => eprintln!("intcode: {}", e),
+        _ => (),
+    }
+    exit(status);
+}
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use crate::{IntcodeComp, Word};
+
+// Instructions between looks at the clock, Instant::now costs more than an instruction does
+const CLOCK_EVERY: u32 = 4096;
+// States remembered between writes, a loop that only moves the relative base would fill the set forever
+const MAX_SEEN: usize = 1 << 16;
+
+// Which limit a run hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    Instructions,
+    WallClock,
+}
+
+// Spots a machine revisiting a state it has been in before. Between two memory changing writes (or
+//  inputs) memory is fixed, so landing on the same pc with the same relative base twice in that window
+//  means the same instructions will run again, forever. Every cycle has a jump back in it, so only
+//  states right after the program counter moved backwards are recorded
+#[derive(Clone, Default)]
+struct CycleDetector {
+    epoch: u64,
+    seen: HashSet<(usize, i64)>,
+}
+
+impl CycleDetector {
+    fn revisited(&mut self, pc: usize, rel_base: i64, epoch: u64) -> bool {
+        if epoch != self.epoch || self.seen.len() >= MAX_SEEN {
+            self.epoch = epoch;
+            self.seen.clear();
+        }
+        !self.seen.insert((pc, rel_base))
+    }
+}
+
+// Everything that can stop a runaway machine, only allocated once one of them is switched on
+#[derive(Clone, Default)]
+pub(crate) struct Limits {
+    instructions: Option<u64>, // Left to execute
+    deadline: Option<Instant>,
+    clock: u32, // Instructions until the next look at the clock
+    cycles: Option<CycleDetector>,
+}
+
+impl Limits {
+    // Checked before each instruction, None means it can go ahead
+    pub fn exhausted(&mut self) -> Option<Budget> {
+        if self.instructions == Some(0) {
+            return Some(Budget::Instructions);
+        }
+        if let Some(deadline) = self.deadline {
+            if self.clock == 0 {
+                self.clock = CLOCK_EVERY;
+                if Instant::now() >= deadline {
+                    return Some(Budget::WallClock);
+                }
+            }
+            self.clock -= 1;
+        }
+        None
+    }
+
+    // An instruction completed
+    pub fn charge(&mut self) {
+        if let Some(n) = &mut self.instructions {
+            *n -= 1;
+        }
+    }
+
+    pub fn cycling(&mut self, pc: usize, rel_base: i64, epoch: u64) -> bool {
+        match &mut self.cycles {
+            Some(c) => c.revisited(pc, rel_base, epoch),
+            None => false,
+        }
+    }
+
+    fn unused(&self) -> bool {
+        self.instructions.is_none() && self.deadline.is_none() && self.cycles.is_none()
+    }
+}
+
+// All of these are off by default, a machine with none of them set runs until it stops by itself
+impl<W: Word> IntcodeComp<W> {
+    // Allow this many more instructions across all later runs, None removes the limit
+    //  Running out stops with RunState::BudgetExhausted before the next instruction, raising the
+    //  budget lets the machine carry on where it left off
+    pub fn set_instruction_budget(&mut self, instructions: Option<u64>) {
+        self.limits_mut().instructions = instructions;
+        self.drop_unused_limits();
+    }
+
+    // Instructions left to execute, None if unlimited
+    pub fn instruction_budget(&self) -> Option<u64> {
+        self.limits.as_ref().and_then(|l| l.instructions)
+    }
+
+    // Stop running once this much time has passed from now, None removes the limit
+    //  The clock is only read every few thousand instructions so runs overshoot a little
+    pub fn set_time_budget(&mut self, time: Option<Duration>) {
+        let limits = self.limits_mut();
+        limits.deadline = time.map(|t| Instant::now() + t);
+        limits.clock = 0;
+        self.drop_unused_limits();
+    }
+
+    // Stop with RunState::Cycling when the machine provably loops forever without input. Loops that
+    //  keep writing new values to memory (counters) can't be caught this way, use a budget for those
+    pub fn set_loop_detection(&mut self, enabled: bool) {
+        self.limits_mut().cycles = if enabled {
+            Some(CycleDetector::default())
+        } else {
+            None
+        };
+        self.drop_unused_limits();
+    }
+
+    fn limits_mut(&mut self) -> &mut Limits {
+        self.limits.get_or_insert_with(Box::default)
+    }
+
+    fn drop_unused_limits(&mut self) {
+        if self.limits.as_ref().is_some_and(|l| l.unused()) {
+            self.limits = None;
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write;
+
+use crate::decode::{decode_at, AddressMode, Instruction, Opcodes};
+use crate::IntcodeError;
+
+// Static control flow analysis. Code is found by following execution from address 0 rather than by
+//  sweeping the image, so data never gets mistaken for instructions. Jumps through memory can't be
+//  followed, they end up as unresolved edges. The usual call idiom (copy a constant return address
+//  somewhere, then jump) is recognised far enough that code after a call is still found
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    // Execution runs off the end of the block into the next one
+    Fallthrough,
+    // A conditional jump with an immediate target
+    Taken,
+    // A jump whose target comes from memory, to is None
+    Unresolved,
+}
+
+// Flow between blocks, from and to are block start addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: Option<usize>,
+    pub kind: EdgeKind,
+}
+
+// Straight line code, only the last instruction can jump and only the first can be jumped to
+//  A block with a fault has no instructions, it marks somewhere execution can reach that doesn't decode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    pub insts: Vec<Instruction>,
+    pub fault: Option<IntcodeError>,
+}
+
+impl Block {
+    // First address after the block
+    pub fn end(&self) -> usize {
+        match self.insts.last() {
+            Some(inst) => inst.addr + inst.width(),
+            None => self.start,
+        }
+    }
+}
+
+// An instruction that writes (through a fixed address) into a word that is also executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfWrite {
+    // Address of the writing instruction
+    pub pc: usize,
+    // Word being written
+    pub addr: usize,
+    // Start of the instruction that word belongs to
+    pub target: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, Block>,
+    pub edges: Vec<Edge>,
+    pub self_writes: Vec<SelfWrite>,
+}
+
+// What an instruction can do next
+struct Flow {
+    fallthrough: bool,
+    target: Option<Option<usize>>, // None: doesn't jump, Some(None): jumps somewhere unknown
+}
+
+fn flow(inst: &Instruction) -> Flow {
+    use Opcodes::*;
+    match inst.opcode {
+        Halt => Flow {
+            fallthrough: false,
+            target: None,
+        },
+        Jnz | Jz => {
+            // An immediate condition makes the jump unconditional (or a no-op)
+            let (taken, not_taken) = match inst.modes[0] {
+                AddressMode::Immediate => {
+                    let jumps = (inst.params[0] != 0) == (inst.opcode == Jnz);
+                    (jumps, !jumps)
+                }
+                _ => (true, true),
+            };
+            let target = match inst.modes[1] {
+                AddressMode::Immediate if inst.params[1] < 0 => None, // Faults at run time, goes nowhere
+                AddressMode::Immediate => Some(Some(inst.params[1] as usize)),
+                _ => Some(None),
+            };
+            Flow {
+                fallthrough: not_taken,
+                target: if taken { target } else { None },
+            }
+        }
+        _ => Flow {
+            fallthrough: true,
+            target: None,
+        },
+    }
+}
+
+// A constant being copied to memory (ADD #c, #0 or MUL #c, #1 and their mirrors), candidates for return addresses
+fn copied_constant(inst: &Instruction) -> Option<i64> {
+    let imm = |i: usize| match inst.modes[i] {
+        AddressMode::Immediate => Some(inst.params[i]),
+        _ => None,
+    };
+    let identity = match inst.opcode {
+        Opcodes::Add => 0,
+        Opcodes::Multiply => 1,
+        _ => return None,
+    };
+    match (imm(0)?, imm(1)?) {
+        (c, i) | (i, c) if i == identity => Some(c),
+        _ => None,
+    }
+}
+
+fn is_jump(inst: &Instruction) -> bool {
+    inst.opcode == Opcodes::Jnz || inst.opcode == Opcodes::Jz
+}
+
+impl Cfg {
+    pub fn build(prog: &[i64]) -> Cfg {
+        // Find every reachable instruction
+        let mut insts: BTreeMap<usize, Instruction> = BTreeMap::new();
+        let mut faults: BTreeMap<usize, IntcodeError> = BTreeMap::new();
+        let mut seeds: BTreeSet<usize> = BTreeSet::new();
+        let mut work = vec![0];
+        loop {
+            while let Some(addr) = work.pop() {
+                if insts.contains_key(&addr) || faults.contains_key(&addr) {
+                    continue;
+                }
+                let inst = match decode_at(prog, addr) {
+                    Ok(inst) => inst,
+                    Err(e) => {
+                        faults.insert(addr, e);
+                        continue;
+                    }
+                };
+                let f = flow(&inst);
+                if f.fallthrough {
+                    work.push(addr + inst.width());
+                }
+                if let Some(Some(t)) = f.target {
+                    work.push(t);
+                }
+                insts.insert(addr, inst);
+            }
+
+            // A constant naming the address right after an always taken jump is almost certainly a
+            //  return address, that code is only reached by jumping back through memory
+            let consts: HashSet<i64> = insts.values().filter_map(copied_constant).collect();
+            for inst in insts
+                .values()
+                .filter(|i| is_jump(i) && !flow(i).fallthrough)
+            {
+                let ret = inst.addr + inst.width();
+                if consts.contains(&(ret as i64)) && seeds.insert(ret) {
+                    work.push(ret);
+                }
+            }
+            if work.is_empty() {
+                break;
+            }
+        }
+
+        // Blocks start at the entry point, jump targets, return addresses, after jumps and anywhere
+        //  the previous instruction doesn't run straight into this one
+        let mut leaders: BTreeSet<usize> = seeds;
+        leaders.insert(0);
+        let mut prev: Option<&Instruction> = None;
+        for inst in insts.values() {
+            if let Some(Some(t)) = flow(inst).target {
+                leaders.insert(t);
+            }
+            let runs_on = match prev {
+                Some(p) => p.addr + p.width() == inst.addr && !is_jump(p) && flow(p).fallthrough,
+                None => false,
+            };
+            if !runs_on {
+                leaders.insert(inst.addr);
+            }
+            prev = Some(inst);
+        }
+
+        let mut blocks: BTreeMap<usize, Block> = BTreeMap::new();
+        let mut current: Option<Block> = None;
+        for inst in insts.values() {
+            if leaders.contains(&inst.addr) {
+                if let Some(b) = current.take() {
+                    blocks.insert(b.start, b);
+                }
+            }
+            current
+                .get_or_insert_with(|| Block {
+                    start: inst.addr,
+                    insts: Vec::new(),
+                    fault: None,
+                })
+                .insts
+                .push(*inst);
+        }
+        if let Some(b) = current {
+            blocks.insert(b.start, b);
+        }
+        for (&addr, e) in &faults {
+            blocks.insert(
+                addr,
+                Block {
+                    start: addr,
+                    insts: Vec::new(),
+                    fault: Some(e.clone()),
+                },
+            );
+        }
+
+        // Edges come from the last instruction of each block
+        let mut edges = Vec::new();
+        for b in blocks.values() {
+            let last = match b.insts.last() {
+                Some(inst) => inst,
+                None => continue,
+            };
+            let f = flow(last);
+            match f.target {
+                Some(Some(t)) => edges.push(Edge {
+                    from: b.start,
+                    to: Some(t),
+                    kind: EdgeKind::Taken,
+                }),
+                Some(None) => edges.push(Edge {
+                    from: b.start,
+                    to: None,
+                    kind: EdgeKind::Unresolved,
+                }),
+                None => (),
+            }
+            if f.fallthrough {
+                edges.push(Edge {
+                    from: b.start,
+                    to: Some(b.end()),
+                    kind: EdgeKind::Fallthrough,
+                });
+            }
+        }
+
+        // Writes through fixed addresses that land inside reachable code
+        let mut self_writes = Vec::new();
+        for inst in insts.values() {
+            let dest = match inst.opcode.dest() {
+                Some(d) if inst.modes[d - 1] == AddressMode::Positional => inst.params[d - 1],
+                _ => continue,
+            };
+            if dest < 0 {
+                continue;
+            }
+            let addr = dest as usize;
+            // Instructions can overlap when something jumps into the middle of another one
+            for target in insts
+                .values()
+                .filter(|t| t.addr <= addr && addr < t.addr + t.width())
+            {
+                self_writes.push(SelfWrite {
+                    pc: inst.addr,
+                    addr,
+                    target: target.addr,
+                });
+            }
+        }
+
+        Cfg {
+            blocks,
+            edges,
+            self_writes,
+        }
+    }
+
+    // Block containing addr, if addr is reachable code
+    pub fn block_at(&self, addr: usize) -> Option<&Block> {
+        let (_, b) = self.blocks.range(..=addr).next_back()?;
+        if addr < b.end() || addr == b.start {
+            Some(b)
+        } else {
+            None
+        }
+    }
+
+    // Graphviz source, one box per block with its disassembly. Jumps through memory go to a "?" node,
+    //  blocks that get written to are drawn red and the written instructions marked
+    pub fn to_dot(&self) -> String {
+        let written: HashSet<usize> = self.self_writes.iter().map(|w| w.target).collect();
+        let mut out = String::new();
+        out.push_str("digraph intcode {\n");
+        out.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        for b in self.blocks.values() {
+            let mut label = String::new();
+            match &b.fault {
+                Some(e) => write!(label, "{}: {}\\l", b.start, e).unwrap(),
+                None => {
+                    for inst in &b.insts {
+                        let mark = if written.contains(&inst.addr) {
+                            "  ; written"
+                        } else {
+                            ""
+                        };
+                        write!(label, "{:>5}: {}{}\\l", inst.addr, inst, mark).unwrap();
+                    }
+                }
+            }
+            let style = if b.fault.is_some() {
+                ", style=dashed"
+            } else if b.insts.iter().any(|i| written.contains(&i.addr)) {
+                ", color=red"
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "    b{} [label=\"{}\"{}];",
+                b.start,
+                label.replace('"', "\\\""),
+                style
+            )
+            .unwrap();
+        }
+        if self.edges.iter().any(|e| e.to.is_none()) {
+            out.push_str("    unresolved [shape=diamond, label=\"?\"];\n");
+        }
+        for e in &self.edges {
+            let to = match e.to {
+                Some(t) => format!("b{}", t),
+                None => "unresolved".to_string(),
+            };
+            let attrs = match e.kind {
+                EdgeKind::Fallthrough => "",
+                EdgeKind::Taken => " [label=\"taken\"]",
+                EdgeKind::Unresolved => " [style=dashed]",
+            };
+            writeln!(out, "    b{} -> {}{};", e.from, to, attrs).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+}
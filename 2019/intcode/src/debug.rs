@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+
+use crate::{IntcodeComp, IntcodeError, RunState, StopOn, Word};
+
+// Debugger support, stepping and inspection of the machine state. Breakpoints and watchpoints are
+//  honoured by every run method so they work the same from eval_async, run_until or step
+impl<W: Word> IntcodeComp<W> {
+    // Execute exactly one instruction
+    pub fn step(&mut self) -> RunState<W> {
+        self.run_until(StopOn::Instructions(1))
+    }
+
+    // Stop before executing the instruction at addr
+    pub fn add_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn has_breakpoint(&self, addr: usize) -> bool {
+        self.breakpoints.contains(&addr)
+    }
+
+    // Breakpoints in address order
+    pub fn breakpoints(&self) -> Vec<usize> {
+        let mut b: Vec<usize> = self.breakpoints.iter().copied().collect();
+        b.sort_unstable();
+        b
+    }
+
+    // Stop after any instruction that writes to addr, even if the value doesn't change
+    pub fn add_watchpoint(&mut self, addr: usize) {
+        self.watchpoints.insert(addr);
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: usize) -> bool {
+        self.watchpoints.remove(&addr)
+    }
+
+    // Watchpoints in address order
+    pub fn watchpoints(&self) -> Vec<usize> {
+        let mut w: Vec<usize> = self.watchpoints.iter().copied().collect();
+        w.sort_unstable();
+        w
+    }
+
+    pub fn program_counter(&self) -> usize {
+        self.program_counter
+    }
+
+    pub fn rel_base(&self) -> i64 {
+        self.rel_base
+    }
+
+    // Value of a memory cell, cells that were never touched read as 0
+    pub fn read_mem(&self, addr: usize) -> W {
+        self.peek(addr)
+    }
+
+    // Patch a memory cell from outside the program, far addresses get a sparse page like a program's
+    //  own writes would. Fails with OutOfMemory (operand 0, at the program counter) past the memory
+    //  limit. Watchpoints don't fire and the undo journal doesn't see it
+    pub fn write_mem(&mut self, addr: usize, val: W) -> Result<(), IntcodeError> {
+        if self.mem.set(addr, val).is_err() {
+            return Err(IntcodeError::OutOfMemory {
+                pc: self.program_counter,
+                word: self.word(),
+                operand: 0,
+                addr,
+                limit: self.mem.limit(),
+            });
+        }
+        self.invalidate(addr);
+        self.epoch += 1;
+        Ok(())
+    }
+
+    // Inputs waiting to be consumed, front is consumed first
+    pub fn input_queue(&self) -> &VecDeque<W> {
+        &self.in_buf
+    }
+
+    // Outputs waiting to be collected, front is the oldest
+    pub fn output_queue(&self) -> &VecDeque<W> {
+        &self.out_buf
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt;
+
+use crate::IntcodeError;
+
+// Enum for addressing modes per spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressMode {
+    Positional, // 0
+    Immediate,  // 1
+    Relative,   // 2
+}
+
+impl AddressMode {
+    pub(crate) fn from_digit(dig: i64) -> Option<AddressMode> {
+        use self::AddressMode::*;
+        match dig {
+            0 => Some(Positional),
+            1 => Some(Immediate),
+            2 => Some(Relative),
+            _ => None,
+        }
+    }
+
+    // Mode digit as it appears in an instruction word
+    pub fn digit(self) -> i64 {
+        use self::AddressMode::*;
+        match self {
+            Positional => 0,
+            Immediate => 1,
+            Relative => 2,
+        }
+    }
+}
+
+// Instructions the intcode computer supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Opcodes {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    Jnz,
+    Jz,
+    Comparelt,
+    Compareq,
+    Rbo,
+    Halt,
+}
+
+impl Opcodes {
+    // Every opcode in numeric order
+    pub const ALL: [Opcodes; 10] = [
+        Opcodes::Add,
+        Opcodes::Multiply,
+        Opcodes::Input,
+        Opcodes::Output,
+        Opcodes::Jnz,
+        Opcodes::Jz,
+        Opcodes::Comparelt,
+        Opcodes::Compareq,
+        Opcodes::Rbo,
+        Opcodes::Halt,
+    ];
+
+    pub fn from_usize(val: usize) -> Option<Opcodes> {
+        match val {
+            1 => Some(Opcodes::Add),
+            2 => Some(Opcodes::Multiply),
+            3 => Some(Opcodes::Input),
+            4 => Some(Opcodes::Output),
+            5 => Some(Opcodes::Jnz),
+            6 => Some(Opcodes::Jz),
+            7 => Some(Opcodes::Comparelt),
+            8 => Some(Opcodes::Compareq),
+            9 => Some(Opcodes::Rbo),
+            99 => Some(Opcodes::Halt),
+            _ => None,
+        }
+    }
+
+    // Low two digits of the instruction word
+    pub fn code(self) -> i64 {
+        use self::Opcodes::*;
+        match self {
+            Add => 1,
+            Multiply => 2,
+            Input => 3,
+            Output => 4,
+            Jnz => 5,
+            Jz => 6,
+            Comparelt => 7,
+            Compareq => 8,
+            Rbo => 9,
+            Halt => 99,
+        }
+    }
+
+    // Number of parameters following the instruction word
+    pub fn arity(self) -> usize {
+        use self::Opcodes::*;
+        match self {
+            Add | Multiply | Comparelt | Compareq => 3,
+            Jnz | Jz => 2,
+            Input | Output | Rbo => 1,
+            Halt => 0,
+        }
+    }
+
+    // Index (1-based) of the parameter the instruction writes through, if any
+    pub fn dest(self) -> Option<usize> {
+        use self::Opcodes::*;
+        match self {
+            Add | Multiply | Comparelt | Compareq => Some(3),
+            Input => Some(1),
+            _ => None,
+        }
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        use self::Opcodes::*;
+        match self {
+            Add => "ADD",
+            Multiply => "MUL",
+            Input => "IN",
+            Output => "OUT",
+            Jnz => "JNZ",
+            Jz => "JZ",
+            Comparelt => "LT",
+            Compareq => "EQ",
+            Rbo => "RBO",
+            Halt => "HLT",
+        }
+    }
+
+    // Inverse of mnemonic, case insensitive
+    pub fn from_mnemonic(name: &str) -> Option<Opcodes> {
+        Opcodes::ALL
+            .iter()
+            .find(|op| op.mnemonic().eq_ignore_ascii_case(name))
+            .copied()
+    }
+
+    // Build an instruction word from the opcode and the modes of its parameters
+    pub fn encode(self, modes: &[AddressMode]) -> i64 {
+        let mut word = self.code();
+        let mut scale = 100;
+        for mode in modes {
+            word += mode.digit() * scale;
+            scale *= 10;
+        }
+        word
+    }
+}
+
+// A fully decoded instruction, params holds the raw parameter words, only the first arity are meaningful
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub addr: usize,
+    pub word: i64,
+    pub opcode: Opcodes,
+    pub modes: [AddressMode; 3],
+    pub params: [i64; 3],
+}
+
+impl Instruction {
+    // Number of memory words the instruction occupies
+    pub fn width(&self) -> usize {
+        1 + self.opcode.arity()
+    }
+
+    // Parameters the opcode actually uses
+    pub fn operands(&self) -> &[i64] {
+        &self.params[..self.opcode.arity()]
+    }
+
+    // Instruction word with only the mode digits for used parameters, two encodings of the same
+    //  instruction can differ in the unused digits so this is what an assembler would emit
+    pub fn canonical_word(&self) -> i64 {
+        self.opcode.encode(&self.modes[..self.opcode.arity()])
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.opcode.mnemonic())?;
+        for (i, (mode, &param)) in self.modes.iter().zip(self.operands()).enumerate() {
+            write!(f, "{}", if i == 0 { " " } else { ", " })?;
+            match mode {
+                AddressMode::Positional => write!(f, "[{}]", param)?,
+                AddressMode::Immediate => write!(f, "#{}", param)?,
+                AddressMode::Relative if param < 0 => write!(f, "rb{}", param)?,
+                AddressMode::Relative => write!(f, "rb+{}", param)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Decode the instruction at addr, words past the end of mem read as 0 just like the VM sees them
+pub fn decode_at(mem: &[i64], addr: usize) -> Result<Instruction, IntcodeError> {
+    decode_with(|a| mem.get(a).copied().unwrap_or(0), addr)
+}
+
+// Decode reading words through peek, for memory that isn't one flat slice
+pub(crate) fn decode_with<F: Fn(usize) -> i64>(
+    peek: F,
+    addr: usize,
+) -> Result<Instruction, IntcodeError> {
+    // Negative words never name an opcode
+    let word = peek(addr);
+    if word < 0 {
+        return Err(IntcodeError::UnknownOpcode { pc: addr, word });
+    }
+
+    // Low two digits are the opcode, digits above anything past the third mode are ignored
+    let opcode = match Opcodes::from_usize((word % 100) as usize) {
+        Some(op) => op,
+        None => return Err(IntcodeError::UnknownOpcode { pc: addr, word }),
+    };
+
+    // Then one digit per parameter, hundreds first. Constant divisors so these compile to multiplies
+    let digits = [word / 100 % 10, word / 1000 % 10, word / 10000 % 10];
+    let mut modes = [AddressMode::Positional; 3];
+    for (i, (mode, &dig)) in modes.iter_mut().zip(&digits).enumerate() {
+        *mode = match AddressMode::from_digit(dig) {
+            Some(m) => m,
+            None => {
+                return Err(IntcodeError::BadMode {
+                    pc: addr,
+                    word,
+                    operand: i + 1,
+                    mode: dig,
+                })
+            }
+        };
+    }
+
+    // Destinations can never be immediate
+    if let Some(dest) = opcode.dest() {
+        if modes[dest - 1] == AddressMode::Immediate {
+            return Err(IntcodeError::ImmediateWrite {
+                pc: addr,
+                word,
+                operand: dest,
+            });
+        }
+    }
+
+    let mut params = [0; 3];
+    for (i, param) in params.iter_mut().take(opcode.arity()).enumerate() {
+        *param = peek(addr + 1 + i);
+    }
+
+    Ok(Instruction {
+        addr,
+        word,
+        opcode,
+        modes,
+        params,
+    })
+}
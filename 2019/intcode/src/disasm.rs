@@ -0,0 +1,67 @@
+use crate::decode::{decode_at, Instruction};
+
+// Longest run of data words put on a single .data line
+const DATA_PER_LINE: usize = 8;
+
+// One entry of a disassembly, either a decoded instruction or a run of words that don't decode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Listing {
+    Code(Instruction),
+    Data { addr: usize, words: Vec<i64> },
+}
+
+impl Listing {
+    pub fn addr(&self) -> usize {
+        match self {
+            Listing::Code(inst) => inst.addr,
+            Listing::Data { addr, .. } => *addr,
+        }
+    }
+}
+
+// Linear sweep over the program image. Anything that doesn't decode, runs off the end of the image or
+//  uses a non canonical encoding (mode digits on parameters the opcode doesn't have) is treated as data,
+//  that way re-assembling the listing always gives back the exact same words
+pub fn listing(prog: &[i64]) -> Vec<Listing> {
+    let mut out = Vec::new();
+    let mut addr = 0;
+    while addr < prog.len() {
+        match decode_at(prog, addr) {
+            Ok(inst) if addr + inst.width() <= prog.len() && inst.canonical_word() == inst.word => {
+                out.push(Listing::Code(inst));
+                addr += inst.width();
+            }
+            _ => {
+                // Extend the previous data run if there is room, else start a new one
+                match out.last_mut() {
+                    Some(Listing::Data { words, .. }) if words.len() < DATA_PER_LINE => {
+                        words.push(prog[addr])
+                    }
+                    _ => out.push(Listing::Data {
+                        addr,
+                        words: vec![prog[addr]],
+                    }),
+                }
+                addr += 1;
+            }
+        }
+    }
+    out
+}
+
+// Render a program as an annotated listing, one instruction or data run per line with its address
+//  in a trailing comment, e.g. "ADD [12], #5, rb+3    ; 4"
+pub fn disassemble(prog: &[i64]) -> String {
+    let mut out = String::new();
+    for line in listing(prog) {
+        let text = match &line {
+            Listing::Code(inst) => inst.to_string(),
+            Listing::Data { words, .. } => {
+                let words: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+                format!(".data {}", words.join(", "))
+            }
+        };
+        out.push_str(&format!("{:<32}; {}\n", text, line.addr()));
+    }
+    out
+}
@@ -0,0 +1,333 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::fmt;
+
+use crate::io::OutputSink;
+
+// A screen for programs that draw by outputting (x, y, value) triples, day 13's arcade cabinet is the
+//  obvious one. The canvas is sparse and grows to fit whatever is drawn, cells that never were show the
+//  background. Some coordinates can be made side channels instead of pixels, the arcade sends its score
+//  to (-1, 0):
+//
+//  let mut screen = Display::new();
+//  screen.add_channel("score", -1, 0);
+//  comp.run_with(&mut joystick, &mut screen);
+//  print!("{}Score: {}", screen, screen.channel("score").unwrap_or(0));
+//
+//  Anything that isn't fed by triples can draw with set() directly
+
+// Most rows or columns rendering will draw, coordinates come from the program and a couple of far flung
+//  pixels would otherwise ask for more text than there is memory. Anything past it is clipped off
+pub const MAX_RENDER: usize = 1024;
+
+// Inclusive bounds of a set of cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub min_x: i64,
+    pub min_y: i64,
+    pub max_x: i64,
+    pub max_y: i64,
+}
+
+impl Rect {
+    fn point(x: i64, y: i64) -> Rect {
+        Rect {
+            min_x: x,
+            min_y: y,
+            max_x: x,
+            max_y: y,
+        }
+    }
+
+    fn grow(&mut self, x: i64, y: i64) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
+    }
+
+    // In cells, saturating for bounds that span (nearly) all of i64
+    pub fn width(&self) -> usize {
+        span(self.min_x, self.max_x)
+    }
+
+    pub fn height(&self) -> usize {
+        span(self.min_y, self.max_y)
+    }
+
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        (self.min_x..=self.max_x).contains(&x) && (self.min_y..=self.max_y).contains(&y)
+    }
+}
+
+fn span(min: i64, max: i64) -> usize {
+    (i128::from(max) - i128::from(min) + 1)
+        .try_into()
+        .unwrap_or(usize::MAX)
+}
+
+// How cell values are drawn as characters. Values without a glyph of their own show as their digit
+//  when they have one and as the unknown glyph otherwise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    glyphs: HashMap<i64, char>,
+    unknown: char,
+}
+
+impl Palette {
+    pub fn new(glyphs: &[(i64, char)]) -> Palette {
+        Palette {
+            glyphs: glyphs.iter().copied().collect(),
+            unknown: '?',
+        }
+    }
+
+    pub fn set(&mut self, val: i64, glyph: char) {
+        self.glyphs.insert(val, glyph);
+    }
+
+    pub fn set_unknown(&mut self, glyph: char) {
+        self.unknown = glyph;
+    }
+
+    pub fn glyph(&self, val: i64) -> char {
+        match self.glyphs.get(&val) {
+            Some(&c) => c,
+            None if (0..10).contains(&val) => (b'0' + val as u8) as char,
+            None => self.unknown,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::new(&[])
+    }
+}
+
+// What a completed triple did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Update {
+    // old is what the cell showed before, the background if it was never drawn
+    Pixel { x: i64, y: i64, old: i64, new: i64 },
+    Channel { x: i64, y: i64, value: i64 },
+}
+
+struct Channel {
+    name: String,
+    x: i64,
+    y: i64,
+    value: Option<i64>,
+}
+
+pub struct Display {
+    cells: HashMap<(i64, i64), i64>,
+    background: i64,
+    bounds: Option<Rect>,
+    channels: Vec<Channel>,
+    palette: Palette,
+    y_up: bool,
+    // Partial triple
+    pending: [i64; 3],
+    filled: usize,
+    // Cells whose value changed since the last take_dirty
+    dirty: HashSet<(i64, i64)>,
+}
+
+impl Display {
+    pub fn new() -> Display {
+        Display {
+            cells: HashMap::new(),
+            background: 0,
+            bounds: None,
+            channels: Vec::new(),
+            palette: Palette::default(),
+            y_up: false,
+            pending: [0; 3],
+            filled: 0,
+            dirty: HashSet::new(),
+        }
+    }
+
+    // What cells that were never drawn hold, 0 unless set
+    pub fn set_background(&mut self, val: i64) {
+        self.background = val;
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    // Rows are drawn from the smallest y down by default, y_up draws the largest y at the top instead
+    pub fn set_y_up(&mut self, y_up: bool) {
+        self.y_up = y_up;
+    }
+
+    pub fn y_up(&self) -> bool {
+        self.y_up
+    }
+
+    // Triples sent to (x, y) set the channel called name rather than a pixel
+    pub fn add_channel(&mut self, name: &str, x: i64, y: i64) {
+        self.channels.push(Channel {
+            name: name.to_string(),
+            x,
+            y,
+            value: None,
+        });
+    }
+
+    // Last value sent to a channel, None if nothing has been yet
+    pub fn channel(&self, name: &str) -> Option<i64> {
+        self.channels
+            .iter()
+            .find(|c| c.name == name)
+            .and_then(|c| c.value)
+    }
+
+    // Every channel in the order they were added, with its last value
+    pub fn channels(&self) -> impl Iterator<Item = (&str, Option<i64>)> + '_ {
+        self.channels.iter().map(|c| (c.name.as_str(), c.value))
+    }
+
+    // Feed one value of the output stream, returns what happened once it completes a triple
+    pub fn push(&mut self, val: i64) -> Option<Update> {
+        self.pending[self.filled] = val;
+        self.filled += 1;
+        if self.filled < 3 {
+            return None;
+        }
+        self.filled = 0;
+        let [x, y, val] = self.pending;
+        if let Some(c) = self.channels.iter_mut().find(|c| (c.x, c.y) == (x, y)) {
+            c.value = Some(val);
+            return Some(Update::Channel { x, y, value: val });
+        }
+        let old = self.set(x, y, val);
+        Some(Update::Pixel {
+            x,
+            y,
+            old,
+            new: val,
+        })
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> i64 {
+        self.cells.get(&(x, y)).copied().unwrap_or(self.background)
+    }
+
+    // Draw one cell, returns what it held before
+    pub fn set(&mut self, x: i64, y: i64, val: i64) -> i64 {
+        let old = self.cells.insert((x, y), val).unwrap_or(self.background);
+        if old != val {
+            self.dirty.insert((x, y));
+        }
+        match &mut self.bounds {
+            Some(b) => b.grow(x, y),
+            None => self.bounds = Some(Rect::point(x, y)),
+        }
+        old
+    }
+
+    // Number of cells ever drawn, even if only with the background
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    // Number of drawn cells holding val
+    pub fn count(&self, val: i64) -> usize {
+        self.cells.values().filter(|&&v| v == val).count()
+    }
+
+    // Some cell holding val, the first one in reading order if there are several
+    pub fn find(&self, val: i64) -> Option<(i64, i64)> {
+        self.cells
+            .iter()
+            .filter(|&(_, &v)| v == val)
+            .map(|(&pos, _)| pos)
+            .min_by_key(|&(x, y)| (self.row_key(y), x))
+    }
+
+    // Everything drawn so far, None before the first pixel
+    pub fn bounds(&self) -> Option<Rect> {
+        self.bounds
+    }
+
+    // Smallest region covering the cells that changed since the last take_dirty
+    pub fn dirty(&self) -> Option<Rect> {
+        let mut cells = self.dirty.iter();
+        let &(x, y) = cells.next()?;
+        let mut r = Rect::point(x, y);
+        for &(x, y) in cells {
+            r.grow(x, y);
+        }
+        Some(r)
+    }
+
+    // The cells that changed since the last call in reading order, the difference between two frames
+    pub fn take_dirty(&mut self) -> Vec<(i64, i64)> {
+        let mut cells: Vec<(i64, i64)> = self.dirty.drain().collect();
+        cells.sort_by_key(|&(x, y)| (self.row_key(y), x));
+        cells
+    }
+
+    pub fn glyph(&self, x: i64, y: i64) -> char {
+        self.palette.glyph(self.get(x, y))
+    }
+
+    // One line per row of r, each ending in a newline. Only the first MAX_RENDER rows and columns
+    //  are drawn, starting from the left and whichever row comes first
+    pub fn render_rect(&self, r: Rect) -> String {
+        let (width, height) = (r.width().min(MAX_RENDER), r.height().min(MAX_RENDER));
+        let mut out = String::with_capacity((width + 1) * height);
+        for row in 0..height as i64 {
+            let y = if self.y_up {
+                r.max_y - row
+            } else {
+                r.min_y + row
+            };
+            out.extend((0..width as i64).map(|col| self.glyph(r.min_x + col, y)));
+            out.push('\n');
+        }
+        out
+    }
+
+    // Sort key putting rows in the order they're drawn
+    fn row_key(&self, y: i64) -> i64 {
+        if self.y_up {
+            !y // -y - 1, which can't overflow
+        } else {
+            y
+        }
+    }
+}
+
+impl Default for Display {
+    fn default() -> Display {
+        Display::new()
+    }
+}
+
+// Everything drawn so far, nothing at all before the first pixel
+impl fmt::Display for Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bounds {
+            Some(r) => write!(f, "{}", self.render_rect(r)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl OutputSink for Display {
+    fn push_output(&mut self, val: i64) {
+        self.push(val);
+    }
+}
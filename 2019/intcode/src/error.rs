@@ -0,0 +1,182 @@
+use std::error::Error;
+use std::fmt;
+
+// Everything that can go wrong while executing an intcode program. Each variant carries the
+//  program counter of the offending instruction and the raw instruction word found there so a
+//  harness can report the failure and move on to the next program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntcodeError {
+    // Low two digits of the instruction word do not name an opcode
+    UnknownOpcode {
+        pc: usize,
+        word: i64,
+    },
+    // A mode digit other than 0, 1 or 2, operand is the 1-based parameter index
+    BadMode {
+        pc: usize,
+        word: i64,
+        operand: usize,
+        mode: i64,
+    },
+    // A destination parameter was encoded in immediate mode
+    ImmediateWrite {
+        pc: usize,
+        word: i64,
+        operand: usize,
+    },
+    // A parameter resolved to an address below zero (read, write or jump target)
+    NegativeAddress {
+        pc: usize,
+        word: i64,
+        operand: usize,
+        addr: i64,
+    },
+    // A write needed more memory than the machine's limit allows, operand is 0 for writes an extension
+    //  made straight to an address
+    OutOfMemory {
+        pc: usize,
+        word: i64,
+        operand: usize,
+        addr: usize,
+        limit: usize,
+    },
+    // An add or multiply didn't fit the word (with Overflow::Trap), or an address or the relative base
+    //  left i64
+    Overflow {
+        pc: usize,
+        word: i64,
+    },
+    // An extension opcode's handler gave up
+    Extension {
+        pc: usize,
+        word: i64,
+        message: String,
+    },
+}
+
+impl IntcodeError {
+    // Program counter of the instruction that failed
+    pub fn pc(&self) -> usize {
+        use IntcodeError::*;
+        match *self {
+            UnknownOpcode { pc, .. }
+            | BadMode { pc, .. }
+            | ImmediateWrite { pc, .. }
+            | NegativeAddress { pc, .. }
+            | OutOfMemory { pc, .. }
+            | Overflow { pc, .. }
+            | Extension { pc, .. } => pc,
+        }
+    }
+
+    // Raw instruction word of the instruction that failed
+    pub fn word(&self) -> i64 {
+        use IntcodeError::*;
+        match *self {
+            UnknownOpcode { word, .. }
+            | BadMode { word, .. }
+            | ImmediateWrite { word, .. }
+            | NegativeAddress { word, .. }
+            | OutOfMemory { word, .. }
+            | Overflow { word, .. }
+            | Extension { word, .. } => word,
+        }
+    }
+
+    // Short name for the kind of failure, unlike the message it doesn't change so it can go in files
+    pub fn code(&self) -> &'static str {
+        use IntcodeError::*;
+        match self {
+            UnknownOpcode { .. } => "unknown-opcode",
+            BadMode { .. } => "bad-mode",
+            ImmediateWrite { .. } => "immediate-write",
+            NegativeAddress { .. } => "negative-address",
+            OutOfMemory { .. } => "out-of-memory",
+            Overflow { .. } => "overflow",
+            Extension { .. } => "extension",
+        }
+    }
+
+    // Parameter index (1-based) that caused the failure, if the failure is tied to one
+    pub fn operand(&self) -> Option<usize> {
+        use IntcodeError::*;
+        match *self {
+            UnknownOpcode { .. } | Overflow { .. } | Extension { .. } => None,
+            OutOfMemory { operand: 0, .. } => None,
+            BadMode { operand, .. }
+            | ImmediateWrite { operand, .. }
+            | NegativeAddress { operand, .. }
+            | OutOfMemory { operand, .. } => Some(operand),
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use IntcodeError::*;
+        match self {
+            UnknownOpcode { pc, word } => {
+                write!(
+                    f,
+                    "unknown opcode {} at pc {} (word {})",
+                    word % 100,
+                    pc,
+                    word
+                )
+            }
+            BadMode {
+                pc,
+                word,
+                operand,
+                mode,
+            } => write!(
+                f,
+                "bad mode {} for operand {} at pc {} (word {})",
+                mode, operand, pc, word
+            ),
+            ImmediateWrite { pc, word, operand } => write!(
+                f,
+                "immediate mode write through operand {} at pc {} (word {})",
+                operand, pc, word
+            ),
+            NegativeAddress {
+                pc,
+                word,
+                operand,
+                addr,
+            } => write!(
+                f,
+                "negative address {} from operand {} at pc {} (word {})",
+                addr, operand, pc, word
+            ),
+            OutOfMemory {
+                pc,
+                word,
+                operand: 0,
+                addr,
+                limit,
+            } => write!(
+                f,
+                "out of memory writing address {} at pc {} (word {}), limit is {} words",
+                addr, pc, word, limit
+            ),
+            OutOfMemory {
+                pc,
+                word,
+                operand,
+                addr,
+                limit,
+            } => write!(
+                f,
+                "out of memory writing address {} from operand {} at pc {} (word {}), limit is {} words",
+                addr, operand, pc, word, limit
+            ),
+            Overflow { pc, word } => write!(f, "arithmetic overflow at pc {} (word {})", pc, word),
+            Extension { pc, word, message } => {
+                write!(f, "{} at pc {} (word {})", message, pc, word)
+            }
+        }
+    }
+}
+
+impl Error for IntcodeError {}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{
+    overflow_at, AddressMode, InputSource, IntcodeComp, IntcodeError, Opcodes, OutputSink,
+};
+use crate::{Step, Word};
+
+// Extra opcodes for experimental intcode dialects, a debug print or a call out to the host. Every
+//  code the spec leaves free (0 and 10 to 98) can be given a handler:
+//
+//  let mut ext = Registry::new();
+//  ext.define(42, "PRT", &[Param::Read], |op| {
+//      println!("{}", op.arg(1)?);
+//      Ok(Flow::Next)
+//  })?;
+//  comp.set_extensions(ext);
+//
+//  Built in opcodes always win and words no one defined still fail with IntcodeError::UnknownOpcode
+
+// How an extension uses each of its parameters, mode digits work the same as for built in opcodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    Read,
+    // Written through, can't be immediate
+    Write,
+}
+
+// What the machine does once a handler returns. Halt and Blocked mean the instruction didn't complete,
+//  return those before changing anything
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    // Carry on with the instruction after this one
+    Next,
+    Jump(usize),
+    // Stop like HLT does, the program counter stays on this instruction
+    Halt,
+    // Wait for input, the instruction runs again from the start once there is some
+    Blocked,
+}
+
+// Why define turned an opcode down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionError {
+    // Taken by a built in opcode or not two digits
+    Reserved(i64),
+    // More parameters than mode digits can describe
+    TooManyParams(usize),
+}
+
+impl fmt::Display for ExtensionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtensionError::Reserved(code) => {
+                write!(f, "opcode {} isn't free for an extension", code)
+            }
+            ExtensionError::TooManyParams(n) => {
+                write!(f, "extensions take at most 3 parameters, not {}", n)
+            }
+        }
+    }
+}
+
+impl Error for ExtensionError {}
+
+type Handler<W> = dyn Fn(&mut Context<'_, W>) -> Result<Flow, IntcodeError> + Send + Sync;
+
+struct Extension<W: Word> {
+    name: String,
+    params: Vec<Param>,
+    handler: Box<Handler<W>>,
+}
+
+// Opcodes added on top of the built in ones, install with IntcodeComp::set_extensions
+pub struct Registry<W: Word = i64> {
+    ops: HashMap<i64, Extension<W>>,
+}
+
+impl<W: Word> Registry<W> {
+    pub fn new() -> Registry<W> {
+        Registry {
+            ops: HashMap::new(),
+        }
+    }
+
+    // Give code a handler, replacing any earlier definition. Fails if code is taken by a built in
+    //  opcode or isn't two digits, or there are more than the 3 parameters mode digits can describe
+    pub fn define<F>(
+        &mut self,
+        code: i64,
+        name: &str,
+        params: &[Param],
+        handler: F,
+    ) -> Result<&mut Self, ExtensionError>
+    where
+        F: Fn(&mut Context<'_, W>) -> Result<Flow, IntcodeError> + Send + Sync + 'static,
+    {
+        if !(0..100).contains(&code) || Opcodes::from_usize(code as usize).is_some() {
+            return Err(ExtensionError::Reserved(code));
+        }
+        if params.len() > 3 {
+            return Err(ExtensionError::TooManyParams(params.len()));
+        }
+        let ext = Extension {
+            name: name.to_string(),
+            params: params.to_vec(),
+            handler: Box::new(handler),
+        };
+        self.ops.insert(code, ext);
+        Ok(self)
+    }
+
+    pub fn name(&self, code: i64) -> Option<&str> {
+        self.ops.get(&code).map(|e| e.name.as_str())
+    }
+
+    pub fn arity(&self, code: i64) -> Option<usize> {
+        self.ops.get(&code).map(|e| e.params.len())
+    }
+}
+
+impl<W: Word> Default for Registry<W> {
+    fn default() -> Registry<W> {
+        Registry::new()
+    }
+}
+
+// Both halves of the device a machine is running with, as one trait object
+trait Device<W>: InputSource<W> + OutputSink<W> {}
+impl<W, D: InputSource<W> + OutputSink<W> + ?Sized> Device<W> for D {}
+
+// Sized stand in for a device that might not be, so it can become a trait object
+struct DeviceRef<'a, D: ?Sized>(&'a mut D);
+
+impl<W, D: InputSource<W> + ?Sized> InputSource<W> for DeviceRef<'_, D> {
+    fn next_input(&mut self) -> Option<W> {
+        self.0.next_input()
+    }
+}
+
+impl<W, D: OutputSink<W> + ?Sized> OutputSink<W> for DeviceRef<'_, D> {
+    fn push_output(&mut self, val: W) {
+        self.0.push_output(val)
+    }
+}
+
+// What a handler gets to work with, the instruction being executed plus the machine's memory and I/O.
+//  Everything goes through the same paths the built in opcodes use, so watchpoints, the undo journal
+//  and loop detection all see what an extension does
+pub struct Context<'a, W: Word> {
+    comp: &'a mut IntcodeComp<W>,
+    dev: &'a mut dyn Device<W>,
+    pc: usize,
+    word: i64,
+    modes: [AddressMode; 3],
+    arity: usize,
+    outputs: usize,
+}
+
+impl<W: Word> Context<'_, W> {
+    // Address of the instruction
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn word(&self) -> i64 {
+        self.word
+    }
+
+    pub fn rel_base(&self) -> i64 {
+        self.comp.rel_base
+    }
+
+    pub fn mode(&self, operand: usize) -> AddressMode {
+        self.modes[..self.arity][operand - 1]
+    }
+
+    // Where a parameter points, operand is 1-based. Immediate parameters point at themselves
+    pub fn addr(&self, operand: usize) -> Result<usize, IntcodeError> {
+        let at = self.pc + operand;
+        match self.mode(operand) {
+            AddressMode::Immediate => Ok(at),
+            mode => {
+                let param = self
+                    .comp
+                    .peek(at)
+                    .to_i64()
+                    .ok_or_else(|| overflow_at(self.pc, self.word))?;
+                self.comp.locate(self.pc, self.word, operand, mode, param)
+            }
+        }
+    }
+
+    // Value of a parameter
+    pub fn arg(&self, operand: usize) -> Result<W, IntcodeError> {
+        Ok(self.comp.peek(self.addr(operand)?))
+    }
+
+    // Write through a parameter
+    pub fn set(&mut self, operand: usize, val: W) -> Result<(), IntcodeError> {
+        if self.mode(operand) == AddressMode::Immediate {
+            return Err(IntcodeError::ImmediateWrite {
+                pc: self.pc,
+                word: self.word,
+                operand,
+            });
+        }
+        let ptr = self.addr(operand)?;
+        self.comp.store(self.pc, self.word, operand, ptr, val)
+    }
+
+    // Memory by address, cells that were never written read as 0
+    pub fn read(&self, addr: usize) -> W {
+        self.comp.peek(addr)
+    }
+
+    // Running out of memory here reports operand 0
+    pub fn write(&mut self, addr: usize, val: W) -> Result<(), IntcodeError> {
+        self.comp.store(self.pc, self.word, 0, addr, val)
+    }
+
+    // Next input from the queue or the device, None means return Flow::Blocked
+    pub fn input(&mut self) -> Option<W> {
+        let val = self
+            .comp
+            .in_buf
+            .pop_front()
+            .or_else(|| self.dev.next_input())?;
+        self.comp.epoch += 1;
+        if let Some(j) = &mut self.comp.journal {
+            j.input(val.clone());
+        }
+        Some(val)
+    }
+
+    pub fn output(&mut self, val: W) {
+        if let Some(j) = &mut self.comp.journal {
+            j.output();
+        }
+        self.dev.push_output(val);
+        self.outputs += 1;
+    }
+
+    // Error for a handler to fail with
+    pub fn fail(&self, message: &str) -> IntcodeError {
+        IntcodeError::Extension {
+            pc: self.pc,
+            word: self.word,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl<W: Word> IntcodeComp<W> {
+    // Replaces any extensions installed before, machines cloned from this one share them
+    pub fn set_extensions(&mut self, registry: Registry<W>) {
+        self.extensions = Some(Arc::new(registry));
+    }
+
+    pub fn clear_extensions(&mut self) {
+        self.extensions = None;
+    }
+
+    pub fn extensions(&self) -> Option<&Registry<W>> {
+        self.extensions.as_deref()
+    }
+
+    // Run the instruction at the program counter as an extension, unknown is the error decoding it
+    //  as a built in opcode gave. Extension instructions aren't cached, they're decoded every time
+    pub(crate) fn execute_extension<D: InputSource<W> + OutputSink<W> + ?Sized>(
+        &mut self,
+        dev: &mut D,
+        unknown: IntcodeError,
+    ) -> Result<Step, IntcodeError> {
+        let registry = match &self.extensions {
+            Some(r) => Arc::clone(r),
+            None => return Err(unknown),
+        };
+        let pc = self.program_counter;
+        let word = unknown.word();
+        let ext = match registry.ops.get(&(word % 100)) {
+            Some(ext) if word >= 0 => ext,
+            _ => return Err(unknown),
+        };
+
+        let mut modes = [AddressMode::Positional; 3];
+        let mut scale = 100;
+        for (i, &param) in ext.params.iter().enumerate() {
+            let dig = word / scale % 10;
+            modes[i] = match AddressMode::from_digit(dig) {
+                Some(AddressMode::Immediate) if param == Param::Write => {
+                    return Err(IntcodeError::ImmediateWrite {
+                        pc,
+                        word,
+                        operand: i + 1,
+                    })
+                }
+                Some(m) => m,
+                None => {
+                    return Err(IntcodeError::BadMode {
+                        pc,
+                        word,
+                        operand: i + 1,
+                        mode: dig,
+                    })
+                }
+            };
+            scale *= 10;
+        }
+
+        let arity = ext.params.len();
+        let mut ctx = Context {
+            comp: self,
+            dev: &mut DeviceRef(dev),
+            pc,
+            word,
+            modes,
+            arity,
+            outputs: 0,
+        };
+        let flow = (ext.handler)(&mut ctx)?;
+        let outputs = ctx.outputs;
+        // Same as built in opcodes, one that's waiting to run again hasn't happened yet
+        if let Some(p) = self.profile.as_mut().filter(|_| flow != Flow::Blocked) {
+            p.extension(pc, word % 100);
+        }
+        match flow {
+            Flow::Next => self.program_counter = pc + 1 + arity,
+            Flow::Jump(target) => {
+                if let Some(p) = &mut self.profile {
+                    p.branch(pc, true, target);
+                }
+                self.program_counter = target;
+            }
+            Flow::Halt => return Ok(Step::Halted),
+            Flow::Blocked => return Ok(Step::Blocked),
+        }
+        Ok(if outputs == 0 {
+            Step::Continue
+        } else {
+            Step::Output(outputs)
+        })
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+
+// Where an intcode machine gets its input from, None means no input is available right now
+//  and the machine stops with RunState::AwaitingInput. W is the machine's word, i64 unless it was
+//  built wider
+pub trait InputSource<W = i64> {
+    fn next_input(&mut self) -> Option<W>;
+}
+
+// Where an intcode machine sends its output
+pub trait OutputSink<W = i64> {
+    fn push_output(&mut self, val: W);
+}
+
+// The default queues
+impl<W> InputSource<W> for VecDeque<W> {
+    fn next_input(&mut self) -> Option<W> {
+        self.pop_front()
+    }
+}
+
+impl<W> OutputSink<W> for VecDeque<W> {
+    fn push_output(&mut self, val: W) {
+        self.push_back(val);
+    }
+}
+
+impl<W> OutputSink<W> for Vec<W> {
+    fn push_output(&mut self, val: W) {
+        self.push(val);
+    }
+}
+
+// Closures, e.g. `|| Some(joystick())` and `|v| screen.draw(v)`
+impl<W, F: FnMut() -> Option<W>> InputSource<W> for F {
+    fn next_input(&mut self) -> Option<W> {
+        self()
+    }
+}
+
+impl<W, F: FnMut(W)> OutputSink<W> for F {
+    fn push_output(&mut self, val: W) {
+        self(val)
+    }
+}
+
+// Channels block until a value arrives, a hung up sender reads as starvation
+impl<W> InputSource<W> for Receiver<W> {
+    fn next_input(&mut self) -> Option<W> {
+        self.recv().ok()
+    }
+}
+
+// If the receiving end is gone there is nobody left to care about the value so it is dropped
+impl<W> OutputSink<W> for Sender<W> {
+    fn push_output(&mut self, val: W) {
+        let _ = self.send(val);
+    }
+}
+
+impl<W> OutputSink<W> for SyncSender<W> {
+    fn push_output(&mut self, val: W) {
+        let _ = self.send(val);
+    }
+}
+
+// Reads one integer per line from stdin, blank lines are skipped and unparseable lines re-prompt
+pub struct StdinLines {
+    pub prompt: Option<String>,
+}
+
+impl StdinLines {
+    pub fn new() -> StdinLines {
+        StdinLines { prompt: None }
+    }
+
+    pub fn with_prompt(prompt: &str) -> StdinLines {
+        StdinLines {
+            prompt: Some(prompt.to_string()),
+        }
+    }
+}
+
+impl Default for StdinLines {
+    fn default() -> Self {
+        StdinLines::new()
+    }
+}
+
+impl InputSource for StdinLines {
+    fn next_input(&mut self) -> Option<i64> {
+        let stdin = io::stdin();
+        loop {
+            if let Some(p) = &self.prompt {
+                print!("{}", p);
+                io::stdout().flush().ok()?;
+            }
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) | Err(_) => return None, // EOF
+                Ok(_) => (),
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            match line.parse::<i64>() {
+                Ok(val) => return Some(val),
+                Err(e) => eprintln!("Warning: {}, string: {}", e, line),
+            }
+        }
+    }
+}
+
+// Prints each output on its own line
+pub struct StdoutLines;
+
+impl<W: fmt::Display> OutputSink<W> for StdoutLines {
+    fn push_output(&mut self, val: W) {
+        println!("{}", val);
+    }
+}
+
+// Glue a separate source and sink into one device
+pub struct Split<'a, I: ?Sized, O: ?Sized>(pub &'a mut I, pub &'a mut O);
+
+impl<W, I: InputSource<W> + ?Sized, O: ?Sized> InputSource<W> for Split<'_, I, O> {
+    fn next_input(&mut self) -> Option<W> {
+        self.0.next_input()
+    }
+}
+
+impl<W, I: ?Sized, O: OutputSink<W> + ?Sized> OutputSink<W> for Split<'_, I, O> {
+    fn push_output(&mut self, val: W) {
+        self.1.push_output(val)
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+
+use crate::memory::Growth;
+use crate::{IntcodeComp, Word};
+
+// Everything one instruction changed, enough to put the machine back the way it was before it ran
+#[derive(Debug, Clone, Default)]
+struct Record<W> {
+    pc: usize,
+    rel_base: i64,
+    write: Option<(usize, W)>, // Address and the value it held before
+    input: Option<W>,          // Value consumed, whether it came from the queue or a device
+    output: Option<Sent>,
+    // Built in opcodes do at most one of each, extension opcodes can do more. Everything after the
+    //  first goes here in the order it happened, empty (and unallocated) almost always
+    more: Vec<Effect<W>>,
+    // Memory the writes had to allocate, oldest first
+    grown: Vec<Growth>,
+}
+
+impl<W> Record<W> {
+    fn wrote(&self, addr: usize) -> bool {
+        matches!(self.write, Some((a, _)) if a == addr)
+            || self
+                .more
+                .iter()
+                .any(|e| matches!(e, Effect::Write(a, _) if *a == addr))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Effect<W> {
+    Write(usize, W),
+    Input(W),
+    Output(Sent),
+}
+
+// Where an output went, only the ones on the output queue can be taken back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sent {
+    Queue,
+    Device,
+}
+
+// Undo history, oldest instructions fall off the front once it's full
+#[derive(Debug, Clone)]
+pub(crate) struct Journal<W> {
+    records: VecDeque<Record<W>>,
+    capacity: usize,
+    current: Record<W>, // Filled in while an instruction executes, kept only if it completes
+    queued: bool,       // Outputs are going to the output queue rather than a device
+}
+
+impl<W: Word> Journal<W> {
+    pub fn begin(&mut self, pc: usize, rel_base: i64) {
+        self.current = Record {
+            pc,
+            rel_base,
+            ..Record::default()
+        };
+    }
+
+    pub fn write(&mut self, addr: usize, old: W, growth: Option<Growth>) {
+        match self.current.write {
+            None => self.current.write = Some((addr, old)),
+            Some(_) => self.current.more.push(Effect::Write(addr, old)),
+        }
+        self.current.grown.extend(growth);
+    }
+
+    pub fn input(&mut self, val: W) {
+        match self.current.input {
+            None => self.current.input = Some(val),
+            Some(_) => self.current.more.push(Effect::Input(val)),
+        }
+    }
+
+    pub fn output(&mut self) {
+        let sent = if self.queued {
+            Sent::Queue
+        } else {
+            Sent::Device
+        };
+        match self.current.output {
+            None => self.current.output = Some(sent),
+            Some(_) => self.current.more.push(Effect::Output(sent)),
+        }
+    }
+
+    // Set by run_until for as long as the output queue is the sink
+    pub fn set_queued(&mut self, queued: bool) {
+        self.queued = queued;
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn commit(&mut self) {
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(std::mem::take(&mut self.current));
+    }
+}
+
+// Reversible execution. With the journal on every completed instruction is recorded, stepping back
+//  undoes them newest first: memory, pc and relative base go back to what they were, consumed inputs
+//  go back on the front of the input queue (so running forward again replays them, even ones that
+//  came from a device) and outputs are taken back off the output queue if they haven't been collected.
+//  Memory a write allocated is released again. Outputs that went to a device are gone for good
+impl<W: Word> IntcodeComp<W> {
+    // Start recording, at most capacity instructions are kept, clears any previous history
+    pub fn enable_journal(&mut self, capacity: usize) {
+        self.journal = Some(Box::new(Journal {
+            records: VecDeque::new(),
+            capacity: capacity.max(1),
+            current: Record::default(),
+            queued: false,
+        }));
+    }
+
+    pub fn disable_journal(&mut self) {
+        self.journal = None;
+    }
+
+    pub fn journaling(&self) -> bool {
+        self.journal.is_some()
+    }
+
+    // Instructions that can be stepped back over
+    pub fn journal_len(&self) -> usize {
+        self.journal.as_ref().map_or(0, |j| j.records.len())
+    }
+
+    // Undo the last n instructions, returns how many there were to undo
+    pub fn step_back(&mut self, n: usize) -> usize {
+        let mut undone = 0;
+        while undone < n && self.undo() {
+            undone += 1;
+        }
+        undone
+    }
+
+    // Step back to just before the most recent instruction that wrote to addr (an extension's later
+    //  writes included), so that instruction is the next to run. Returns its address, or None (and changes nothing) if the journal doesn't go
+    //  back far enough
+    pub fn back_to_write(&mut self, addr: usize) -> Option<usize> {
+        let journal = self.journal.as_ref()?;
+        let back = journal.records.iter().rev().position(|r| r.wrote(addr))?;
+        self.step_back(back + 1);
+        Some(self.program_counter)
+    }
+
+    // Undo the newest record, false if there isn't one
+    fn undo(&mut self) -> bool {
+        let r = match self.journal.as_mut().and_then(|j| j.records.pop_back()) {
+            Some(r) => r,
+            None => return false,
+        };
+        self.program_counter = r.pc;
+        self.rel_base = r.rel_base;
+        // Newest first, the extra effects all came after the first of each kind
+        for effect in r.more.into_iter().rev() {
+            match effect {
+                Effect::Write(addr, old) => self.unwrite(addr, old),
+                Effect::Input(val) => self.in_buf.push_front(val),
+                Effect::Output(sent) => self.unoutput(sent),
+            }
+        }
+        if let Some((addr, old)) = r.write {
+            self.unwrite(addr, old);
+        }
+        if let Some(val) = r.input {
+            self.in_buf.push_front(val);
+        }
+        if let Some(sent) = r.output {
+            self.unoutput(sent);
+        }
+        // With every cell back to its old value whatever the writes allocated holds nothing
+        for growth in r.grown.into_iter().rev() {
+            self.mem.shrink(growth);
+        }
+        self.epoch += 1;
+        true
+    }
+
+    fn unwrite(&mut self, addr: usize, old: W) {
+        // The cell was allocated by the write being undone, putting the old value back can't fail
+        self.mem.set(addr, old).ok();
+        self.invalidate(addr);
+    }
+
+    // Newer outputs have been undone already, so this one is at the back of the queue unless it was
+    //  collected, and then so was everything before it
+    fn unoutput(&mut self, sent: Sent) {
+        if sent == Sent::Queue {
+            self.out_buf.pop_back();
+        }
+    }
+}
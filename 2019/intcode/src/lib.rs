@@ -6,83 +6,195 @@ mod tests {
     // }
     //TODO: Determine if it's worth unit testing anything in here
 }
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::convert::TryInto;
-use std::fs;
-
-pub struct IntcodeComp {
-    mem_space: Vec<i64>,
+use std::mem;
+use std::sync::Arc;
+
+pub mod ascii;
+pub mod asm;
+mod budget;
+pub mod cfg;
+mod debug;
+mod decode;
+pub mod disasm;
+pub mod display;
+mod error;
+pub mod extension;
+pub mod io;
+mod journal;
+pub mod load;
+mod memory;
+pub mod network;
+mod profile;
+mod snapshot;
+pub mod symbolic;
+pub mod threaded;
+pub mod trace;
+pub mod tui;
+mod word;
+pub use budget::Budget;
+use budget::Limits;
+pub use decode::{decode_at, AddressMode, Instruction, Opcodes};
+pub use error::IntcodeError;
+use extension::Registry;
+pub use io::{InputSource, OutputSink};
+use journal::Journal;
+use memory::Memory;
+pub use memory::{DEFAULT_MEMORY_LIMIT, PAGE_SIZE};
+pub use num_bigint::BigInt;
+pub use profile::{BranchStats, HotLoop, Profile};
+pub use snapshot::Snapshot;
+pub use word::{Overflow, Word};
+
+// Generic over what a memory cell holds, i64 unless asked otherwise. Addresses and the relative base
+//  are always machine sized, only data gets wider
+#[derive(Clone)]
+pub struct IntcodeComp<W: Word = i64> {
+    mem: Memory<W>,
     program_counter: usize,
-    in_buf: VecDeque<i64>,
-    out_buf: VecDeque<i64>,
+    in_buf: VecDeque<W>,
+    out_buf: VecDeque<W>,
     rel_base: i64,
+    overflow: Overflow,
+    breakpoints: HashSet<usize>,
+    watchpoints: HashSet<usize>,
+    watch_hit: Option<RunState<W>>, // Set by write_back when a watched cell is written
+    profile: Option<Box<Profile>>,  // Only collected when profiling is enabled
+    icache: Option<DecodeCache>,    // None when caching is off
+    limits: Option<Box<Limits>>, // Instruction/time budgets and loop detection, None when all are off
+    epoch: u64, // Bumped by every write that changes memory and every input consumed
+    journal: Option<Box<Journal<W>>>, // Undo history, only kept when enabled
+    extensions: Option<Arc<Registry<W>>>, // Extra opcodes, shared between clones
 }
 
-// Enum for addressing modes per spec
-enum AddressMode {
-    Positional, // 0
-    Immediate,  // 1
-    Relative,   // 2
+// Where execution stopped, returned by eval_async and run_until
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunState<W = i64> {
+    // Program executed a halt instruction, calling again is a no-op
+    Halted,
+    // Program wants input and the input queue is empty, feed it with input() and resume
+    AwaitingInput,
+    // Program just produced an output, this many values are waiting in the output queue
+    OutputReady(usize),
+    // Stop condition was met (instruction count), program can be resumed as is
+    Running,
+    // Program faulted, the program counter still points at the faulting instruction
+    Error(IntcodeError),
+    // About to execute the instruction at a breakpoint, resuming executes it
+    Breakpoint(usize),
+    // The instruction that just executed wrote to a watched address
+    Watchpoint { addr: usize, old: W, new: W },
+    // Ran out of instructions or time before the next instruction, raise the budget to resume
+    BudgetExhausted(Budget),
+    // Loop detection saw the machine come back to this pc with nothing changed, it will never stop
+    Cycling(usize),
+    // An add or multiply at this pc overflowed under Overflow::Promote, nothing was written.
+    //  promote() the machine to a wider word and resume that
+    Overflowed(usize),
 }
 
-// Instructions the intcode computer supports
-enum Opcodes {
-    Add,
-    Multiply,
-    Input,
+// Extra reasons for run_until to hand control back, halting, input starvation and errors always stop execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopOn {
+    // Stop after every output instruction
     Output,
-    Jnz,
-    Jz,
-    Comparelt,
-    Compareq,
-    Rbo,
+    // Stop after this many instructions have executed
+    Instructions(u64),
+    // Only stop for the unconditional reasons
     Halt,
 }
 
-impl Opcodes {
-    fn from_usize(val: usize) -> Opcodes {
-        match val {
-            1 => Opcodes::Add,
-            2 => Opcodes::Multiply,
-            3 => Opcodes::Input,
-            4 => Opcodes::Output,
-            5 => Opcodes::Jnz,
-            6 => Opcodes::Jz,
-            7 => Opcodes::Comparelt,
-            8 => Opcodes::Compareq,
-            9 => Opcodes::Rbo,
-            99 => Opcodes::Halt,
-            _ => panic!("Opcode {} not valid", val),
+// Decoded instructions by address. Filling it costs more than decoding once, so it only starts once a
+//  machine has run long enough to be looping, short lived machines (day 2 searches) never pay for it
+#[derive(Clone)]
+struct DecodeCache {
+    entries: Vec<Option<Instruction>>,
+    warmup: u32, // Cache misses left before entries start being stored
+}
+
+impl DecodeCache {
+    fn new() -> DecodeCache {
+        DecodeCache {
+            entries: Vec::new(),
+            warmup: 256,
         }
     }
 }
 
-// Closures etc could make this much much cleaner I might come back and clean it up later
+// What a single instruction did, lets run_until decide whether to keep going
+enum Step {
+    Continue,
+    Output(usize), // Values delivered, more than one only from extensions
+    Blocked,
+    Halted,
+    Overflow,
+}
+
 impl IntcodeComp {
     pub fn new(prog: &[i64]) -> IntcodeComp {
-        let mem = prog.to_vec(); // Make a mutable clone of the program to work on in local memory
+        IntcodeComp::from_words(prog)
+    }
+}
+
+// Closures etc could make this much much cleaner I might come back and clean it up later
+impl<W: Word> IntcodeComp<W> {
+    // Load an ordinary program into a machine with a different word, IntcodeComp::<i128>::from_prog(&prog)
+    pub fn from_prog(prog: &[i64]) -> IntcodeComp<W> {
+        let words: Vec<W> = prog.iter().map(|&v| W::from_i64(v)).collect();
+        IntcodeComp::from_words(&words)
+    }
+
+    pub fn from_words(prog: &[W]) -> IntcodeComp<W> {
+        let mem = Memory::new(prog); // Make a mutable clone of the program to work on in local memory
         let pc = 0;
         let i = VecDeque::new();
         let o = VecDeque::new();
         let rb = 0;
         IntcodeComp {
-            mem_space: mem,
+            mem,
             program_counter: pc,
             in_buf: i,
             out_buf: o,
             rel_base: rb,
+            overflow: Overflow::Trap,
+            breakpoints: HashSet::new(),
+            watchpoints: HashSet::new(),
+            watch_hit: None,
+            profile: None,
+            icache: Some(DecodeCache::new()),
+            limits: None,
+            epoch: 0,
+            journal: None,
+            extensions: None,
         }
     }
 
-    pub fn _int_mem(&self) -> &Vec<i64> {
-        &self.mem_space
+    // The program image and everything near it, far writes that went to sparse pages aren't included
+    pub fn _int_mem(&self) -> &Vec<W> {
+        self.mem.dense()
     }
 
-    pub fn input(&mut self, i: i64) {
+    // Writes that would take the machine past this many allocated words fail with OutOfMemory
+    pub fn set_memory_limit(&mut self, words: usize) {
+        self.mem.set_limit(words);
+    }
+
+    pub fn memory_limit(&self) -> usize {
+        self.mem.limit()
+    }
+
+    // Words currently allocated, sparse pages included
+    pub fn memory_used(&self) -> usize {
+        self.mem.allocated()
+    }
+
+    pub fn input(&mut self, i: W) {
         self.in_buf.push_back(i);
     }
 
-    pub fn output(&mut self) -> Option<i64> {
+    pub fn output(&mut self) -> Option<W> {
         self.out_buf.pop_front()
     }
 
@@ -92,263 +204,567 @@ impl IntcodeComp {
     }
 
     // Convenience method to run until either a halt command or the core is starved of input
-    pub fn run_all(&mut self) {
-        // starvation must occur twice
-        let mut starved = false;
+    //  Returns Halted or AwaitingInput so callers can tell the two apart, faults come back as Err
+    //  With a budget or loop detection switched on it can also stop with BudgetExhausted or Cycling
+    pub fn run_all(&mut self) -> Result<RunState<W>, IntcodeError> {
+        match self.eval_async() {
+            RunState::Error(e) => Err(e),
+            state => Ok(state),
+        }
+    }
+
+    // Run until the program halts, needs input or faults
+    pub fn eval_async(&mut self) -> RunState<W> {
+        self.run_until(StopOn::Halt)
+    }
+
+    // Run until the program halts, needs input, faults or the stop condition is met
+    pub fn run_until(&mut self, stop: StopOn) -> RunState<W> {
+        // The output queue is just another sink, borrow it out of self for the duration of the run
+        let mut out_buf = mem::take(&mut self.out_buf);
+        if let Some(j) = &mut self.journal {
+            j.set_queued(true);
+        }
+        let state = self.run_device_until(&mut io::Split(&mut VecDeque::new(), &mut out_buf), stop);
+        if let Some(j) = &mut self.journal {
+            j.set_queued(false);
+        }
+        self.out_buf = out_buf;
+        match state {
+            RunState::OutputReady(_) => RunState::OutputReady(self.out_buf.len()),
+            s => s,
+        }
+    }
+
+    // Run with a separate input source and output sink until the program halts, starves or faults
+    pub fn run_with<I: InputSource<W>, O: OutputSink<W>>(
+        &mut self,
+        input: &mut I,
+        output: &mut O,
+    ) -> RunState<W> {
+        self.run_device_until(&mut io::Split(input, output), StopOn::Halt)
+    }
+
+    // Run with a device that is both source and sink (a robot, a screen with a joystick)
+    pub fn attach<D: InputSource<W> + OutputSink<W>>(&mut self, dev: &mut D) -> RunState<W> {
+        self.run_device_until(dev, StopOn::Halt)
+    }
+
+    // Anything already queued with input() is consumed before the device is asked, outputs go straight to
+    //  the device and OutputReady reports how many values were delivered during this call
+    pub fn run_device_until<D: InputSource<W> + OutputSink<W> + ?Sized>(
+        &mut self,
+        dev: &mut D,
+        stop: StopOn,
+    ) -> RunState<W> {
+        let mut executed: u64 = 0;
+        let mut delivered: usize = 0;
         loop {
-            if self.eval_async() {
-                if self.in_buf.is_empty() {
-                    if starved {
-                        println!("intcode CPU input starved in run_all, this is probably not what you want");
-                        break;
-                    } else {
-                        starved = true;
+            if let StopOn::Instructions(n) = stop {
+                if executed >= n {
+                    return RunState::Running;
+                }
+            }
+            // Never stop on the first instruction so execution can resume from a breakpoint
+            if executed > 0
+                && !self.breakpoints.is_empty()
+                && self.breakpoints.contains(&self.program_counter)
+            {
+                return RunState::Breakpoint(self.program_counter);
+            }
+            if let Some(limits) = &mut self.limits {
+                if let Some(b) = limits.exhausted() {
+                    return RunState::BudgetExhausted(b);
+                }
+            }
+            let pc = self.program_counter;
+            if let Some(j) = &mut self.journal {
+                j.begin(pc, self.rel_base);
+            }
+            let result = self.execute(dev);
+            // Halting changes nothing and blocked or faulting instructions didn't happen
+            if let (Some(j), Ok(Step::Continue | Step::Output(_))) = (&mut self.journal, &result) {
+                j.commit();
+            }
+            if let (Some(limits), Ok(Step::Continue | Step::Output(_) | Step::Halted)) =
+                (&mut self.limits, &result)
+            {
+                limits.charge();
+            }
+            // Writes are always the last thing an instruction does so the instruction has completed
+            if let Some(hit) = self.watch_hit.take() {
+                return hit;
+            }
+            match result {
+                Ok(Step::Continue) => (),
+                Ok(Step::Output(n)) => {
+                    delivered += n;
+                    if stop == StopOn::Output {
+                        return RunState::OutputReady(delivered);
                     }
-                } // else continue
-            } else {
-                break; // Completed execution
+                }
+                Ok(Step::Blocked) => return RunState::AwaitingInput,
+                Ok(Step::Halted) => return RunState::Halted,
+                Ok(Step::Overflow) => return RunState::Overflowed(pc),
+                Err(e) => return RunState::Error(e),
+            }
+            executed += 1;
+            // Only a backwards move can close a loop
+            if let Some(limits) = &mut self.limits {
+                if self.program_counter <= pc
+                    && limits.cycling(self.program_counter, self.rel_base, self.epoch)
+                {
+                    return RunState::Cycling(self.program_counter);
+                }
             }
         }
     }
 
-    // Memory access macros
-    // Closures for common repeated operations (using closures to save on suuuper repetitive argument passing)
-    fn addr_mode(dig: Option<&u8>) -> AddressMode {
-        use self::AddressMode::*;
-        match dig {
-            Some(&2) => Relative,
-            Some(&1) => Immediate,
-            Some(&0) | None => Positional,
-            Some(&e) => panic!("Imm bad: {}", e),
+    // What Add and Multiply do when the result doesn't fit, trapping is the default
+    pub fn set_overflow(&mut self, policy: Overflow) {
+        self.overflow = policy;
+    }
+
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    // Copy of the machine with every word widened, breakpoints, budgets and the rest come along but
+    //  the undo journal and extensions (their handlers take the old word) don't. Pick up after
+    //  RunState::Overflowed with this
+    pub fn promote<V: Word + From<W>>(&self) -> IntcodeComp<V> {
+        let widen = |q: &VecDeque<W>| q.iter().cloned().map(V::from).collect();
+        IntcodeComp {
+            mem: self.mem.promote(),
+            program_counter: self.program_counter,
+            in_buf: widen(&self.in_buf),
+            out_buf: widen(&self.out_buf),
+            rel_base: self.rel_base,
+            overflow: self.overflow,
+            breakpoints: self.breakpoints.clone(),
+            watchpoints: self.watchpoints.clone(),
+            watch_hit: None,
+            profile: self.profile.clone(),
+            icache: self.icache.clone(),
+            limits: self.limits.clone(),
+            epoch: self.epoch,
+            journal: None,
+            extensions: None,
         }
     }
 
-    fn op_fetch(&mut self, mode: AddressMode, pc_off: usize) -> i64 {
+    // Caching decoded instructions is on by default, self modifying code is handled either way
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.icache = if enabled {
+            Some(DecodeCache::new())
+        } else {
+            None
+        };
+    }
+
+    // Decode the instruction stored at addr without executing it
+    pub fn decode(&self, addr: usize) -> Result<Instruction, IntcodeError> {
+        decode::decode_with(|a| self.mem.get(a).saturate(), addr)
+    }
+
+    // Memory access macros
+    // Raw instruction word at the program counter
+    fn word(&self) -> i64 {
+        self.peek(self.program_counter).saturate()
+    }
+
+    // Read a memory cell without growing memory, cells past the end read as 0
+    fn peek(&self, addr: usize) -> W {
+        self.mem.get(addr)
+    }
+
+    // Resolve the address a positional or relative parameter points at, operand is 1-based
+    fn resolve(&self, inst: &Instruction, operand: usize) -> Result<usize, IntcodeError> {
+        let (mode, param) = (inst.modes[operand - 1], inst.params[operand - 1]);
+        self.locate(inst.addr, inst.word, operand, mode, param)
+    }
+
+    // Same for a parameter taken apart by hand, pc and word are only for errors
+    fn locate(
+        &self,
+        pc: usize,
+        word: i64,
+        operand: usize,
+        mode: AddressMode,
+        param: i64,
+    ) -> Result<usize, IntcodeError> {
+        let addr = match mode {
+            // For relative, add a relative base register to ptr
+            AddressMode::Relative => param
+                .checked_add(self.rel_base)
+                .ok_or_else(|| overflow_at(pc, word))?,
+            _ => param,
+        };
+        addr.try_into().map_err(|_| IntcodeError::NegativeAddress {
+            pc,
+            word,
+            operand,
+            addr,
+        })
+    }
+
+    fn op_fetch(&self, inst: &Instruction, operand: usize) -> Result<W, IntcodeError> {
         use self::AddressMode::*;
-        match mode {
-            Positional => {
-                let ptr: usize = self.mem_space[self.program_counter + pc_off]
-                    .try_into()
-                    .unwrap();
-                if ptr >= self.mem_space.len() {
-                    // Ptr would access memory not currently allocated, grow our memory space to fit the need initializing new entries to 0
-                    self.mem_space.resize(ptr + 1, 0);
+        match inst.modes[operand - 1] {
+            // Decoding pins anything that doesn't fit an i64 to the limits, go back to memory for those
+            Immediate => match inst.params[operand - 1] {
+                p @ (i64::MIN | i64::MAX) if W::from_i64(p) != self.peek(inst.addr + operand) => {
+                    Ok(self.peek(inst.addr + operand))
                 }
-                self.mem_space[ptr]
-            }
-            Immediate => self.mem_space[self.program_counter + pc_off],
-            Relative => {
-                let ptr: usize = (self.mem_space[self.program_counter + pc_off] + self.rel_base)
-                    .try_into()
-                    .unwrap();
-                // For relative, add a relative base register to ptr
-                if ptr >= self.mem_space.len() {
-                    // Ptr would access memory not currently allocated, grow our memory space to fit the need initializing new entries to 0
-                    self.mem_space.resize(ptr + 1, 0);
-                }
-                self.mem_space[ptr]
+                p => Ok(W::from_i64(p)),
+            },
+            Positional | Relative => {
+                // Memory that was never written reads as 0, nothing needs allocating for a read
+                let ptr = self.resolve(inst, operand)?;
+                Ok(self.mem.get(ptr))
             }
         }
     }
 
     // very similar to op_fetch except for data direction and immediate is not supported
-    fn write_back(&mut self, mode: AddressMode, pc_off: usize, data: i64) {
+    fn write_back(
+        &mut self,
+        inst: &Instruction,
+        operand: usize,
+        data: W,
+    ) -> Result<(), IntcodeError> {
         use self::AddressMode::*;
-        match mode {
-            Positional => {
-                let ptr = self.mem_space[self.program_counter + pc_off] as usize;
-                if ptr >= self.mem_space.len() {
-                    // Ptr would access memory not currently allocated, grow our memory space to fit the need initializing new entries to 0
-                    self.mem_space.resize(ptr + 1, 0);
-                }
-                self.mem_space[ptr] = data;
+        match inst.modes[operand - 1] {
+            Positional | Relative => {
+                let ptr = self.resolve(inst, operand)?;
+                self.store(inst.addr, inst.word, operand, ptr, data)
             }
-            Relative => {
-                let ptr: usize = (self.mem_space[self.program_counter + pc_off] + self.rel_base)
-                    .try_into()
-                    .unwrap();
-                // For relative, add a relative base register to ptr
-                if ptr >= self.mem_space.len() {
-                    // Ptr would access memory not currently allocated, grow our memory space to fit the need initializing new entries to 0
-                    self.mem_space.resize(ptr + 1, 0);
-                }
-                self.mem_space[ptr] = data;
+            Immediate => Err(IntcodeError::ImmediateWrite {
+                pc: inst.addr,
+                word: inst.word,
+                operand,
+            }),
+        }
+    }
+
+    // Write a cell on behalf of the instruction at pc, keeping the cache, watchpoints, loop
+    //  detection and journal up to date
+    fn store(
+        &mut self,
+        pc: usize,
+        word: i64,
+        operand: usize,
+        ptr: usize,
+        data: W,
+    ) -> Result<(), IntcodeError> {
+        let old = self.mem.get(ptr);
+        let changed = old != data;
+        let watched = !self.watchpoints.is_empty() && self.watchpoints.contains(&ptr);
+        let new = if watched { Some(data.clone()) } else { None };
+        // Memory grows as needed up to the limit, a failed write leaves everything as it was
+        let growth = match self.mem.set(ptr, data) {
+            Ok(growth) => growth,
+            Err(_) => {
+                return Err(IntcodeError::OutOfMemory {
+                    pc,
+                    word,
+                    operand,
+                    addr: ptr,
+                    limit: self.mem.limit(),
+                })
             }
-            Immediate => panic!("Attempted to write with immediate mode, this is not allowed"),
         };
+        if growth.is_some() {
+            if let Some(p) = &mut self.profile {
+                p.grow(self.mem.allocated());
+            }
+        }
+        self.invalidate(ptr);
+        if changed {
+            self.epoch += 1;
+        }
+        if let Some(new) = new {
+            self.watch_hit = Some(RunState::Watchpoint {
+                addr: ptr,
+                old: old.clone(),
+                new,
+            });
+        }
+        if let Some(j) = &mut self.journal {
+            j.write(ptr, old, growth);
+        }
+        Ok(())
     }
 
-    // Implementation of the computer generalized, call to evaluate until output, return is true if continued execution is a thing, or false if the program has halted
-    pub fn eval_async(&mut self) -> bool {
-        // Push least significant digit first, then rest into array of digits for decoding
-        fn decompose(n: i64, digits: &mut Vec<u8>) {
-            digits.push((n % 10) as u8);
-            if n >= 10 {
-                decompose(n / 10, digits)
+    // Decode the instruction at the program counter, going through the cache when it's on
+    fn fetch(&mut self) -> Result<Instruction, IntcodeError> {
+        let pc = self.program_counter;
+        if let Some(Some(inst)) = self.icache.as_ref().and_then(|c| c.entries.get(pc)) {
+            return Ok(*inst);
+        }
+        let inst = self.decode(pc)?;
+        // Decoding pins parameters that don't fit an i64 to the limits, as addresses they'd all alias
+        //  the same cell so they fault instead. Checked here so cached instructions are only checked once
+        for (i, (&mode, &param)) in inst.modes.iter().zip(&inst.params).enumerate() {
+            if mode != AddressMode::Immediate
+                && matches!(param, i64::MIN | i64::MAX)
+                && self.peek(pc + 1 + i).to_i64().is_none()
+            {
+                return Err(overflow_at(inst.addr, inst.word));
+            }
+        }
+        // Only the dense region is cached, code out in the sparse pages is decoded every time
+        if let Some(cache) = &mut self.icache {
+            if cache.warmup > 0 {
+                cache.warmup -= 1;
+            } else if pc < self.mem.dense().len() {
+                if cache.entries.len() <= pc {
+                    cache.entries.resize(pc + 1, None);
+                }
+                cache.entries[pc] = Some(inst);
             }
         }
+        Ok(inst)
+    }
 
-        loop {
-            // Break opcode into vec of digits for decoding of modes
-            let mut digits: Vec<u8> = Vec::with_capacity(5); // 2 for opcode, 3 for mode bits
-            decompose(self.mem_space[self.program_counter], &mut digits);
-
-            // use iterator to pop without having to reverse
-            let mut it = digits.iter();
-            let mut opcode: usize = *it.next().unwrap() as usize; // first digit must always exist
-
-            // Second digit may exist
-            opcode += match it.next() {
-                Some(&i) => (i * 10) as usize,
-                None => 0,
-            };
-
-            // Unwrap next 3 if they exist to determine address modes for operands and result
-            let l_imm = IntcodeComp::addr_mode(it.next());
-            let r_imm = IntcodeComp::addr_mode(it.next());
-            let dst_imm = IntcodeComp::addr_mode(it.next());
-
-            // This is the state machine that executes directions, 3 stages for each math-ish instruction, IO is similar but omits one or more steps
-            // -Fetch
-            // -Operate
-            // -Writeback
-            use self::Opcodes::*;
-            match Opcodes::from_usize(opcode) {
-                Add => {
-                    // Operand fetch
-                    let l = self.op_fetch(l_imm, 1);
-                    let r = self.op_fetch(r_imm, 2);
-
-                    // Operate on local "registers"
-                    let result: i64 = l + r;
-
-                    // Writeback
-                    self.write_back(dst_imm, 3, result);
-
-                    // add consumes 4 ints
-                    self.program_counter += 4;
+    // A write to addr changes any instruction that covers it, operands included, the longest
+    //  instructions start 3 words earlier
+    fn invalidate(&mut self, addr: usize) {
+        if let Some(cache) = &mut self.icache {
+            for a in addr.saturating_sub(3)..=addr {
+                if let Some(slot) = cache.entries.get_mut(a) {
+                    *slot = None;
                 }
-                Multiply => {
-                    // Operand fetch
-                    let l = self.op_fetch(l_imm, 1);
-                    let r = self.op_fetch(r_imm, 2);
+            }
+        }
+    }
 
-                    // Operate on local "registers"
-                    let result: i64 = l * r;
+    // Jump targets must land on a real address
+    fn jump_target(&self, target: &W) -> Result<usize, IntcodeError> {
+        let target = target.saturate();
+        target
+            .try_into()
+            .map_err(|_| IntcodeError::NegativeAddress {
+                pc: self.program_counter,
+                word: self.word(),
+                operand: 2,
+                addr: target,
+            })
+    }
+
+    fn profile_branch(&mut self, taken: bool, target: &W) {
+        if let Some(p) = &mut self.profile {
+            p.branch(
+                self.program_counter,
+                taken,
+                target.saturate().max(0) as usize,
+            );
+        }
+    }
+
+    // Add or multiply under the overflow policy, None when the machine has to stop for a wider word
+    fn arith(&self, inst: &Instruction, l: &W, r: &W) -> Result<Option<W>, IntcodeError> {
+        let add = inst.opcode == Opcodes::Add;
+        let exact = if add {
+            l.checked_add(r)
+        } else {
+            l.checked_mul(r)
+        };
+        match (exact, self.overflow) {
+            (Some(v), _) => Ok(Some(v)),
+            (None, Overflow::Wrap) if add => Ok(Some(l.wrapping_add(r))),
+            (None, Overflow::Wrap) => Ok(Some(l.wrapping_mul(r))),
+            (None, Overflow::Promote) => Ok(None),
+            (None, Overflow::Trap) => Err(overflow_at(inst.addr, inst.word)),
+        }
+    }
 
-                    // Writeback
-                    self.write_back(dst_imm, 3, result);
+    // Executes the instruction at the program counter
+    //  Malformed instructions stop execution with an error, the program counter is left pointing at the faulting instruction
+    fn execute<D: InputSource<W> + OutputSink<W> + ?Sized>(
+        &mut self,
+        dev: &mut D,
+    ) -> Result<Step, IntcodeError> {
+        let inst = match self.fetch() {
+            Ok(inst) => inst,
+            Err(e @ IntcodeError::UnknownOpcode { .. }) => return self.execute_extension(dev, e),
+            Err(e) => return Err(e),
+        };
+        let (addr, opcode) = (inst.addr, inst.opcode);
+        let step = self.execute_inst(dev, inst)?;
+        // Only count instructions that happened, a blocked IN runs again from the start on resume
+        if let (Some(p), Step::Continue | Step::Output(_) | Step::Halted) =
+            (&mut self.profile, &step)
+        {
+            p.instruction(addr, opcode);
+        }
+        Ok(step)
+    }
 
-                    // add consumes 4 ints
-                    self.program_counter += 4;
+    // Implementation of the computer generalized, inst is the decoded instruction at the program counter
+    fn execute_inst<D: InputSource<W> + OutputSink<W> + ?Sized>(
+        &mut self,
+        dev: &mut D,
+        inst: Instruction,
+    ) -> Result<Step, IntcodeError> {
+        // This is the state machine that executes directions, 3 stages for each math-ish instruction, IO is similar but omits one or more steps
+        // -Fetch
+        // -Operate
+        // -Writeback
+        use self::Opcodes::*;
+        match inst.opcode {
+            Add => {
+                // Operand fetch
+                let l = self.op_fetch(&inst, 1)?;
+                let r = self.op_fetch(&inst, 2)?;
+
+                // Operate on local "registers"
+                let result = match self.arith(&inst, &l, &r)? {
+                    Some(v) => v,
+                    None => return Ok(Step::Overflow),
+                };
+
+                // Writeback
+                self.write_back(&inst, 3, result)?;
+
+                // add consumes 4 ints
+                self.program_counter += 4;
+            }
+            Multiply => {
+                // Operand fetch
+                let l = self.op_fetch(&inst, 1)?;
+                let r = self.op_fetch(&inst, 2)?;
+
+                // Operate on local "registers"
+                let result = match self.arith(&inst, &l, &r)? {
+                    Some(v) => v,
+                    None => return Ok(Step::Overflow),
+                };
+
+                // Writeback
+                self.write_back(&inst, 3, result)?;
+
+                // add consumes 4 ints
+                self.program_counter += 4;
+            }
+            Input => {
+                // input
+                // Match here only returns if input is needed but not available, to allow calling function to give us more
+                //  The destination is checked before anything is taken (decoding already rejected immediate
+                //  ones) so a faulting instruction leaves the queue alone
+                let ptr = self.resolve(&inst, 1)?;
+                let val = match self.in_buf.pop_front().or_else(|| dev.next_input()) {
+                    Some(val) => val,
+                    None => return Ok(Step::Blocked),
+                };
+                // Running out of memory puts it back on the queue, even one that came from the device
+                if let Err(e) = self.store(inst.addr, inst.word, 1, ptr, val.clone()) {
+                    self.in_buf.push_front(val);
+                    return Err(e);
                 }
-                Input => {
-                    // input
-                    // Match here only returns if input is needed but not available, to allow calling function to give us more
-                    match self.in_buf.pop_front() {
-                        Some(val) => self.write_back(l_imm, 1, val),
-                        None => return true,
-                    };
-
-                    // input consumes 2 ints
-                    self.program_counter += 2;
+                self.epoch += 1; // The queue moved on even if memory didn't change
+                if let Some(j) = &mut self.journal {
+                    j.input(val);
                 }
-                Output => {
-                    // output
-                    let val = self.op_fetch(l_imm, 1);
-                    self.out_buf.push_back(val);
-
-                    // output consumes 2 ints
-                    self.program_counter += 2;
-                    // return true; // comment out so that the CPU only pauses on input
+
+                // input consumes 2 ints
+                self.program_counter += 2;
+            }
+            Output => {
+                // output
+                let val = self.op_fetch(&inst, 1)?;
+                if let Some(j) = &mut self.journal {
+                    j.output();
                 }
-                Jnz => {
-                    // jump if true (if input operand is nonzero)
-                    // Operand fetch, same as math instructions plus logic for jump
-                    let cond = match self.op_fetch(l_imm, 1) {
-                        0 => false,
-                        _ => true, // any nonzero value means jump
-                    };
-                    let j_addr = self.op_fetch(r_imm, 2);
-
-                    // Perform jump or not
-                    if cond {
-                        // Do jump
-                        self.program_counter = j_addr.try_into().unwrap();
-                    } else {
-                        // business as usual
-                        self.program_counter += 3;
-                    }
+                dev.push_output(val);
+
+                // output consumes 2 ints
+                self.program_counter += 2;
+                return Ok(Step::Output(1));
+            }
+            Jnz => {
+                // jump if true (if input operand is nonzero)
+                // Operand fetch, same as math instructions plus logic for jump
+                let cond = !self.op_fetch(&inst, 1)?.is_zero(); // any nonzero value means jump
+                let j_addr = self.op_fetch(&inst, 2)?;
+                self.profile_branch(cond, &j_addr);
+
+                // Perform jump or not
+                if cond {
+                    // Do jump
+                    self.program_counter = self.jump_target(&j_addr)?;
+                } else {
+                    // business as usual
+                    self.program_counter += 3;
                 }
-                Jz => {
-                    // jump if not true (if input operand is zero)
-                    // Operand fetch, same as math instructions plus logic for jump
-                    // Operand fetch, same as math instructions plus logic for jump
-                    let cond = match self.op_fetch(l_imm, 1) {
-                        0 => true, // zero means jump
-                        _ => false,
-                    };
-                    let j_addr = self.op_fetch(r_imm, 2);
-
-                    // Perform jump or not
-                    if cond {
-                        // Do jump
-                        self.program_counter = j_addr.try_into().unwrap();
-                    } else {
-                        // business as usual
-                        self.program_counter += 3;
-                    }
+            }
+            Jz => {
+                // jump if not true (if input operand is zero)
+                // Operand fetch, same as math instructions plus logic for jump
+                let cond = self.op_fetch(&inst, 1)?.is_zero(); // zero means jump
+                let j_addr = self.op_fetch(&inst, 2)?;
+                self.profile_branch(cond, &j_addr);
+
+                // Perform jump or not
+                if cond {
+                    // Do jump
+                    self.program_counter = self.jump_target(&j_addr)?;
+                } else {
+                    // business as usual
+                    self.program_counter += 3;
                 }
-                Comparelt => {
-                    // Less than, write 1 to destination if first op is less than second, else write 0
-                    // Operand fetch
-                    let l = self.op_fetch(l_imm, 1);
-                    let r = self.op_fetch(r_imm, 2);
+            }
+            Comparelt => {
+                // Less than, write 1 to destination if first op is less than second, else write 0
+                // Operand fetch
+                let l = self.op_fetch(&inst, 1)?;
+                let r = self.op_fetch(&inst, 2)?;
 
-                    // Operate on local "registers"
-                    let result: i64 = if l < r { 1 } else { 0 };
+                // Operate on local "registers"
+                let result = W::from_i64(if l < r { 1 } else { 0 });
 
-                    // Writeback
-                    self.write_back(dst_imm, 3, result);
+                // Writeback
+                self.write_back(&inst, 3, result)?;
 
-                    // < consumes 4 ints
-                    self.program_counter += 4;
-                }
-                Compareq => {
-                    // equals, write 1 to destination if first op == second, else write 0
-                    // Operand fetch
-                    // Operand fetch
-                    let l = self.op_fetch(l_imm, 1);
-                    let r = self.op_fetch(r_imm, 2);
+                // < consumes 4 ints
+                self.program_counter += 4;
+            }
+            Compareq => {
+                // equals, write 1 to destination if first op == second, else write 0
+                // Operand fetch
+                let l = self.op_fetch(&inst, 1)?;
+                let r = self.op_fetch(&inst, 2)?;
 
-                    // Operate on local "registers"
-                    let result: i64 = if l == r { 1 } else { 0 };
+                // Operate on local "registers"
+                let result = W::from_i64(if l == r { 1 } else { 0 });
 
-                    // Writeback
-                    self.write_back(dst_imm, 3, result);
+                // Writeback
+                self.write_back(&inst, 3, result)?;
 
-                    // == consumes 4 ints
-                    self.program_counter += 4;
-                }
-                Rbo => {
-                    // Adjust the relative base offset by this ops only parameter
-                    self.rel_base += self.op_fetch(l_imm, 1);
-                    self.program_counter += 2;
-                }
-                Halt => break,
+                // == consumes 4 ints
+                self.program_counter += 4;
             }
+            Rbo => {
+                // Adjust the relative base offset by this ops only parameter, it's an address so it
+                //  has to stay machine sized whatever the overflow policy
+                let offset = self.op_fetch(&inst, 1)?.to_i64();
+                self.rel_base = offset
+                    .and_then(|o| self.rel_base.checked_add(o))
+                    .ok_or_else(|| overflow_at(inst.addr, inst.word))?;
+                self.program_counter += 2;
+            }
+            Halt => return Ok(Step::Halted),
         }
-        false
+        Ok(Step::Continue)
     }
 }
 
+// A value that had to fit somewhere didn't, see IntcodeError::Overflow
+pub(crate) fn overflow_at(pc: usize, word: i64) -> IntcodeError {
+    IntcodeError::Overflow { pc, word }
+}
+
+// Panics with the position of the problem if the program doesn't load, load::from_file to handle that
 pub fn prog_from_file(path: &str) -> Vec<i64> {
-    let buf = &fs::read(path).unwrap();
-    std::str::from_utf8(buf)
-        .unwrap()
-        .trim()
-        .trim_end_matches(',')
-        .split(',')
-        .map(|x| x.parse::<i64>().unwrap())
-        .collect()
+    load::from_file(path).unwrap_or_else(|e| panic!("{}: {}", path, e))
 }
@@ -0,0 +1,274 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+
+// Reading programs in from wherever they live. The text form is what the puzzles hand out, comma
+//  separated numbers, but it's forgiving about layout so a program can be written out by hand:
+//
+//  ; day 2 with the noun and verb patched in
+//  1, 12, 2, 3,
+//  1, 1, 2, 3,     # comments run to the end of the line
+//  99,
+//
+//  Values can be spread over any number of lines as long as the commas are kept, a trailing comma is
+//  fine and both ; and # start a comment. There's also a compact binary image, see to_image, which the
+//  readers recognize on their own
+
+// Starts every binary image. Text programs can't begin with a zero byte so the two never mix up
+const MAGIC: &[u8; 4] = b"\0ICI";
+const VERSION: u8 = 1;
+
+// What was wrong with a text program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextError {
+    // Not valid UTF-8, the position is where the bad bytes start
+    NotUtf8,
+    BadNumber(String),
+    // A comma with nothing before it
+    MissingValue,
+    // Two values with only whitespace between them
+    MissingComma,
+    // Nothing but whitespace and comments
+    Empty,
+}
+
+// What was wrong with a binary image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageError {
+    BadMagic,
+    Version(u8),
+    Truncated,
+    // A varint too long for 64 bits
+    Overlong,
+    TrailingBytes,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    // line and col are 1-based, col counts characters and points at the start of the problem
+    Text {
+        line: usize,
+        col: usize,
+        kind: TextError,
+    },
+    // offset is the byte the problem was found at
+    Image {
+        offset: usize,
+        kind: ImageError,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{}", e),
+            LoadError::Text { line, col, kind } => {
+                write!(f, "line {}, col {}: ", line, col)?;
+                match kind {
+                    TextError::NotUtf8 => write!(f, "not valid UTF-8"),
+                    TextError::BadNumber(s) => write!(f, "can't parse {} as a number", s),
+                    TextError::MissingValue => write!(f, "expected a value before the comma"),
+                    TextError::MissingComma => write!(f, "expected a comma before the value"),
+                    TextError::Empty => write!(f, "no program"),
+                }
+            }
+            LoadError::Image { offset, kind } => {
+                write!(f, "byte {} of image: ", offset)?;
+                match kind {
+                    ImageError::BadMagic => write!(f, "not an intcode image"),
+                    ImageError::Version(v) => write!(f, "unknown image version {}", v),
+                    ImageError::Truncated => write!(f, "image ends early"),
+                    ImageError::Overlong => write!(f, "word doesn't fit in 64 bits"),
+                    ImageError::TrailingBytes => write!(f, "extra bytes after the last word"),
+                }
+            }
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+// Parse a text program
+pub fn parse(src: &str) -> Result<Vec<i64>, LoadError> {
+    let mut prog = Vec::new();
+    // Whether a value has been seen since the last comma
+    let mut have_value = false;
+    for (num, line) in src.lines().enumerate() {
+        let err = |i: usize, kind| LoadError::Text {
+            line: num + 1,
+            col: line[..i].chars().count() + 1,
+            kind,
+        };
+        let code = match line.find([';', '#']) {
+            Some(i) => &line[..i],
+            None => line,
+        };
+
+        let mut i = 0;
+        while i < code.len() {
+            let rest = &code[i..];
+            let c = rest.chars().next().unwrap();
+            if c.is_whitespace() {
+                i += c.len_utf8();
+            } else if c == ',' {
+                if !have_value {
+                    return Err(err(i, TextError::MissingValue));
+                }
+                have_value = false;
+                i += 1;
+            } else {
+                let len = rest
+                    .find(|c: char| c == ',' || c.is_whitespace())
+                    .unwrap_or(rest.len());
+                let token = &rest[..len];
+                if have_value {
+                    return Err(err(i, TextError::MissingComma));
+                }
+                match token.parse::<i64>() {
+                    Ok(val) => prog.push(val),
+                    Err(_) => return Err(err(i, TextError::BadNumber(token.to_string()))),
+                }
+                have_value = true;
+                i += len;
+            }
+        }
+    }
+    if prog.is_empty() {
+        return Err(LoadError::Text {
+            line: 1,
+            col: 1,
+            kind: TextError::Empty,
+        });
+    }
+    Ok(prog)
+}
+
+// Either form, binary images are told apart by their header
+pub fn from_bytes(bytes: &[u8]) -> Result<Vec<i64>, LoadError> {
+    if bytes.starts_with(MAGIC) {
+        return from_image(bytes);
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(src) => parse(src),
+        Err(e) => {
+            // Everything up to the bad bytes is fine, count lines and columns in that
+            let good = std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap();
+            let line = good.matches('\n').count() + 1;
+            let col = good.rsplit('\n').next().unwrap().chars().count() + 1;
+            Err(LoadError::Text {
+                line,
+                col,
+                kind: TextError::NotUtf8,
+            })
+        }
+    }
+}
+
+pub fn from_reader<R: Read>(mut reader: R) -> Result<Vec<i64>, LoadError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    from_bytes(&bytes)
+}
+
+pub fn from_stdin() -> Result<Vec<i64>, LoadError> {
+    from_reader(io::stdin().lock())
+}
+
+pub fn from_file(path: &str) -> Result<Vec<i64>, LoadError> {
+    from_bytes(&fs::read(path)?)
+}
+
+// The binary image: the magic, a version byte, the number of words and then the words themselves.
+//  Numbers are LEB128 varints, words zigzag encoded first so small negative values stay short.
+//  Puzzle programs come out at around half the size of their text
+pub fn to_image(prog: &[i64]) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    push_varint(&mut bytes, prog.len() as u64);
+    for &word in prog {
+        push_varint(&mut bytes, ((word << 1) ^ (word >> 63)) as u64);
+    }
+    bytes
+}
+
+pub fn save_image(path: &str, prog: &[i64]) -> io::Result<()> {
+    fs::write(path, to_image(prog))
+}
+
+pub fn from_image(bytes: &[u8]) -> Result<Vec<i64>, LoadError> {
+    let err = |offset, kind| LoadError::Image { offset, kind };
+    if !bytes.starts_with(MAGIC) {
+        return Err(err(0, ImageError::BadMagic));
+    }
+    let mut pos = MAGIC.len();
+    match bytes.get(pos) {
+        Some(&VERSION) => pos += 1,
+        Some(&v) => return Err(err(pos, ImageError::Version(v))),
+        None => return Err(err(pos, ImageError::Truncated)),
+    }
+
+    let len = read_varint(bytes, &mut pos)?;
+    // Every word takes at least a byte, don't trust the count any further than that
+    let mut prog = Vec::with_capacity((len as usize).min(bytes.len() - pos));
+    for _ in 0..len {
+        let zz = read_varint(bytes, &mut pos)?;
+        prog.push((zz >> 1) as i64 ^ -((zz & 1) as i64));
+    }
+    if pos != bytes.len() {
+        return Err(err(pos, ImageError::TrailingBytes));
+    }
+    Ok(prog)
+}
+
+fn push_varint(bytes: &mut Vec<u8>, mut val: u64) {
+    while val >= 0x80 {
+        bytes.push(val as u8 | 0x80);
+        val >>= 7;
+    }
+    bytes.push(val as u8);
+}
+
+// Read one varint at pos and move past it
+fn read_varint(bytes: &[u8], pos: &mut usize) -> Result<u64, LoadError> {
+    let start = *pos;
+    let mut val = 0u64;
+    for shift in (0..64).step_by(7) {
+        let b = match bytes.get(*pos) {
+            Some(&b) => b,
+            None => {
+                return Err(LoadError::Image {
+                    offset: *pos,
+                    kind: ImageError::Truncated,
+                })
+            }
+        };
+        *pos += 1;
+        // The tenth byte only has room for the top bit
+        if shift == 63 && b > 1 {
+            break;
+        }
+        val |= u64::from(b & 0x7f) << shift;
+        if b & 0x80 == 0 {
+            return Ok(val);
+        }
+    }
+    Err(LoadError::Image {
+        offset: start,
+        kind: ImageError::Overlong,
+    })
+}
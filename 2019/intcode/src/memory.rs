@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::Word;
+
+// Words per sparse page
+pub const PAGE_SIZE: usize = 512;
+// Default cap on allocated words, 128MiB worth of i64
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 24;
+// Writes this close past the end of the dense region grow it instead of starting a page
+const GROW_SLACK: usize = 4 * PAGE_SIZE;
+
+// Machine memory, the program image and anything written just past it live in one dense vector,
+//  far away addresses get pages of their own so a single write to 10^12 costs one page, not terabytes
+//  Cells that were never written read as 0 and reading never allocates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Memory<W> {
+    dense: Vec<W>,
+    pages: HashMap<usize, Box<[W]>>, // Keyed on page number (addr / PAGE_SIZE)
+    limit: usize,
+}
+
+// Writing would allocate more than the limit allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct OutOfMemory;
+
+// How a write changed the layout, enough for shrink to put it back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Growth {
+    // The dense region was extended from len words, taking in these pages
+    Dense { len: usize, swallowed: Vec<usize> },
+    Page(usize),
+}
+
+impl<W: Word> Memory<W> {
+    pub fn new(prog: &[W]) -> Memory<W> {
+        Memory {
+            dense: prog.to_vec(),
+            pages: HashMap::new(),
+            limit: DEFAULT_MEMORY_LIMIT,
+        }
+    }
+
+    pub fn dense(&self) -> &Vec<W> {
+        &self.dense
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    // Words currently allocated, dense region plus every page
+    pub fn allocated(&self) -> usize {
+        self.dense.len() + self.pages.len() * PAGE_SIZE
+    }
+
+    pub fn get(&self, addr: usize) -> W {
+        if addr < self.dense.len() {
+            return self.dense[addr].clone();
+        }
+        match self.pages.get(&(addr / PAGE_SIZE)) {
+            Some(page) => page[addr % PAGE_SIZE].clone(),
+            None => W::default(),
+        }
+    }
+
+    // Returns how memory grew to make room, None if the cell was already there
+    pub fn set(&mut self, addr: usize, val: W) -> Result<Option<Growth>, OutOfMemory> {
+        if addr < self.dense.len() {
+            self.dense[addr] = val;
+            return Ok(None);
+        }
+        let page = addr / PAGE_SIZE;
+        if let Some(p) = self.pages.get_mut(&page) {
+            p[addr % PAGE_SIZE] = val;
+            return Ok(None);
+        }
+        if val.is_zero() {
+            return Ok(None); // Unallocated cells already read as 0
+        }
+
+        if addr - self.dense.len() < GROW_SLACK {
+            let len = self.dense.len();
+            let swallowed = self.grow_dense(addr + 1)?;
+            self.dense[addr] = val;
+            Ok(Some(Growth::Dense { len, swallowed }))
+        } else {
+            if self.allocated() + PAGE_SIZE > self.limit {
+                return Err(OutOfMemory);
+            }
+            let mut p = vec![W::default(); PAGE_SIZE].into_boxed_slice();
+            p[addr % PAGE_SIZE] = val;
+            self.pages.insert(page, p);
+            Ok(Some(Growth::Page(page)))
+        }
+    }
+
+    // Undo a growth set reported. Cells it added go away, so they should already be back to 0
+    pub fn shrink(&mut self, growth: Growth) {
+        match growth {
+            Growth::Page(p) => {
+                self.pages.remove(&p);
+            }
+            Growth::Dense { len, swallowed } => {
+                // Swallowed pages take their cells back out of the dense region
+                for p in swallowed {
+                    let base = p * PAGE_SIZE;
+                    let mut page = vec![W::default(); PAGE_SIZE].into_boxed_slice();
+                    for (i, cell) in page.iter_mut().enumerate().skip(len.saturating_sub(base)) {
+                        *cell = self.dense[base + i].clone();
+                    }
+                    self.pages.insert(p, page);
+                }
+                self.dense.truncate(len);
+            }
+        }
+    }
+
+    // Extend the dense region to at least len words, swallowing any pages it runs into. Returns the
+    //  pages it swallowed
+    fn grow_dense(&mut self, len: usize) -> Result<Vec<usize>, OutOfMemory> {
+        let old = self.dense.len();
+        // Only called for an address whose page doesn't exist, so no page straddles the new end
+        let swallowed: Vec<usize> = self
+            .pages
+            .keys()
+            .copied()
+            .filter(|&p| p * PAGE_SIZE < len)
+            .collect();
+        let after = len + (self.pages.len() - swallowed.len()) * PAGE_SIZE;
+        if after > self.limit {
+            return Err(OutOfMemory);
+        }
+
+        self.dense.resize(len, W::default());
+        for &p in &swallowed {
+            let page = self.pages.remove(&p).unwrap();
+            // The start of a page can overlap the old dense region, those cells were never used
+            let base = p * PAGE_SIZE;
+            for (i, val) in page
+                .into_vec()
+                .into_iter()
+                .enumerate()
+                .skip(old.saturating_sub(base))
+            {
+                self.dense[base + i] = val;
+            }
+        }
+        Ok(swallowed)
+    }
+
+    // Pages as (base address, words) in address order, for snapshots
+    pub fn pages(&self) -> BTreeMap<usize, Vec<W>> {
+        self.pages
+            .iter()
+            .map(|(&p, words)| (p * PAGE_SIZE, words.to_vec()))
+            .collect()
+    }
+
+    // Rebuild from a dense image and pages taken with pages(), the limit is left alone
+    pub fn restore(&mut self, dense: &[W], pages: &BTreeMap<usize, Vec<W>>) {
+        self.dense = dense.to_vec();
+        self.pages.clear();
+        for (&base, words) in pages {
+            for (i, val) in words.iter().enumerate() {
+                let addr = base + i;
+                if addr < self.dense.len() {
+                    self.dense[addr] = val.clone();
+                } else if !val.is_zero() {
+                    self.pages
+                        .entry(addr / PAGE_SIZE)
+                        .or_insert_with(|| vec![W::default(); PAGE_SIZE].into_boxed_slice())
+                        [addr % PAGE_SIZE] = val.clone();
+                }
+            }
+        }
+    }
+
+    // Same layout and limit with every word widened
+    pub fn promote<V: Word + From<W>>(&self) -> Memory<V> {
+        let widen = |words: &[W]| words.iter().cloned().map(V::from).collect::<Vec<V>>();
+        Memory {
+            dense: widen(&self.dense),
+            pages: self
+                .pages
+                .iter()
+                .map(|(&p, words)| (p, widen(words).into_boxed_slice()))
+                .collect(),
+            limit: self.limit,
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{IntcodeComp, IntcodeError, RunState};
+
+// A message between nodes in packet mode, every node output is grouped into (dest, x, y) triples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    pub dest: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+// How values move between nodes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Topology {
+    // Outputs are (address, x, y) triples, x and y are queued on the addressed node
+    //  Packets to addresses without a node go to the monitor
+    Packets,
+    // Every output of node i is copied to each node in links[i], nodes without links keep their
+    //  outputs in their own output queue for the caller to collect
+    Links(Vec<Vec<usize>>),
+}
+
+impl Topology {
+    // 0 -> 1 -> .. -> n-1, the last node keeps its outputs
+    pub fn pipeline(n: usize) -> Topology {
+        Topology::Links(
+            (0..n)
+                .map(|i| if i + 1 < n { vec![i + 1] } else { vec![] })
+                .collect(),
+        )
+    }
+
+    // Pipeline with the last node feeding back into the first
+    pub fn ring(n: usize) -> Topology {
+        Topology::Links((0..n).map(|i| vec![(i + 1) % n]).collect())
+    }
+
+    // Everything from one node is copied to all the others, which keep their own outputs
+    pub fn broadcast(n: usize, from: usize) -> Topology {
+        Topology::Links(
+            (0..n)
+                .map(|i| {
+                    if i == from {
+                        (0..n).filter(|&j| j != from).collect()
+                    } else {
+                        vec![]
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+// Things the monitor gets to see
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorEvent {
+    // Packet addressed to a node that doesn't exist
+    Packet(Packet),
+    // A full round went by without a single output from any live node
+    Idle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorAction {
+    Continue,
+    // Queue x and y on the node the packet is addressed to
+    Send(Packet),
+    Stop,
+}
+
+// Why Network::run returned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetState {
+    // Every node halted
+    Halted,
+    // No live node can make progress and nodes are being fed the idle input
+    Idle,
+    // No live node can make progress and there is no idle input to wake them
+    Deadlock,
+    // The monitor asked to stop
+    Stopped,
+    // A node faulted, the rest of the network is left as it was
+    Error(usize, IntcodeError),
+    // A node stopped for a debugger reason (breakpoint, watchpoint)
+    Interrupted(usize, RunState),
+}
+
+type Monitor<'a> = Box<dyn FnMut(MonitorEvent) -> MonitorAction + 'a>;
+
+// N intcode machines with a queue each, run round robin with their outputs routed by the topology
+pub struct Network<'a> {
+    nodes: Vec<IntcodeComp>,
+    halted: Vec<bool>,
+    last: Vec<Option<i64>>,
+    topology: Topology,
+    idle_input: Option<i64>,
+    monitor: Option<Monitor<'a>>,
+}
+
+impl<'a> Network<'a> {
+    // n copies of the same program
+    pub fn new(prog: &[i64], n: usize, topology: Topology) -> Network<'a> {
+        Network::from_nodes(vec![IntcodeComp::new(prog); n], topology)
+    }
+
+    pub fn from_nodes(nodes: Vec<IntcodeComp>, topology: Topology) -> Network<'a> {
+        let n = nodes.len();
+        Network {
+            nodes,
+            halted: vec![false; n],
+            last: vec![None; n],
+            topology,
+            idle_input: None,
+            monitor: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, i: usize) -> &IntcodeComp {
+        &self.nodes[i]
+    }
+
+    pub fn node_mut(&mut self, i: usize) -> &mut IntcodeComp {
+        &mut self.nodes[i]
+    }
+
+    // Last value node i produced, whether it was routed or kept
+    pub fn last_output(&self, i: usize) -> Option<i64> {
+        self.last[i]
+    }
+
+    // Queue a value on a node (initial configuration like phase settings or network addresses)
+    pub fn send(&mut self, node: usize, val: i64) {
+        self.nodes[node].input(val);
+    }
+
+    // Value handed to a node that asks for input with nothing queued, e.g. -1 for a NIC polling for packets
+    pub fn set_idle_input(&mut self, val: Option<i64>) {
+        self.idle_input = val;
+    }
+
+    pub fn set_monitor<F: FnMut(MonitorEvent) -> MonitorAction + 'a>(&mut self, monitor: F) {
+        self.monitor = Some(Box::new(monitor));
+    }
+
+    // Returns false if the monitor asked to stop
+    fn deliver(&mut self, p: Packet) -> bool {
+        if p.dest >= 0 && (p.dest as usize) < self.nodes.len() {
+            let node = &mut self.nodes[p.dest as usize];
+            node.input(p.x);
+            node.input(p.y);
+            return true;
+        }
+        self.notify(MonitorEvent::Packet(p)) != Some(MonitorAction::Stop)
+    }
+
+    // Pass an event to the monitor and act on the answer, None when there is no monitor
+    fn notify(&mut self, event: MonitorEvent) -> Option<MonitorAction> {
+        let action = (self.monitor.as_mut()?)(event);
+        if let MonitorAction::Send(p) = action {
+            // Packets the monitor sends to nowhere are dropped rather than looping back to it
+            if p.dest < 0 || (p.dest as usize) >= self.nodes.len() {
+                return Some(MonitorAction::Continue);
+            }
+            self.deliver(p);
+        }
+        Some(action)
+    }
+
+    // Move whatever node i produced, returns false if the monitor asked to stop
+    fn route(&mut self, i: usize) -> bool {
+        match &self.topology {
+            Topology::Packets => {
+                while self.nodes[i].output_available() >= 3 {
+                    let node = &mut self.nodes[i];
+                    let p = Packet {
+                        dest: node.output().unwrap(),
+                        x: node.output().unwrap(),
+                        y: node.output().unwrap(),
+                    };
+                    if !self.deliver(p) {
+                        return false;
+                    }
+                }
+            }
+            Topology::Links(links) => {
+                if links[i].is_empty() {
+                    return true; // Sink node, outputs stay put
+                }
+                let targets = links[i].clone();
+                while let Some(val) = self.nodes[i].output() {
+                    for &t in &targets {
+                        self.nodes[t].input(val);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    // Run every node in turn until the whole network halts, goes idle or the monitor stops it
+    pub fn run(&mut self) -> NetState {
+        loop {
+            let mut traffic = false;
+            for i in 0..self.nodes.len() {
+                if self.halted[i] {
+                    continue;
+                }
+                let node = &mut self.nodes[i];
+                if let Some(idle) = self.idle_input {
+                    if node.input_queue().is_empty() {
+                        node.input(idle);
+                    }
+                }
+                let before = node.output_available();
+                let state = node.eval_async();
+                if let Some(&v) = node.output_queue().back() {
+                    if node.output_available() > before {
+                        traffic = true;
+                        self.last[i] = Some(v);
+                    }
+                }
+                match state {
+                    RunState::Halted => self.halted[i] = true,
+                    RunState::AwaitingInput => (),
+                    RunState::Error(e) => return NetState::Error(i, e),
+                    s => return NetState::Interrupted(i, s),
+                }
+                if !self.route(i) {
+                    return NetState::Stopped;
+                }
+            }
+
+            if self.halted.iter().all(|&h| h) {
+                return NetState::Halted;
+            }
+            if !traffic {
+                // Everyone is waiting on everyone else, give the monitor a chance to kick things off
+                match self.notify(MonitorEvent::Idle) {
+                    Some(MonitorAction::Send(_)) => continue,
+                    Some(MonitorAction::Stop) => return NetState::Stopped,
+                    Some(MonitorAction::Continue) | None => {
+                        return match self.idle_input {
+                            Some(_) => NetState::Idle,
+                            None => NetState::Deadlock,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
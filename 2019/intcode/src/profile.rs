@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{IntcodeComp, Opcodes, Word};
+
+// Taken/not taken counts for one conditional jump
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BranchStats {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+// A range of code closed by a backward jump, start is the jump target and end the last word of the jump
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HotLoop {
+    pub start: usize,
+    pub end: usize,
+    // Times the backward jump was taken
+    pub iterations: u64,
+    // Instructions executed inside the range, nested loops are counted in every loop containing them
+    pub instructions: u64,
+}
+
+// Execution statistics gathered while profiling is switched on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub instructions: u64,
+    pub opcodes: HashMap<Opcodes, u64>,
+    // Extension opcodes by code
+    pub extensions: HashMap<i64, u64>,
+    // Executions per instruction address
+    pub addresses: HashMap<usize, u64>,
+    // Keyed on the address of the Jnz/Jz
+    pub branches: HashMap<usize, BranchStats>,
+    // Most words ever allocated, and how many writes had to allocate to get there
+    pub mem_high_water: usize,
+    pub mem_grows: u64,
+    // Taken jumps that went backwards (or to themselves), keyed on (jump address, target)
+    back_edges: HashMap<(usize, usize), u64>,
+    // Last thing counted was a HLT, running a halted machine again doesn't execute anything
+    halted: bool,
+}
+
+impl Profile {
+    pub(crate) fn instruction(&mut self, addr: usize, op: Opcodes) {
+        if op == Opcodes::Halt && self.halted {
+            return;
+        }
+        self.halted = op == Opcodes::Halt;
+        self.instructions += 1;
+        *self.opcodes.entry(op).or_insert(0) += 1;
+        *self.addresses.entry(addr).or_insert(0) += 1;
+    }
+
+    pub(crate) fn extension(&mut self, addr: usize, code: i64) {
+        self.halted = false;
+        self.instructions += 1;
+        *self.extensions.entry(code).or_insert(0) += 1;
+        *self.addresses.entry(addr).or_insert(0) += 1;
+    }
+
+    pub(crate) fn branch(&mut self, addr: usize, taken: bool, target: usize) {
+        let stats = self.branches.entry(addr).or_default();
+        if taken {
+            stats.taken += 1;
+            if target <= addr {
+                *self.back_edges.entry((addr, target)).or_insert(0) += 1;
+            }
+        } else {
+            stats.not_taken += 1;
+        }
+    }
+
+    pub(crate) fn grow(&mut self, size: usize) {
+        self.mem_grows += 1;
+        self.mem_high_water = self.mem_high_water.max(size);
+    }
+
+    // Loops found through their backward jumps, busiest first
+    pub fn hot_loops(&self) -> Vec<HotLoop> {
+        let mut loops: Vec<HotLoop> = self
+            .back_edges
+            .iter()
+            .map(|(&(from, to), &iterations)| {
+                let end = from + 2; // Conditional jumps are 3 words long
+                HotLoop {
+                    start: to,
+                    end,
+                    iterations,
+                    instructions: self
+                        .addresses
+                        .iter()
+                        .filter(|(&a, _)| a >= to && a <= end)
+                        .map(|(_, &n)| n)
+                        .sum(),
+                }
+            })
+            .collect();
+        loops.sort_by(|a, b| {
+            b.instructions
+                .cmp(&a.instructions)
+                .then(a.start.cmp(&b.start))
+        });
+        loops
+    }
+
+    // Busiest addresses first, ties in address order
+    pub fn hot_addresses(&self) -> Vec<(usize, u64)> {
+        let mut addrs: Vec<(usize, u64)> = self.addresses.iter().map(|(&a, &n)| (a, n)).collect();
+        addrs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        addrs
+    }
+}
+
+// Human readable report, only the top 10 of the long lists are shown
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const TOP: usize = 10;
+        let pct = |n: u64| 100.0 * n as f64 / self.instructions.max(1) as f64;
+
+        writeln!(f, "{} instructions executed", self.instructions)?;
+        writeln!(
+            f,
+            "memory high water {} words ({} grows)",
+            self.mem_high_water, self.mem_grows
+        )?;
+
+        writeln!(f, "\nopcodes:")?;
+        let mut ops: Vec<(&Opcodes, &u64)> = self.opcodes.iter().collect();
+        ops.sort_by(|a, b| b.1.cmp(a.1).then(a.0.code().cmp(&b.0.code())));
+        for (op, &n) in ops {
+            writeln!(f, "  {:<4} {:>12} {:>6.2}%", op.mnemonic(), n, pct(n))?;
+        }
+        let mut exts: Vec<(&i64, &u64)> = self.extensions.iter().collect();
+        exts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (code, &n) in exts {
+            writeln!(f, "  x{:<3} {:>12} {:>6.2}%", code, n, pct(n))?;
+        }
+
+        writeln!(f, "\nhot loops:")?;
+        for l in self.hot_loops().iter().take(TOP) {
+            writeln!(
+                f,
+                "  {:>6}..{:<6} {:>12} instructions {:>6.2}% {:>10} iterations",
+                l.start,
+                l.end,
+                l.instructions,
+                pct(l.instructions),
+                l.iterations
+            )?;
+        }
+
+        writeln!(f, "\nhot addresses:")?;
+        for (addr, n) in self.hot_addresses().into_iter().take(TOP) {
+            writeln!(f, "  {:>6} {:>12} {:>6.2}%", addr, n, pct(n))?;
+        }
+
+        writeln!(f, "\nbranches:")?;
+        let mut branches: Vec<(&usize, &BranchStats)> = self.branches.iter().collect();
+        branches.sort_by(|a, b| {
+            let total = |s: &BranchStats| s.taken + s.not_taken;
+            total(b.1).cmp(&total(a.1)).then(a.0.cmp(b.0))
+        });
+        for (addr, s) in branches.into_iter().take(TOP) {
+            writeln!(
+                f,
+                "  {:>6} taken {:>10} not taken {:>10}",
+                addr, s.taken, s.not_taken
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Profiling is off by default so the interpreter only pays for it when asked
+impl<W: Word> IntcodeComp<W> {
+    // Start collecting statistics, clears any previous profile
+    pub fn enable_profiling(&mut self) {
+        self.profile = Some(Box::new(Profile {
+            mem_high_water: self.mem.allocated(),
+            ..Profile::default()
+        }));
+    }
+
+    // Stop collecting and hand back what was gathered
+    pub fn disable_profiling(&mut self) -> Option<Profile> {
+        self.profile.take().map(|p| *p)
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_deref()
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{IntcodeComp, Word};
+
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::fs;
+#[cfg(feature = "serde")]
+use std::io;
+
+// Complete execution state of a machine, enough to resume it exactly where it left off
+//  Debugger state (breakpoints and watchpoints) is deliberately not part of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Snapshot<W = i64> {
+    pub mem_space: Vec<W>,
+    // Sparse pages by base address, saves from before paged memory existed have none
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pages: BTreeMap<usize, Vec<W>>,
+    pub program_counter: usize,
+    pub rel_base: i64,
+    pub in_buf: VecDeque<W>,
+    pub out_buf: VecDeque<W>,
+}
+
+impl<W: Word> IntcodeComp<W> {
+    pub fn snapshot(&self) -> Snapshot<W> {
+        Snapshot {
+            mem_space: self.mem.dense().clone(),
+            pages: self.mem.pages(),
+            program_counter: self.program_counter,
+            rel_base: self.rel_base,
+            in_buf: self.in_buf.clone(),
+            out_buf: self.out_buf.clone(),
+        }
+    }
+
+    // Rewind (or fast forward) to a snapshot taken from any machine
+    pub fn restore(&mut self, snap: &Snapshot<W>) {
+        self.mem.restore(&snap.mem_space, &snap.pages);
+        self.set_decode_cache(self.icache.is_some()); // Whatever was cached belongs to the old memory
+        self.program_counter = snap.program_counter;
+        self.rel_base = snap.rel_base;
+        self.in_buf = snap.in_buf.clone();
+        self.out_buf = snap.out_buf.clone();
+        self.epoch += 1; // Loop detection mustn't match states from before the jump
+        if let Some(j) = &self.journal {
+            self.enable_journal(j.capacity()); // Nor can the journal undo its way back across it
+        }
+    }
+
+    // Build a fresh machine from a snapshot
+    pub fn from_snapshot(snap: &Snapshot<W>) -> IntcodeComp<W> {
+        let mut comp = IntcodeComp::from_words(&[]);
+        comp.restore(snap);
+        comp
+    }
+}
+
+// On disk format is plain JSON so saves stay readable and diffable
+#[cfg(feature = "serde")]
+impl<W: Word + Serialize + DeserializeOwned> Snapshot<W> {
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    // Load a save from a machine with a wider word, plain load() is for the usual i64 ones
+    pub fn load_words(path: &str) -> io::Result<Snapshot<W>> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Snapshot {
+    pub fn load(path: &str) -> io::Result<Snapshot> {
+        Snapshot::load_words(path)
+    }
+}
@@ -0,0 +1,571 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::decode::{decode_with, AddressMode, Instruction, Opcodes};
+use crate::IntcodeError;
+
+// Symbolic evaluation. Chosen memory cells and inputs start out as unknowns and every value is kept as
+//  a linear expression over them, so a program that only adds and multiplies (day 2) ends up with
+//  its answer as a formula that can be solved instead of searched. Anything that can't stay linear
+//  (unknown * unknown, compares, reads through an unknown address) becomes NonLinear, which is only an
+//  error if the value asked about depends on it. Jumps have to be decidable, there's one path only
+
+// Default cap on instructions, concrete loops run for real and might never end
+const MAX_STEPS: u64 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Unknown {
+    // Initial contents of a memory cell
+    Cell(usize),
+    // The nth value handed to the program as input, counting from 0
+    Input(usize),
+}
+
+impl fmt::Display for Unknown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unknown::Cell(addr) => write!(f, "[{}]", addr),
+            Unknown::Input(n) => write!(f, "in{}", n),
+        }
+    }
+}
+
+// constant + sum of coefficient * unknown, unknowns with a zero coefficient are never stored
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expr {
+    pub constant: i64,
+    pub terms: BTreeMap<Unknown, i64>,
+}
+
+impl Expr {
+    pub fn constant(c: i64) -> Expr {
+        Expr {
+            constant: c,
+            terms: BTreeMap::new(),
+        }
+    }
+
+    pub fn unknown(u: Unknown) -> Expr {
+        let mut terms = BTreeMap::new();
+        terms.insert(u, 1);
+        Expr { constant: 0, terms }
+    }
+
+    // The value, if it doesn't depend on any unknowns
+    pub fn as_constant(&self) -> Option<i64> {
+        if self.terms.is_empty() {
+            Some(self.constant)
+        } else {
+            None
+        }
+    }
+
+    // Substitute values for the unknowns, any left out count as 0. None if it doesn't fit an i64
+    pub fn eval(&self, values: &BTreeMap<Unknown, i64>) -> Option<i64> {
+        self.terms.iter().try_fold(self.constant, |acc, (u, &c)| {
+            acc.checked_add(c.checked_mul(values.get(u).copied().unwrap_or(0))?)
+        })
+    }
+
+    // None if the constant or a coefficient doesn't fit an i64
+    fn add(&self, other: &Expr) -> Option<Expr> {
+        let mut sum = self.clone();
+        sum.constant = sum.constant.checked_add(other.constant)?;
+        for (&u, &c) in &other.terms {
+            let coeff = sum.terms.entry(u).or_insert(0);
+            *coeff = coeff.checked_add(c)?;
+            if *coeff == 0 {
+                sum.terms.remove(&u);
+            }
+        }
+        Some(sum)
+    }
+
+    fn scale(&self, k: i64) -> Option<Expr> {
+        if k == 0 {
+            return Some(Expr::constant(0));
+        }
+        let terms = self
+            .terms
+            .iter()
+            .map(|(&u, &c)| Some((u, c.checked_mul(k)?)))
+            .collect::<Option<_>>()?;
+        Some(Expr {
+            constant: self.constant.checked_mul(k)?,
+            terms,
+        })
+    }
+}
+
+// Written the way you'd write it by hand, 100*[1] + [2] - 3
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (u, &c) in &self.terms {
+            let sign = if c < 0 { "-" } else { "+" };
+            match (first, c.unsigned_abs()) {
+                (true, 1) if c < 0 => write!(f, "-{}", u)?,
+                (true, 1) => write!(f, "{}", u)?,
+                (true, _) => write!(f, "{}*{}", c, u)?,
+                (false, 1) => write!(f, " {} {}", sign, u)?,
+                (false, a) => write!(f, " {} {}*{}", sign, a, u)?,
+            }
+            first = false;
+        }
+        match (first, self.constant) {
+            (true, c) => write!(f, "{}", c),
+            (false, 0) => Ok(()),
+            (false, c) if c < 0 => write!(f, " - {}", c.unsigned_abs()),
+            (false, c) => write!(f, " + {}", c),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Linear(Expr),
+    // Depends on the unknowns in a way a linear expression can't describe, pc is where it started
+    NonLinear(usize),
+}
+
+impl Value {
+    fn constant(c: i64) -> Value {
+        Value::Linear(Expr::constant(c))
+    }
+
+    pub fn as_constant(&self) -> Option<i64> {
+        match self {
+            Value::Linear(e) => e.as_constant(),
+            Value::NonLinear(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Linear(e) => write!(f, "{}", e),
+            Value::NonLinear(pc) => write!(f, "<non-linear from pc {}>", pc),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolicError {
+    // A jump whose condition or target depends on the unknowns, there's more than one path
+    Branch { pc: usize },
+    // An instruction word, write address or relative base change depends on the unknowns
+    Address { pc: usize },
+    // The program wanted more input than was given
+    NeedsInput { pc: usize },
+    // The value being solved for isn't linear in the unknowns, pc is where that started
+    NonLinear { pc: usize },
+    // Asked to solve for an output the program never produced
+    MissingOutput(usize),
+    // Ran for longer than the step limit
+    StepLimit,
+    // The concrete parts of the program faulted the way a real machine would
+    Fault(IntcodeError),
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SymbolicError::*;
+        match self {
+            Branch { pc } => write!(f, "jump at pc {} depends on the unknowns", pc),
+            Address { pc } => write!(f, "address used at pc {} depends on the unknowns", pc),
+            NeedsInput { pc } => write!(f, "ran out of input at pc {}", pc),
+            NonLinear { pc } => write!(f, "result is not linear in the unknowns (from pc {})", pc),
+            MissingOutput(n) => write!(f, "program never produced output {}", n),
+            StepLimit => write!(f, "step limit reached"),
+            Fault(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for SymbolicError {}
+
+// An intcode machine over symbolic values, runs start to finish in one go
+pub struct Symbolic {
+    image: Vec<i64>,
+    written: HashMap<usize, Value>, // Cells that differ from the image, unknowns included
+    pc: usize,
+    rel_base: i64,
+    inputs: VecDeque<Value>,
+    queued: usize, // Inputs ever queued, numbers the unknown ones
+    outputs: Vec<Value>,
+    ranges: BTreeMap<Unknown, RangeInclusive<i64>>,
+    halted: bool,
+    max_steps: u64,
+}
+
+impl Symbolic {
+    pub fn new(prog: &[i64]) -> Symbolic {
+        Symbolic {
+            image: prog.to_vec(),
+            written: HashMap::new(),
+            pc: 0,
+            rel_base: 0,
+            inputs: VecDeque::new(),
+            queued: 0,
+            outputs: Vec::new(),
+            ranges: BTreeMap::new(),
+            halted: false,
+            max_steps: MAX_STEPS,
+        }
+    }
+
+    // Treat the initial contents of addr as unknown, solutions are only looked for inside range
+    pub fn unknown_cell(&mut self, addr: usize, range: RangeInclusive<i64>) -> Unknown {
+        let u = Unknown::Cell(addr);
+        self.written.insert(addr, Value::Linear(Expr::unknown(u)));
+        self.ranges.insert(u, range);
+        u
+    }
+
+    pub fn input(&mut self, val: i64) {
+        self.inputs.push_back(Value::constant(val));
+        self.queued += 1;
+    }
+
+    // Queue an input whose value is unknown
+    pub fn unknown_input(&mut self, range: RangeInclusive<i64>) -> Unknown {
+        let u = Unknown::Input(self.queued);
+        self.inputs.push_back(Value::Linear(Expr::unknown(u)));
+        self.queued += 1;
+        self.ranges.insert(u, range);
+        u
+    }
+
+    pub fn set_step_limit(&mut self, steps: u64) {
+        self.max_steps = steps;
+    }
+
+    pub fn read_mem(&self, addr: usize) -> Value {
+        match self.written.get(&addr) {
+            Some(v) => v.clone(),
+            None => Value::constant(self.image.get(addr).copied().unwrap_or(0)),
+        }
+    }
+
+    pub fn outputs(&self) -> &[Value] {
+        &self.outputs
+    }
+
+    // Run until the program halts
+    pub fn run(&mut self) -> Result<(), SymbolicError> {
+        let mut steps = 0;
+        while !self.halted {
+            if steps == self.max_steps {
+                return Err(SymbolicError::StepLimit);
+            }
+            self.step()?;
+            steps += 1;
+        }
+        Ok(())
+    }
+
+    // Values for the unknowns that make value equal target, None if there aren't any in range.
+    //  The unknown with the widest range is solved for directly, every combination of the others is
+    //  tried in order, so with one or two unknowns this is quick
+    pub fn solve(
+        &self,
+        value: &Value,
+        target: i64,
+    ) -> Result<Option<BTreeMap<Unknown, i64>>, SymbolicError> {
+        let expr = match value {
+            Value::Linear(e) => e,
+            Value::NonLinear(pc) => return Err(SymbolicError::NonLinear { pc: *pc }),
+        };
+        // Unknowns the value doesn't depend on can be anything, take the bottom of their range
+        let mut sol: BTreeMap<Unknown, i64> =
+            self.ranges.iter().map(|(&u, r)| (u, *r.start())).collect();
+        let span = |u: &Unknown| {
+            let r = &self.ranges[u];
+            r.end().saturating_sub(*r.start())
+        };
+        let direct = match expr.terms.keys().max_by_key(|u| span(u)) {
+            Some(&u) => (u, expr.terms[&u], self.ranges[&u].clone()),
+            None => {
+                return Ok(if expr.constant == target {
+                    Some(sol)
+                } else {
+                    None
+                })
+            }
+        };
+        let others: Vec<(Unknown, i64, RangeInclusive<i64>)> = expr
+            .terms
+            .iter()
+            .filter(|(&u, _)| u != direct.0)
+            .map(|(&u, &c)| (u, c, self.ranges[&u].clone()))
+            .collect();
+        // Nothing in range can make up a difference that doesn't even fit an i64
+        let rest = match target.checked_sub(expr.constant) {
+            Some(rest) => rest,
+            None => return Ok(None),
+        };
+        if search(&others, rest, &direct, &mut sol) {
+            Ok(Some(sol))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Run and solve for the final contents of addr
+    pub fn solve_cell(
+        &mut self,
+        addr: usize,
+        target: i64,
+    ) -> Result<Option<BTreeMap<Unknown, i64>>, SymbolicError> {
+        self.run()?;
+        self.solve(&self.read_mem(addr), target)
+    }
+
+    // Run and solve for the nth output
+    pub fn solve_output(
+        &mut self,
+        n: usize,
+        target: i64,
+    ) -> Result<Option<BTreeMap<Unknown, i64>>, SymbolicError> {
+        self.run()?;
+        let value = self.outputs.get(n).ok_or(SymbolicError::MissingOutput(n))?;
+        self.solve(value, target)
+    }
+
+    // The cell as a number, None if it depends on the unknowns
+    fn peek(&self, addr: usize) -> Option<i64> {
+        match self.written.get(&addr) {
+            Some(v) => v.as_constant(),
+            None => Some(self.image.get(addr).copied().unwrap_or(0)),
+        }
+    }
+
+    // Address a positional or relative parameter refers to, None if the parameter is unknown
+    fn address(&self, inst: &Instruction, operand: usize) -> Result<Option<usize>, SymbolicError> {
+        let param = match self.peek(inst.addr + operand) {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        let addr = match inst.modes[operand - 1] {
+            AddressMode::Relative => param
+                .checked_add(self.rel_base)
+                .ok_or_else(|| overflow(inst))?,
+            _ => param,
+        };
+        if addr < 0 {
+            return Err(SymbolicError::Fault(IntcodeError::NegativeAddress {
+                pc: inst.addr,
+                word: inst.word,
+                operand,
+                addr,
+            }));
+        }
+        Ok(Some(addr as usize))
+    }
+
+    fn operand(&self, inst: &Instruction, operand: usize) -> Result<Value, SymbolicError> {
+        if inst.modes[operand - 1] == AddressMode::Immediate {
+            return Ok(self.read_mem(inst.addr + operand));
+        }
+        // Reading through an unknown address could give anything
+        Ok(match self.address(inst, operand)? {
+            Some(addr) => self.read_mem(addr),
+            None => Value::NonLinear(inst.addr),
+        })
+    }
+
+    fn write(
+        &mut self,
+        inst: &Instruction,
+        operand: usize,
+        val: Value,
+    ) -> Result<(), SymbolicError> {
+        match self.address(inst, operand)? {
+            Some(addr) => {
+                self.written.insert(addr, val);
+                Ok(())
+            }
+            None => Err(SymbolicError::Address { pc: inst.addr }),
+        }
+    }
+
+    fn step(&mut self) -> Result<(), SymbolicError> {
+        let pc = self.pc;
+        if self.peek(pc).is_none() {
+            return Err(SymbolicError::Address { pc });
+        }
+        // Unknown parameters decode as 0, their real values are looked up when they're used
+        let inst = decode_with(|a| self.peek(a).unwrap_or(0), pc).map_err(SymbolicError::Fault)?;
+
+        use Opcodes::*;
+        match inst.opcode {
+            Add | Multiply | Comparelt | Compareq => {
+                let l = self.operand(&inst, 1)?;
+                let r = self.operand(&inst, 2)?;
+                let result = match (inst.opcode, &l, &r) {
+                    (Add, Value::Linear(a), Value::Linear(b)) => linear(a.add(b), &inst, &l, &r)?,
+                    (Multiply, Value::Linear(a), Value::Linear(b)) => {
+                        match (a.as_constant(), b.as_constant()) {
+                            (Some(k), _) => linear(b.scale(k), &inst, &l, &r)?,
+                            (_, Some(k)) => linear(a.scale(k), &inst, &l, &r)?,
+                            _ => Value::NonLinear(pc),
+                        }
+                    }
+                    // Zero times anything is still zero
+                    (Multiply, v, Value::NonLinear(_)) | (Multiply, Value::NonLinear(_), v)
+                        if v.as_constant() == Some(0) =>
+                    {
+                        Value::constant(0)
+                    }
+                    (Comparelt | Compareq, _, _) => match (l.as_constant(), r.as_constant()) {
+                        (Some(a), Some(b)) => {
+                            let hit = if inst.opcode == Comparelt {
+                                a < b
+                            } else {
+                                a == b
+                            };
+                            Value::constant(hit as i64)
+                        }
+                        _ => Value::NonLinear(pc),
+                    },
+                    // Keep pointing at where the trouble started
+                    (_, Value::NonLinear(from), _) | (_, _, Value::NonLinear(from)) => {
+                        Value::NonLinear(*from)
+                    }
+                    _ => unreachable!("only arithmetic and compares get here"),
+                };
+                self.write(&inst, 3, result)?;
+                self.pc += 4;
+            }
+            Input => {
+                let val = self
+                    .inputs
+                    .pop_front()
+                    .ok_or(SymbolicError::NeedsInput { pc })?;
+                self.write(&inst, 1, val)?;
+                self.pc += 2;
+            }
+            Output => {
+                let val = self.operand(&inst, 1)?;
+                self.outputs.push(val);
+                self.pc += 2;
+            }
+            Jnz | Jz => {
+                let cond = self.operand(&inst, 1)?.as_constant();
+                let target = self.operand(&inst, 2)?.as_constant();
+                let (cond, target) = match (cond, target) {
+                    (Some(c), Some(t)) => (c, t),
+                    _ => return Err(SymbolicError::Branch { pc }),
+                };
+                if (cond != 0) == (inst.opcode == Jnz) {
+                    if target < 0 {
+                        return Err(SymbolicError::Fault(IntcodeError::NegativeAddress {
+                            pc,
+                            word: inst.word,
+                            operand: 2,
+                            addr: target,
+                        }));
+                    }
+                    self.pc = target as usize;
+                } else {
+                    self.pc += 3;
+                }
+            }
+            Rbo => {
+                match self.operand(&inst, 1)?.as_constant() {
+                    Some(d) => {
+                        self.rel_base = self
+                            .rel_base
+                            .checked_add(d)
+                            .ok_or_else(|| overflow(&inst))?
+                    }
+                    None => return Err(SymbolicError::Address { pc }),
+                }
+                self.pc += 2;
+            }
+            Halt => self.halted = true,
+        }
+        Ok(())
+    }
+}
+
+// The relative base or an address left i64, a real machine faults the same way
+fn overflow(inst: &Instruction) -> SymbolicError {
+    SymbolicError::Fault(IntcodeError::Overflow {
+        pc: inst.addr,
+        word: inst.word,
+    })
+}
+
+// Result of an add or multiply, None if it didn't fit an i64. With plain numbers a real machine traps,
+//  with unknowns involved it depends on their values, so the result is just something we can't describe
+fn linear(
+    result: Option<Expr>,
+    inst: &Instruction,
+    l: &Value,
+    r: &Value,
+) -> Result<Value, SymbolicError> {
+    match result {
+        Some(e) => Ok(Value::Linear(e)),
+        None if l.as_constant().is_some() && r.as_constant().is_some() => Err(overflow(inst)),
+        None => Ok(Value::NonLinear(inst.addr)),
+    }
+}
+
+// Try every value of the first unknown in others, recursing on the rest, then solve
+//  coefficient * x = rest for the direct one
+fn search(
+    others: &[(Unknown, i64, RangeInclusive<i64>)],
+    rest: i64,
+    direct: &(Unknown, i64, RangeInclusive<i64>),
+    sol: &mut BTreeMap<Unknown, i64>,
+) -> bool {
+    match others.split_first() {
+        None => {
+            let (u, coeff, range) = direct;
+            // Coefficients are never 0, but MIN / -1 doesn't fit
+            match (rest.checked_rem(*coeff), rest.checked_div(*coeff)) {
+                (Some(0), Some(x)) if range.contains(&x) => {
+                    sol.insert(*u, x);
+                    true
+                }
+                _ => false,
+            }
+        }
+        Some(((u, coeff, range), tail)) => {
+            for x in range.clone() {
+                // A term that doesn't fit can't be part of an answer
+                let left = match coeff.checked_mul(x).and_then(|t| rest.checked_sub(t)) {
+                    Some(left) => left,
+                    None => continue,
+                };
+                if search(tail, left, direct, sol) {
+                    sol.insert(*u, x);
+                    return true;
+                }
+            }
+            false
+        }
+    }
+}
+
+// Find values for the given cells (each searched inside its range) that leave target in memory at
+//  addr once the program halts. The values come back in the same order as cells
+pub fn solve_cells(
+    prog: &[i64],
+    cells: &[(usize, RangeInclusive<i64>)],
+    addr: usize,
+    target: i64,
+) -> Result<Option<Vec<i64>>, SymbolicError> {
+    let mut sym = Symbolic::new(prog);
+    let unknowns: Vec<Unknown> = cells
+        .iter()
+        .map(|(cell, range)| sym.unknown_cell(*cell, range.clone()))
+        .collect();
+    Ok(sym
+        .solve_cell(addr, target)?
+        .map(|sol| unknowns.iter().map(|u| sol[u]).collect()))
+}
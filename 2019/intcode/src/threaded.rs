@@ -0,0 +1,432 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::network::{NetState, Topology};
+use crate::{InputSource, IntcodeComp, OutputSink, RunState, StopOn};
+
+// Instructions a node runs between checks of the abort flag
+const CHUNK: u64 = 10_000;
+// How long a blocked node waits before looking around for deadlock or abort
+const POLL: Duration = Duration::from_millis(5);
+
+// What one node looked like when its thread finished
+#[derive(Clone)]
+pub struct NodeReport {
+    // Halted, Error, Breakpoint/Watchpoint, or AwaitingInput if it was starved or shut down
+    pub state: RunState,
+    // Last value the node produced, whether it was routed or kept
+    pub last: Option<i64>,
+    // Outputs that weren't routed anywhere (sink nodes, packets to unknown addresses)
+    pub outputs: Vec<i64>,
+    // The machine itself, for looking at memory afterwards
+    pub comp: IntcodeComp,
+}
+
+// What a node's thread is doing as far as the rest of the network is concerned
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Wait {
+    Running,
+    // On its own empty channel
+    Input,
+    // On this node's full channel
+    Send(usize),
+}
+
+// Bookkeeping shared by every node to tell a quiet network from a stuck one
+struct Shared {
+    waiting: Vec<Wait>,
+    in_flight: Vec<usize>,    // Values sent to each node but not yet picked up
+    done: Vec<bool>,          // Nodes whose thread has finished, values sent there are lost
+    stuck: Vec<bool>,         // Nodes found waiting for good, they stop as soon as they notice
+    targets: Vec<Vec<usize>>, // Nodes each node can send to
+}
+
+impl Shared {
+    // Mark every node that's waiting on something that can never happen and say whether id is one.
+    //  Running nodes might do anything, so start from them and the waiting nodes they're bound to wake
+    //  (input on its way, a send to a node that's gone), then keep adding nodes that could be fed or
+    //  drained by those. Whatever is left over only waits on other leftovers, and it all has to stop
+    //  at once or one giving up would free the rest to carry on. A sender can still be holding a
+    //  count for a node that finished after it was bumped, so only values on their way to live nodes
+    //  count
+    fn stuck(&mut self, id: usize) -> bool {
+        let n = self.done.len();
+        let mut awake: Vec<bool> = (0..n)
+            .map(|i| match self.waiting[i] {
+                _ if self.done[i] => false,
+                Wait::Running => true,
+                Wait::Input => self.in_flight[i] > 0,
+                Wait::Send(dest) => self.done[dest],
+            })
+            .collect();
+        loop {
+            let mut woke = false;
+            for i in 0..n {
+                if awake[i] || self.done[i] {
+                    continue;
+                }
+                awake[i] = match self.waiting[i] {
+                    Wait::Running => true,
+                    Wait::Input => (0..n).any(|j| awake[j] && self.targets[j].contains(&i)),
+                    Wait::Send(dest) => awake[dest],
+                };
+                woke |= awake[i];
+            }
+            if !woke {
+                break;
+            }
+        }
+        for ((stuck, awake), done) in self.stuck.iter_mut().zip(awake).zip(&self.done) {
+            *stuck |= !awake && !done;
+        }
+        self.stuck[id]
+    }
+}
+
+struct Link {
+    shared: Arc<Mutex<Shared>>,
+    abort: Arc<AtomicBool>,
+}
+
+impl Link {
+    fn aborted(&self) -> bool {
+        self.abort.load(Ordering::SeqCst)
+    }
+
+    fn stop_everyone(&self) {
+        self.abort.store(true, Ordering::SeqCst);
+    }
+}
+
+// One node's view of the network, reads its own channel and writes to its neighbours'
+struct Port {
+    link: Link,
+    rx: Receiver<i64>,
+    topology: Topology,
+    txs: Vec<Option<SyncSender<i64>>>, // Indexed by node, None if this node never writes there
+    id: usize,
+    pending: Vec<i64>, // Partial packet
+    last: Option<i64>,
+    kept: Vec<i64>,
+    cut_off: bool, // Found stuck sending, the node is stopped at the next chance
+}
+
+impl Port {
+    // Blocks while the destination's channel is full, gives up if the network is shutting down
+    fn send(&mut self, dest: usize, val: i64) {
+        let tx = match &self.txs[dest] {
+            Some(tx) => tx,
+            None => return,
+        };
+        {
+            let mut shared = self.link.shared.lock().unwrap();
+            if shared.done[dest] {
+                return;
+            }
+            shared.in_flight[dest] += 1;
+        }
+        let mut val = val;
+        let sent = loop {
+            match tx.try_send(val) {
+                Ok(()) => break true,
+                Err(TrySendError::Full(v)) if !self.link.aborted() => {
+                    val = v;
+                    let mut shared = self.link.shared.lock().unwrap();
+                    shared.waiting[self.id] = Wait::Send(dest);
+                    // Everyone who could make room is waiting on something that won't happen either
+                    if shared.stuck(self.id) {
+                        break false;
+                    }
+                    drop(shared);
+                    thread::sleep(POLL);
+                }
+                Err(_) => break false, // Receiver is gone or we are shutting down
+            }
+        };
+        let mut shared = self.link.shared.lock().unwrap();
+        shared.waiting[self.id] = Wait::Running;
+        // Found stuck along with the receiver, which might have given up first and hung up
+        self.cut_off = shared.stuck[self.id];
+        if !sent {
+            // The receiver may have finished and written off its queue already
+            shared.in_flight[dest] = shared.in_flight[dest].saturating_sub(1);
+        }
+    }
+}
+
+impl InputSource for Port {
+    fn next_input(&mut self) -> Option<i64> {
+        if self.cut_off {
+            return None;
+        }
+        self.link.shared.lock().unwrap().waiting[self.id] = Wait::Input;
+        let val = loop {
+            match self.rx.recv_timeout(POLL) {
+                Ok(val) => {
+                    // Stop waiting and retire the value under one lock so nobody sees a false deadlock
+                    let mut shared = self.link.shared.lock().unwrap();
+                    shared.in_flight[self.id] -= 1;
+                    shared.waiting[self.id] = Wait::Running;
+                    return Some(val);
+                }
+                Err(RecvTimeoutError::Disconnected) => break None,
+                Err(RecvTimeoutError::Timeout) => {
+                    if self.link.aborted() {
+                        break None;
+                    }
+                    // Nothing is on its way and nobody who could send anything ever will
+                    if self.link.shared.lock().unwrap().stuck(self.id) {
+                        break None;
+                    }
+                }
+            }
+        };
+        self.link.shared.lock().unwrap().waiting[self.id] = Wait::Running;
+        val
+    }
+}
+
+impl OutputSink for Port {
+    fn push_output(&mut self, val: i64) {
+        if self.cut_off {
+            return;
+        }
+        self.last = Some(val);
+        match &self.topology {
+            Topology::Links(links) => {
+                if links[self.id].is_empty() {
+                    self.kept.push(val);
+                }
+                for t in links[self.id].clone() {
+                    self.send(t, val);
+                }
+            }
+            Topology::Packets => {
+                self.pending.push(val);
+                if self.pending.len() == 3 {
+                    let (dest, x, y) = (self.pending[0], self.pending[1], self.pending[2]);
+                    self.pending.clear();
+                    if dest >= 0 && (dest as usize) < self.txs.len() {
+                        self.send(dest as usize, x);
+                        self.send(dest as usize, y);
+                    } else {
+                        self.kept.extend([dest, x, y]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Drive one machine until it stops for good or someone else asks for a shutdown
+fn run_node(comp: &mut IntcodeComp, port: &mut Port) -> RunState {
+    loop {
+        if port.link.aborted() || port.cut_off {
+            return RunState::AwaitingInput;
+        }
+        match comp.run_device_until(port, StopOn::Instructions(CHUNK)) {
+            RunState::Running => {
+                // run_device_until never stops on the first instruction, so check the boundary here
+                let pc = comp.program_counter();
+                if comp.has_breakpoint(pc) {
+                    return RunState::Breakpoint(pc);
+                }
+            }
+            RunState::OutputReady(_) => (),
+            s => return s,
+        }
+    }
+}
+
+// Like network::Network but every node gets its own thread, values travel over bounded channels
+//  Only halting everywhere ends the run normally, a fault or interruption anywhere stops the lot.
+//  A node is only stopped otherwise once it's waiting to read or send and nothing that could ever
+//  unblock it is still running, a node that's busy computing is left to finish however long it takes
+pub struct ThreadedNetwork {
+    nodes: Vec<IntcodeComp>,
+    topology: Topology,
+    capacity: usize,
+}
+
+impl ThreadedNetwork {
+    // n copies of the same program
+    pub fn new(prog: &[i64], n: usize, topology: Topology) -> ThreadedNetwork {
+        ThreadedNetwork::from_nodes(vec![IntcodeComp::new(prog); n], topology)
+    }
+
+    pub fn from_nodes(nodes: Vec<IntcodeComp>, topology: Topology) -> ThreadedNetwork {
+        ThreadedNetwork {
+            nodes,
+            topology,
+            capacity: 64,
+        }
+    }
+
+    // Number of values that can queue up on a channel before the sender blocks
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+    }
+
+    // Queue a value on a node before the threads start (phase settings, addresses)
+    pub fn send(&mut self, node: usize, val: i64) {
+        self.nodes[node].input(val);
+    }
+
+    // Spawn every node, wait for all of them and report how each one ended
+    pub fn run(self) -> (NetState, Vec<NodeReport>) {
+        let ThreadedNetwork {
+            nodes,
+            topology,
+            capacity,
+        } = self;
+        let n = nodes.len();
+        let mut txs = Vec::with_capacity(n);
+        let mut rxs = Vec::with_capacity(n);
+        for _ in 0..n {
+            let (tx, rx) = mpsc::sync_channel(capacity);
+            txs.push(tx);
+            rxs.push(rx);
+        }
+
+        let linked = |from: usize, to: usize| match &topology {
+            Topology::Links(links) => links[from].contains(&to),
+            Topology::Packets => true,
+        };
+        let shared = Arc::new(Mutex::new(Shared {
+            waiting: vec![Wait::Running; n],
+            in_flight: vec![0; n],
+            done: vec![false; n],
+            stuck: vec![false; n],
+            targets: (0..n)
+                .map(|id| (0..n).filter(|&t| linked(id, t)).collect())
+                .collect(),
+        }));
+        let abort = Arc::new(AtomicBool::new(false));
+
+        let mut handles = Vec::with_capacity(n);
+        for (id, (mut comp, rx)) in nodes.into_iter().zip(rxs).enumerate() {
+            // Only hand out senders a node can actually use, so channels hang up once their writers finish
+            let node_txs = (0..n)
+                .map(|t| {
+                    if linked(id, t) {
+                        Some(txs[t].clone())
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            let mut port = Port {
+                link: Link {
+                    shared: Arc::clone(&shared),
+                    abort: Arc::clone(&abort),
+                },
+                rx,
+                topology: topology.clone(),
+                txs: node_txs,
+                id,
+                pending: Vec::new(),
+                last: None,
+                kept: Vec::new(),
+                cut_off: false,
+            };
+            handles.push(thread::spawn(move || {
+                let state = run_node(&mut comp, &mut port);
+                if state != RunState::Halted && state != RunState::AwaitingInput {
+                    port.link.stop_everyone();
+                }
+                {
+                    // Anything still queued for this node will never be read
+                    let mut shared = port.link.shared.lock().unwrap();
+                    shared.done[id] = true;
+                    shared.in_flight[id] = 0;
+                }
+                NodeReport {
+                    state,
+                    last: port.last,
+                    outputs: port.kept,
+                    comp,
+                }
+            }));
+        }
+        drop(txs); // Threads own all the senders now
+
+        let reports: Vec<NodeReport> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        let mut state = NetState::Halted;
+        for (i, r) in reports.iter().enumerate() {
+            match &r.state {
+                RunState::Halted => (),
+                RunState::AwaitingInput => {
+                    if state == NetState::Halted {
+                        state = NetState::Deadlock;
+                    }
+                }
+                RunState::Error(e) => {
+                    state = NetState::Error(i, e.clone());
+                    break;
+                }
+                s => {
+                    state = NetState::Interrupted(i, s.clone());
+                    break;
+                }
+            }
+        }
+        (state, reports)
+    }
+}
+
+// Map f over items using every core, results come back in the same order as the items
+pub fn sweep<T, R, F>(items: Vec<T>, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(items.len().max(1));
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let mut results: Vec<(usize, R)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        // Take the lock only long enough to grab the next item
+                        let next = queue.lock().unwrap().next();
+                        match next {
+                            Some((i, item)) => done.push((i, f(item))),
+                            None => return done,
+                        }
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+// Every ordering of values, for phase setting searches and the like
+pub fn permutations(values: &[i64]) -> Vec<Vec<i64>> {
+    if values.len() <= 1 {
+        return vec![values.to_vec()];
+    }
+    let mut perms = Vec::new();
+    for i in 0..values.len() {
+        let mut rest = values.to_vec();
+        let first = rest.remove(i);
+        for mut p in permutations(&rest) {
+            p.insert(0, first);
+            perms.push(p);
+        }
+    }
+    perms
+}
@@ -0,0 +1,418 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::str::FromStr;
+
+use crate::{Budget, InputSource, IntcodeComp, OutputSink, RunState, StopOn};
+
+// Recording and replaying everything that passes between a machine and its device. A session is split
+//  into steps at each input, steps[i] is what came out before inputs[i] was read and the last step is
+//  what came out after the final input. Outputs are only kept as a count and a hash per step so long
+//  sessions stay small, a replay can say where it went wrong but not what the outputs were
+//
+//  On disk it's one line per event, easy to diff and to check in next to a test:
+//
+//  intcode trace
+//  out 2640 9c2f41e3b07d5a18
+//  in -1
+//  out 9 05e7d1f2a3c4b681
+//  end halted
+
+const HEADER: &str = "intcode trace";
+
+// 64 bit FNV-1a
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+// The outputs of one step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepOutputs {
+    pub count: u64,
+    pub hash: u64,
+}
+
+impl StepOutputs {
+    fn new() -> StepOutputs {
+        StepOutputs {
+            count: 0,
+            hash: FNV_OFFSET,
+        }
+    }
+
+    fn push(&mut self, val: i64) {
+        for b in val.to_le_bytes().iter() {
+            self.hash = (self.hash ^ u64::from(*b)).wrapping_mul(FNV_PRIME);
+        }
+        self.count += 1;
+    }
+}
+
+impl Default for StepOutputs {
+    fn default() -> StepOutputs {
+        StepOutputs::new()
+    }
+}
+
+impl fmt::Display for StepOutputs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} outputs (hash {:016x})", self.count, self.hash)
+    }
+}
+
+// How a recorded run stopped. Saved as a short token (halted, blocked, fault:overflow:12) rather
+//  than anything derived from RunState, so renaming a variant or rewording an error message doesn't
+//  break traces that are already checked in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum End {
+    Halted,
+    // Starved of input
+    Blocked,
+    // Error code (IntcodeError::code) and pc
+    Fault(String, usize),
+    Breakpoint(usize),
+    Watchpoint(usize),
+    Budget(Budget),
+    Cycling(usize),
+    Overflowed(usize),
+    // Stopped by a run_until condition, sessions run to the end so it never comes up
+    Running,
+}
+
+impl End {
+    pub fn of(state: &RunState) -> End {
+        match state {
+            RunState::Halted => End::Halted,
+            RunState::AwaitingInput => End::Blocked,
+            RunState::Error(e) => End::Fault(e.code().to_string(), e.pc()),
+            RunState::Breakpoint(pc) => End::Breakpoint(*pc),
+            RunState::Watchpoint { addr, .. } => End::Watchpoint(*addr),
+            RunState::BudgetExhausted(b) => End::Budget(*b),
+            RunState::Cycling(pc) => End::Cycling(*pc),
+            RunState::Overflowed(pc) => End::Overflowed(*pc),
+            RunState::OutputReady(_) | RunState::Running => End::Running,
+        }
+    }
+}
+
+impl fmt::Display for End {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            End::Halted => write!(f, "halted"),
+            End::Blocked => write!(f, "blocked"),
+            End::Fault(code, pc) => write!(f, "fault:{}:{}", code, pc),
+            End::Breakpoint(pc) => write!(f, "breakpoint:{}", pc),
+            End::Watchpoint(addr) => write!(f, "watchpoint:{}", addr),
+            End::Budget(Budget::Instructions) => write!(f, "budget:instructions"),
+            End::Budget(Budget::WallClock) => write!(f, "budget:wall-clock"),
+            End::Cycling(pc) => write!(f, "cycling:{}", pc),
+            End::Overflowed(pc) => write!(f, "overflowed:{}", pc),
+            End::Running => write!(f, "running"),
+        }
+    }
+}
+
+impl FromStr for End {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<End, ()> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let num = |i: usize| parts[i].parse::<usize>().map_err(|_| ());
+        Ok(match parts[..] {
+            ["halted"] => End::Halted,
+            ["blocked"] => End::Blocked,
+            ["fault", code, _] if !code.is_empty() => End::Fault(code.to_string(), num(2)?),
+            ["breakpoint", _] => End::Breakpoint(num(1)?),
+            ["watchpoint", _] => End::Watchpoint(num(1)?),
+            ["budget", "instructions"] => End::Budget(Budget::Instructions),
+            ["budget", "wall-clock"] => End::Budget(Budget::WallClock),
+            ["cycling", _] => End::Cycling(num(1)?),
+            ["overflowed", _] => End::Overflowed(num(1)?),
+            ["running"] => End::Running,
+            _ => return Err(()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub inputs: Vec<i64>,
+    // Always one more than there are inputs
+    pub steps: Vec<StepOutputs>,
+    // How the recorded run stopped
+    pub end: End,
+}
+
+impl Trace {
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: &str) -> io::Result<Trace> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f, "out {} {:016x}", step.count, step.hash)?;
+            if let Some(input) = self.inputs.get(i) {
+                writeln!(f, "in {}", input)?;
+            }
+        }
+        writeln!(f, "end {}", self.end)
+    }
+}
+
+// A trace file that doesn't parse, line is 1-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseTraceError {
+    pub line: usize,
+}
+
+impl fmt::Display for ParseTraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: not part of an intcode trace", self.line)
+    }
+}
+
+impl Error for ParseTraceError {}
+
+impl FromStr for Trace {
+    type Err = ParseTraceError;
+
+    // Lines have to come in the order Display writes them, out and in taking turns
+    fn from_str(s: &str) -> Result<Trace, ParseTraceError> {
+        let mut inputs = Vec::new();
+        let mut steps = Vec::new();
+        let mut end = None;
+        let mut lines = s.lines().enumerate().map(|(n, l)| (n + 1, l.trim()));
+        match lines.next() {
+            Some((_, HEADER)) => (),
+            _ => return Err(ParseTraceError { line: 1 }),
+        }
+        for (line, text) in lines {
+            let err = ParseTraceError { line };
+            if text.is_empty() {
+                continue;
+            }
+            // Nothing but blank lines after the end
+            if end.is_some() {
+                return Err(err);
+            }
+            let expect_out = steps.len() == inputs.len();
+            let mut words = text.split_whitespace();
+            match (words.next(), expect_out) {
+                (Some("out"), true) => {
+                    let count = words.next().and_then(|w| w.parse().ok()).ok_or(err)?;
+                    let hash = words
+                        .next()
+                        .and_then(|w| u64::from_str_radix(w, 16).ok())
+                        .ok_or(err)?;
+                    steps.push(StepOutputs { count, hash });
+                }
+                (Some("in"), false) => {
+                    let val = words.next().and_then(|w| w.parse().ok()).ok_or(err)?;
+                    inputs.push(val);
+                }
+                (Some("end"), false) => {
+                    end = Some(words.next().and_then(|w| w.parse().ok()).ok_or(err)?);
+                }
+                _ => return Err(err),
+            }
+            if words.next().is_some() {
+                return Err(err);
+            }
+        }
+        match end {
+            Some(end) => Ok(Trace { inputs, steps, end }),
+            None => Err(ParseTraceError {
+                line: s.lines().count() + 1,
+            }),
+        }
+    }
+}
+
+// Where a replay first stopped matching its trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    // The outputs leading up to input step (or following the last input) differ
+    Outputs {
+        step: usize,
+        expected: StepOutputs,
+        found: StepOutputs,
+    },
+    // The machine stopped before reading every recorded input
+    Inputs {
+        read: usize,
+        recorded: usize,
+    },
+    // Same inputs and outputs but the run finished differently
+    End {
+        expected: End,
+        found: End,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Divergence::*;
+        match self {
+            Outputs {
+                step,
+                expected,
+                found,
+            } => write!(f, "step {}: expected {}, got {}", step, expected, found),
+            Inputs { read, recorded } => write!(
+                f,
+                "stopped after reading {} of {} recorded inputs",
+                read, recorded
+            ),
+            End { expected, found } => write!(
+                f,
+                "expected the run to end {}, it ended {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl Error for Divergence {}
+
+// Passes everything through to the real device, noting it down on the way
+struct Recorder<'a, D: ?Sized> {
+    dev: &'a mut D,
+    inputs: Vec<i64>,
+    steps: Vec<StepOutputs>,
+    current: StepOutputs,
+}
+
+impl<D: InputSource + ?Sized> InputSource for Recorder<'_, D> {
+    fn next_input(&mut self) -> Option<i64> {
+        // A step only ends once there is an input to end it, asking and getting nothing doesn't count
+        let val = self.dev.next_input()?;
+        self.steps.push(mem::take(&mut self.current));
+        self.inputs.push(val);
+        Some(val)
+    }
+}
+
+impl<D: OutputSink + ?Sized> OutputSink for Recorder<'_, D> {
+    fn push_output(&mut self, val: i64) {
+        self.current.push(val);
+        self.dev.push_output(val);
+    }
+}
+
+// Feeds the recorded inputs back, checking each step's outputs before handing over the next one
+struct Replayer<'a, O: ?Sized> {
+    trace: &'a Trace,
+    out: &'a mut O,
+    read: usize,
+    current: StepOutputs,
+    diverged: Option<Divergence>,
+}
+
+impl<O: ?Sized> Replayer<'_, O> {
+    // Compare what came out since the last input with the recording
+    fn check_step(&self) -> Option<Divergence> {
+        let expected = self.trace.steps.get(self.read).copied().unwrap_or_default();
+        if self.current == expected {
+            return None;
+        }
+        Some(Divergence::Outputs {
+            step: self.read,
+            expected,
+            found: self.current,
+        })
+    }
+}
+
+impl<O: ?Sized> InputSource for Replayer<'_, O> {
+    fn next_input(&mut self) -> Option<i64> {
+        // Past the end of the recording starves the machine, if the recording ended that way too
+        //  the end states will agree
+        let val = *self.trace.inputs.get(self.read)?;
+        if let Some(d) = self.check_step() {
+            self.diverged = Some(d);
+            return None;
+        }
+        self.current = StepOutputs::new();
+        self.read += 1;
+        Some(val)
+    }
+}
+
+impl<O: OutputSink + ?Sized> OutputSink for Replayer<'_, O> {
+    fn push_output(&mut self, val: i64) {
+        self.current.push(val);
+        self.out.push_output(val);
+    }
+}
+
+// Inputs queued with input() don't come from the device, so they're neither recorded nor replayed.
+//  Start both runs with the same queue
+impl IntcodeComp {
+    // attach() that also records the session
+    pub fn record<D: InputSource + OutputSink + ?Sized>(
+        &mut self,
+        dev: &mut D,
+    ) -> (RunState, Trace) {
+        let mut rec = Recorder {
+            dev,
+            inputs: Vec::new(),
+            steps: Vec::new(),
+            current: StepOutputs::new(),
+        };
+        let state = self.run_device_until(&mut rec, StopOn::Halt);
+        rec.steps.push(rec.current);
+        let trace = Trace {
+            inputs: rec.inputs,
+            steps: rec.steps,
+            end: End::of(&state),
+        };
+        (state, trace)
+    }
+
+    // Run on the inputs from a trace instead of a device, stopping at the first difference from the
+    //  recording. Outputs still go to out, so a screen can show the replay
+    pub fn replay<O: OutputSink + ?Sized>(
+        &mut self,
+        trace: &Trace,
+        out: &mut O,
+    ) -> Result<RunState, Divergence> {
+        let mut rep = Replayer {
+            trace,
+            out,
+            read: 0,
+            current: StepOutputs::new(),
+            diverged: None,
+        };
+        let state = self.run_device_until(&mut rep, StopOn::Halt);
+        if let Some(d) = rep.diverged {
+            return Err(d);
+        }
+        if rep.read < trace.inputs.len() {
+            return Err(Divergence::Inputs {
+                read: rep.read,
+                recorded: trace.inputs.len(),
+            });
+        }
+        if let Some(d) = rep.check_step() {
+            return Err(d);
+        }
+        let found = End::of(&state);
+        if found != trace.end {
+            return Err(Divergence::End {
+                expected: trace.end.clone(),
+                found,
+            });
+        }
+        Ok(state)
+    }
+}
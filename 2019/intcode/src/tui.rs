@@ -0,0 +1,483 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use crate::display::{Display, Rect, MAX_RENDER};
+use crate::io::{InputSource, OutputSink};
+use crate::{IntcodeComp, RunState};
+
+// A full screen frontend for programs that draw on a Display and read a key each frame, like day 13's
+//  arcade. Frames are drawn when the program asks for input and only cells that changed since the last
+//  one are sent to the screen. Under the picture is a status line with the display's channels, whether
+//  the machine is running and the speed, and a line of help:
+//
+//  let mut tui = Tui::new(Terminal::new()?);
+//  tui.bind(Key::Left, -1).bind(Key::Right, 1);
+//  tui.set_idle_input(Some(0));
+//  let state = tui.run(&mut comp, &mut display)?;
+//
+//  With an idle input the game doesn't wait for the player, each frame lasts the frame delay and the
+//  idle input is sent if no key was pressed. Without one every frame waits for a bound key. Either way
+//  p pauses, n steps one frame while paused (so do bound keys), + and - change the speed and q or Esc
+//  quits, leaving the machine waiting for input.
+//
+//  The drawing goes through a Backend. Terminal (with the tui feature) is the real one, in raw mode so
+//  keys arrive without Enter, and MemoryBackend draws into a buffer for tests
+
+const HELP: &str = "p pause  n step  + faster  - slower  q quit";
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+// Keys the frontend understands, backends translate whatever they read into these
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    Enter,
+    Esc,
+    Char(char),
+}
+
+// Where frames are drawn and keys come from
+pub trait Backend {
+    fn clear(&mut self) -> io::Result<()>;
+    // Write text starting at a column and row, counted from 0 at the top left
+    fn put(&mut self, col: u16, row: u16, text: &str) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+    // Wait up to timeout for a key, forever if it's None. Ok(None) only ever means the time ran out
+    fn poll_key(&mut self, timeout: Option<Duration>) -> io::Result<Option<Key>>;
+}
+
+// A screen in memory with its keys scripted up front. It never really waits: each wait() in the script
+//  is one timeout running out, and running out of script fails the poll so a test can't hang
+pub struct MemoryBackend {
+    rows: Vec<Vec<char>>,
+    script: VecDeque<Option<Key>>,
+    written: usize,
+}
+
+impl MemoryBackend {
+    pub fn new() -> MemoryBackend {
+        MemoryBackend {
+            rows: Vec::new(),
+            script: VecDeque::new(),
+            written: 0,
+        }
+    }
+
+    pub fn press(&mut self, key: Key) -> &mut Self {
+        self.script.push_back(Some(key));
+        self
+    }
+
+    // Each character as a Key::Char
+    pub fn type_str(&mut self, s: &str) -> &mut Self {
+        self.script.extend(s.chars().map(|c| Some(Key::Char(c))));
+        self
+    }
+
+    // Let one frame go by without a key
+    pub fn wait(&mut self) -> &mut Self {
+        self.script.push_back(None);
+        self
+    }
+
+    // Text of one row, without trailing spaces
+    pub fn row(&self, row: usize) -> String {
+        let text: String = self.rows.get(row).into_iter().flatten().collect();
+        text.trim_end().to_string()
+    }
+
+    // Characters put since the last call, to see how much of a frame was redrawn
+    pub fn take_written(&mut self) -> usize {
+        std::mem::take(&mut self.written)
+    }
+}
+
+impl Default for MemoryBackend {
+    fn default() -> MemoryBackend {
+        MemoryBackend::new()
+    }
+}
+
+// Every row, trailing spaces trimmed
+impl fmt::Display for MemoryBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.rows.len() {
+            writeln!(f, "{}", self.row(row))?;
+        }
+        Ok(())
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn clear(&mut self) -> io::Result<()> {
+        self.rows.clear();
+        Ok(())
+    }
+
+    fn put(&mut self, col: u16, row: u16, text: &str) -> io::Result<()> {
+        let (col, row) = (col as usize, row as usize);
+        if self.rows.len() <= row {
+            self.rows.resize(row + 1, Vec::new());
+        }
+        let line = &mut self.rows[row];
+        for (i, c) in text.chars().enumerate() {
+            if line.len() <= col + i {
+                line.resize(col + i + 1, ' ');
+            }
+            line[col + i] = c;
+            self.written += 1;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_key(&mut self, timeout: Option<Duration>) -> io::Result<Option<Key>> {
+        while let Some(next) = self.script.pop_front() {
+            match (next, timeout) {
+                (Some(key), _) => return Ok(Some(key)),
+                (None, Some(_)) => return Ok(None),
+                // Waiting forever, time passing doesn't end that
+                (None, None) => (),
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "ran out of scripted keys",
+        ))
+    }
+}
+
+pub struct Tui<B: Backend> {
+    backend: B,
+    keys: HashMap<Key, i64>,
+    idle: Option<i64>,
+    delay: Duration,
+    paused: bool,
+    frames: u64,
+    // What the picture covered last frame, anything else needs a full redraw
+    area: Option<Option<Rect>>,
+    status_len: usize,
+    // A backend failure stops the machine, finish() reports it
+    error: Option<io::Error>,
+}
+
+impl<B: Backend> Tui<B> {
+    pub fn new(backend: B) -> Tui<B> {
+        Tui {
+            backend,
+            keys: HashMap::new(),
+            idle: None,
+            delay: Duration::from_millis(100),
+            paused: false,
+            frames: 0,
+            area: None,
+            status_len: 0,
+            error: None,
+        }
+    }
+
+    // Send input when key is pressed
+    pub fn bind(&mut self, key: Key, input: i64) -> &mut Self {
+        self.keys.insert(key, input);
+        self
+    }
+
+    // Input sent when a frame goes by without a key, None to wait for one. Only used by run()
+    pub fn set_idle_input(&mut self, idle: Option<i64>) {
+        self.idle = idle;
+    }
+
+    pub fn set_frame_delay(&mut self, delay: Duration) {
+        self.delay = delay.min(MAX_DELAY);
+    }
+
+    pub fn frame_delay(&self) -> Duration {
+        self.delay
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    // Frames drawn so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+
+    // Bring the screen up to date with display, state goes in the status line. The whole screen is only
+    //  redrawn the first time and when the picture grows, otherwise just the cells that changed
+    pub fn render(&mut self, display: &mut Display, state: &str) -> io::Result<()> {
+        let bounds = display.bounds();
+        if self.area != Some(bounds) {
+            self.backend.clear()?;
+            if let Some(r) = bounds {
+                for (row, line) in display.render_rect(r).lines().enumerate() {
+                    self.backend.put(0, row as u16, line)?;
+                }
+            }
+            display.take_dirty();
+            self.backend.put(0, self.status_row(display) + 1, HELP)?;
+            self.area = Some(bounds);
+            self.status_len = 0;
+        } else if let Some(r) = bounds {
+            for (x, y) in display.take_dirty() {
+                let row = if display.y_up() {
+                    i128::from(r.max_y) - i128::from(y)
+                } else {
+                    i128::from(y) - i128::from(r.min_y)
+                };
+                let col = i128::from(x) - i128::from(r.min_x);
+                // Clipped off like in a full redraw
+                let limit = MAX_RENDER as i128;
+                if col >= limit || row >= limit {
+                    continue;
+                }
+                let glyph = display.glyph(x, y).to_string();
+                self.backend.put(col as u16, row as u16, &glyph)?;
+            }
+        }
+        self.draw_status(display, state)?;
+        self.backend.flush()
+    }
+
+    fn status_row(&self, display: &Display) -> u16 {
+        display
+            .bounds()
+            .map_or(0, |r| r.height().min(MAX_RENDER) as u16)
+    }
+
+    fn draw_status(&mut self, display: &Display, state: &str) -> io::Result<()> {
+        let mut status = String::new();
+        for (name, val) in display.channels() {
+            match val {
+                Some(v) => status += &format!("{} {}  ", name, v),
+                None => status += &format!("{} -  ", name),
+            }
+        }
+        status += &format!(
+            "{}  frame {}  {}ms/frame",
+            state,
+            self.frames,
+            self.delay.as_millis()
+        );
+        // Pad over whatever was there before
+        let len = status.chars().count();
+        status.extend(std::iter::repeat(' ').take(self.status_len.saturating_sub(len)));
+        self.status_len = len;
+        self.backend.put(0, self.status_row(display), &status)
+    }
+
+    fn state_word(&self) -> &'static str {
+        if self.paused {
+            "paused"
+        } else {
+            "running"
+        }
+    }
+
+    // Draw the frame and work out the input that ends it, with idle as described up top. None means quit
+    //  (or the backend failed)
+    pub fn frame(&mut self, display: &mut Display, idle: Option<i64>) -> Option<i64> {
+        self.frames += 1;
+        let state = self.state_word();
+        if let Err(e) = self.render(display, state) {
+            self.error = Some(e);
+            return None;
+        }
+        loop {
+            let timeout = match (self.paused, idle) {
+                (false, Some(_)) => Some(self.delay),
+                _ => None,
+            };
+            let key = match self.backend.poll_key(timeout) {
+                Ok(Some(key)) => key,
+                Ok(None) => return idle,
+                Err(e) => {
+                    self.error = Some(e);
+                    return None;
+                }
+            };
+            match key {
+                Key::Esc | Key::Char('q') => return None,
+                Key::Char('p') => self.paused = !self.paused,
+                Key::Char('n') if self.paused && idle.is_some() => return idle,
+                Key::Char('+') => self.delay /= 2,
+                Key::Char('-') => {
+                    self.delay = (self.delay * 2)
+                        .max(Duration::from_millis(1))
+                        .min(MAX_DELAY)
+                }
+                k => match self.keys.get(&k) {
+                    Some(&input) => return Some(input),
+                    None => continue,
+                },
+            }
+            // Something in the status line changed
+            let state = self.state_word();
+            if let Err(e) = self.draw_status(display, state).and(self.backend.flush()) {
+                self.error = Some(e);
+                return None;
+            }
+        }
+    }
+
+    // Draw the last frame with how the machine stopped, and report anything that went wrong with the
+    //  backend along the way
+    pub fn finish(&mut self, display: &mut Display, state: &RunState) -> io::Result<()> {
+        let state = match state {
+            RunState::Halted => "halted".to_string(),
+            RunState::AwaitingInput => "stopped".to_string(),
+            RunState::Error(e) => format!("error: {}", e),
+            s => format!("{:?}", s),
+        };
+        let drawn = self.render(display, &state);
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => drawn,
+        }
+    }
+
+    // Run comp to the end on display, a frame every time it asks for input
+    pub fn run(&mut self, comp: &mut IntcodeComp, display: &mut Display) -> io::Result<RunState> {
+        let state = comp.attach(&mut Frontend { tui: self, display });
+        self.finish(display, &state)?;
+        Ok(state)
+    }
+}
+
+// The device run() attaches, frames for input and the display for output
+struct Frontend<'a, B: Backend> {
+    tui: &'a mut Tui<B>,
+    display: &'a mut Display,
+}
+
+impl<B: Backend> InputSource for Frontend<'_, B> {
+    fn next_input(&mut self) -> Option<i64> {
+        let idle = self.tui.idle;
+        self.tui.frame(self.display, idle)
+    }
+}
+
+impl<B: Backend> OutputSink for Frontend<'_, B> {
+    fn push_output(&mut self, val: i64) {
+        self.display.push(val);
+    }
+}
+
+#[cfg(feature = "tui")]
+pub use self::terminal::Terminal;
+
+#[cfg(feature = "tui")]
+mod terminal {
+    use std::io::{self, Stdout, Write};
+    use std::time::{Duration, Instant};
+
+    use crossterm::cursor::{Hide, MoveTo, Show};
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::style::Print;
+    use crossterm::terminal::{self, Clear, ClearType};
+    use crossterm::{execute, queue};
+
+    use super::{Backend, Key};
+
+    // The real terminal, in raw mode for as long as this is alive. The picture is drawn on the normal
+    //  screen rather than the alternate one so the last frame is still there afterwards
+    pub struct Terminal {
+        out: Stdout,
+        // Lowest row drawn on, the cursor is left below it
+        bottom: u16,
+    }
+
+    impl Terminal {
+        pub fn new() -> io::Result<Terminal> {
+            terminal::enable_raw_mode()?;
+            let mut out = io::stdout();
+            if let Err(e) = execute!(out, Hide) {
+                terminal::disable_raw_mode().ok();
+                return Err(e);
+            }
+            Ok(Terminal { out, bottom: 0 })
+        }
+    }
+
+    impl Drop for Terminal {
+        fn drop(&mut self) {
+            execute!(self.out, MoveTo(0, self.bottom), Show).ok();
+            terminal::disable_raw_mode().ok();
+            println!();
+        }
+    }
+
+    // Ctrl-C doesn't interrupt in raw mode, treat it like Esc
+    fn translate(code: KeyCode, modifiers: KeyModifiers) -> Option<Key> {
+        Some(match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => Key::Esc,
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Enter => Key::Enter,
+            KeyCode::Esc => Key::Esc,
+            KeyCode::Char(c) => Key::Char(c),
+            _ => return None,
+        })
+    }
+
+    impl Backend for Terminal {
+        fn clear(&mut self) -> io::Result<()> {
+            self.bottom = 0;
+            queue!(self.out, Clear(ClearType::All))
+        }
+
+        fn put(&mut self, col: u16, row: u16, text: &str) -> io::Result<()> {
+            self.bottom = self.bottom.max(row);
+            queue!(self.out, MoveTo(col, row), Print(text))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.out.flush()
+        }
+
+        fn poll_key(&mut self, timeout: Option<Duration>) -> io::Result<Option<Key>> {
+            let deadline = timeout.map(|t| Instant::now() + t);
+            loop {
+                if let Some(d) = deadline {
+                    if !event::poll(d.saturating_duration_since(Instant::now()))? {
+                        return Ok(None);
+                    }
+                }
+                // Releases (on terminals that report them), resizes and the mouse are skipped
+                if let Event::Key(k) = event::read()? {
+                    if k.kind != KeyEventKind::Release {
+                        if let Some(key) = translate(k.code, k.modifiers) {
+                            return Ok(Some(key));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
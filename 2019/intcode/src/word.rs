@@ -0,0 +1,109 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+
+// What a memory cell holds. Programs are always loaded as i64 and converted, the machine only ever
+//  needs to add, multiply, compare and turn values into addresses
+pub trait Word:
+    Clone + Default + PartialEq + PartialOrd + fmt::Debug + fmt::Display + Send + Sync + 'static
+{
+    fn from_i64(v: i64) -> Self;
+    // None if the value doesn't fit
+    fn to_i64(&self) -> Option<i64>;
+    fn is_zero(&self) -> bool;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    // Two's complement wrap around, types that can't overflow just give the exact answer
+    fn wrapping_add(&self, other: &Self) -> Self;
+    fn wrapping_mul(&self, other: &Self) -> Self;
+
+    // Nearest i64, for addresses and error reports where anything that big is going to fail anyway
+    fn saturate(&self) -> i64 {
+        match self.to_i64() {
+            Some(v) => v,
+            None if *self < Self::default() => i64::MIN,
+            None => i64::MAX,
+        }
+    }
+}
+
+// What an add or multiply does when the result doesn't fit the word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    // Fault with IntcodeError::Overflow
+    Trap,
+    // Wrap around like the hardware would
+    Wrap,
+    // Stop with RunState::Overflowed before anything is written, promote() to a wider word and resume
+    Promote,
+}
+
+macro_rules! fixed_word {
+    ($t:ty) => {
+        impl Word for $t {
+            fn from_i64(v: i64) -> Self {
+                v as $t
+            }
+
+            fn to_i64(&self) -> Option<i64> {
+                i64::try_from(*self).ok()
+            }
+
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+
+            fn checked_add(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_add(*self, *other)
+            }
+
+            fn checked_mul(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_mul(*self, *other)
+            }
+
+            fn wrapping_add(&self, other: &Self) -> Self {
+                <$t>::wrapping_add(*self, *other)
+            }
+
+            fn wrapping_mul(&self, other: &Self) -> Self {
+                <$t>::wrapping_mul(*self, *other)
+            }
+        }
+    };
+}
+
+fixed_word!(i64);
+fixed_word!(i128);
+
+// Arbitrary precision, never overflows so the policy doesn't matter
+impl Word for BigInt {
+    fn from_i64(v: i64) -> Self {
+        BigInt::from(v)
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        ToPrimitive::to_i64(self)
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn wrapping_add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn wrapping_mul(&self, other: &Self) -> Self {
+        self * other
+    }
+}
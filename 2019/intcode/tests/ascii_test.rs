@@ -0,0 +1,88 @@
+extern crate intcode;
+use intcode::ascii::AsciiItem;
+use intcode::asm::assemble;
+use intcode::{IntcodeComp, RunState};
+
+// Prints a prompt, echoes one line back, then reports its length as a number outside the ASCII range
+fn echo() -> Vec<i64> {
+    assemble(
+        "
+            OUT #63     ; ?
+            OUT #10
+        loop:
+            IN [c]
+            OUT [c]
+            EQ [c], #10, [t]
+            JNZ [t], #done
+            ADD [len], #1, [len]
+            JZ #0, #loop
+        done:
+            ADD [len], #1000, [len]
+            OUT [len]
+            HLT
+        c:   .data 0
+        t:   .data 0
+        len: .data 0
+        ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn read_lines() {
+    let mut comp = IntcodeComp::new(&echo());
+    assert_eq!(comp.eval_async(), RunState::AwaitingInput);
+    assert_eq!(comp.read_line(), Some(AsciiItem::Text("?".to_string())));
+    assert_eq!(comp.read_line(), None);
+
+    comp.input_str("hel");
+    comp.eval_async();
+    // Partial lines stay queued until the newline shows up
+    assert_eq!(comp.read_line(), None);
+    assert_eq!(comp.output_available(), 3);
+    comp.input_line("lo");
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.read_line(), Some(AsciiItem::Text("hello".to_string())));
+    assert_eq!(comp.read_line(), Some(AsciiItem::Number(1005)));
+    assert_eq!(comp.read_line(), None);
+}
+#[test]
+fn drain() {
+    let mut comp = IntcodeComp::new(&echo());
+    comp.input_line("abc");
+    comp.eval_async();
+    let items = comp.drain_string();
+    assert_eq!(
+        items,
+        vec![
+            AsciiItem::Text("?\nabc\n".to_string()),
+            AsciiItem::Number(1003)
+        ]
+    );
+    assert_eq!(items[1].to_string(), "1003");
+    assert!(comp.drain_string().is_empty());
+}
+#[test]
+fn number_splits_text() {
+    let mut comp = IntcodeComp::new(&[104, 65, 104, 200, 104, 66, 99]);
+    comp.eval_async();
+    assert_eq!(
+        comp.drain_string(),
+        vec![
+            AsciiItem::Text("A".to_string()),
+            AsciiItem::Number(200),
+            AsciiItem::Text("B".to_string())
+        ]
+    );
+}
+#[test]
+fn number_ends_line() {
+    // "A: " then the answer with no newline in between
+    let mut comp = IntcodeComp::new(&[104, 65, 104, 58, 104, 32, 104, 12345, 104, 66, 99]);
+    comp.eval_async();
+    assert_eq!(comp.read_line(), Some(AsciiItem::Text("A: ".to_string())));
+    assert_eq!(comp.read_line(), Some(AsciiItem::Number(12345)));
+    // Trailing text still needs its newline
+    assert_eq!(comp.read_line(), None);
+    assert_eq!(comp.output_available(), 1);
+}
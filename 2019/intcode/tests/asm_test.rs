@@ -0,0 +1,126 @@
+extern crate intcode;
+use intcode::asm::{assemble, AsmError, AsmErrorKind};
+use intcode::disasm::disassemble;
+use intcode::prog_from_file;
+use intcode::IntcodeComp;
+
+// Programs from intcode_test written out in assembly
+#[test]
+fn equals_eight() {
+    let prog = assemble(
+        "
+        ; outputs 1 if the input is 8, else 0
+            IN [val]
+            EQ [val], [eight], [val]
+            OUT [val]
+            HLT
+        val:   .data -1
+        eight: .data 8
+        ",
+    )
+    .unwrap();
+    assert_eq!(prog, vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8]);
+}
+#[test]
+fn self_copy() {
+    let prog = assemble(
+        "
+        start:
+            RBO #1
+            OUT rb-1
+            ADD [100], #1, [100]
+            EQ [100], #16, [101]
+            JZ [101], #start
+            HLT
+        ",
+    )
+    .unwrap();
+    assert_eq!(
+        prog,
+        vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99]
+    );
+}
+
+// day_07's first feedback loop sample
+#[test]
+fn amplifier_feedback() {
+    let prog = assemble(
+        "
+            IN [phase]
+            ADD [phase], #-4, [phase]
+        loop:
+            IN [signal]
+            MUL [signal], #2, [signal]
+            ADD [signal], [phase], [signal]
+            OUT [signal]
+            ADD [count], #-1, [count]
+            JNZ [count], #loop
+            HLT
+        phase:  .data 0
+        signal: .data 0
+        count:  .data 5
+        ",
+    )
+    .unwrap();
+    assert_eq!(
+        prog,
+        vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5
+        ]
+    );
+}
+#[test]
+fn label_offsets_and_case() {
+    let prog = assemble("out  [table+1]\nhlt\ntable: .data 7, 8, table, end-1\nend:").unwrap();
+    assert_eq!(prog, vec![4, 4, 99, 7, 8, 3, 6]);
+    let mut comp = IntcodeComp::new(&prog);
+    comp.run_all().unwrap();
+    assert_eq!(comp.output(), Some(8));
+}
+#[test]
+fn errors() {
+    let err = |src: &str| assemble(src).unwrap_err();
+    assert_eq!(
+        err("HLT\nFOO #1"),
+        AsmError {
+            line: 2,
+            kind: AsmErrorKind::UnknownMnemonic("FOO".to_string())
+        }
+    );
+    assert_eq!(
+        err("ADD #1, #2").kind,
+        AsmErrorKind::OperandCount {
+            expected: 3,
+            found: 2
+        }
+    );
+    assert_eq!(err("IN #4").kind, AsmErrorKind::ImmediateDest(1));
+    assert_eq!(err("OUT 4").kind, AsmErrorKind::BadOperand("4".to_string()));
+    assert_eq!(
+        err("JZ #0, #nowhere").kind,
+        AsmErrorKind::UnknownLabel("nowhere".to_string())
+    );
+    assert_eq!(
+        err("a: HLT\na: HLT").kind,
+        AsmErrorKind::DuplicateLabel("a".to_string())
+    );
+    assert_eq!(
+        err(".word 1").to_string(),
+        "line 1: unknown directive .word"
+    );
+}
+
+// Disassembling then reassembling the puzzle programs must give back the same image
+#[test]
+fn round_trip() {
+    for path in &[
+        "../day_09/BOOST.txt",
+        "../day_11/painter.txt",
+        "../day_13/game.txt",
+        "../day_07/ACS.txt",
+    ] {
+        let prog = prog_from_file(path);
+        assert_eq!(assemble(&disassemble(&prog)).unwrap(), prog, "{}", path);
+    }
+}
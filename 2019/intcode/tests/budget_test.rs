@@ -0,0 +1,151 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::{Budget, IntcodeComp, RunState};
+use std::time::{Duration, Instant};
+
+// Counts down from the input, outputting each value
+const COUNTDOWN: &str = "
+        IN [n]
+    loop:
+        OUT [n]
+        ADD [n], #-1, [n]
+        JNZ [n], #loop
+        HLT
+    n: .data 0
+";
+
+#[test]
+fn instruction_budget() {
+    let mut comp = IntcodeComp::new(&assemble(COUNTDOWN).unwrap());
+    comp.input(3);
+    comp.set_instruction_budget(Some(5));
+    assert_eq!(
+        comp.eval_async(),
+        RunState::BudgetExhausted(Budget::Instructions)
+    );
+    assert_eq!(comp.instruction_budget(), Some(0));
+    assert_eq!(comp.program_counter(), 4);
+    // Stays out of budget until it is raised, then carries on where it stopped
+    assert_eq!(
+        comp.eval_async(),
+        RunState::BudgetExhausted(Budget::Instructions)
+    );
+    comp.set_instruction_budget(Some(100));
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output_queue(), &[3, 2, 1]);
+    // IN, 3 times OUT/ADD/JNZ and the HLT, less the 5 that ran first
+    assert_eq!(comp.instruction_budget(), Some(100 - 6));
+
+    comp.set_instruction_budget(None);
+    assert_eq!(comp.instruction_budget(), None);
+}
+
+#[test]
+fn blocking_is_free() {
+    // Waiting on input doesn't use up budget, the IN runs once it gets a value
+    let mut comp = IntcodeComp::new(&[3, 0, 99]);
+    comp.set_instruction_budget(Some(2));
+    assert_eq!(comp.eval_async(), RunState::AwaitingInput);
+    assert_eq!(comp.eval_async(), RunState::AwaitingInput);
+    comp.input(7);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.instruction_budget(), Some(0));
+}
+
+#[test]
+fn time_budget() {
+    // A counter loop, loop detection can't see through it
+    let mut comp =
+        IntcodeComp::new(&assemble("loop: ADD [n], #1, [n]\nJZ #0, #loop\nn: .data 0").unwrap());
+    comp.set_time_budget(Some(Duration::from_millis(20)));
+    let start = Instant::now();
+    assert_eq!(
+        comp.eval_async(),
+        RunState::BudgetExhausted(Budget::WallClock)
+    );
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert!(comp.read_mem(7) > 0);
+
+    comp.set_time_budget(None);
+    comp.set_instruction_budget(Some(10));
+    assert_eq!(
+        comp.eval_async(),
+        RunState::BudgetExhausted(Budget::Instructions)
+    );
+}
+
+#[test]
+fn cycles() {
+    // Spins on a flag that nothing will ever set
+    let mut comp =
+        IntcodeComp::new(&assemble("spin: JZ [flag], #spin\nHLT\nflag: .data 0").unwrap());
+    comp.set_loop_detection(true);
+    assert_eq!(comp.eval_async(), RunState::Cycling(0));
+
+    // Bouncing between two places while writing the same value over and over is still stuck
+    let prog = assemble(
+        "
+        a:  ADD #1, #0, [x]
+            JZ #0, #b
+            HLT
+        b:  OUT [x]
+            JZ #0, #a
+        x:  .data 0
+        ",
+    )
+    .unwrap();
+    let mut comp = IntcodeComp::new(&prog);
+    comp.set_loop_detection(true);
+    assert_eq!(comp.eval_async(), RunState::Cycling(0));
+    assert_eq!(comp.output_queue(), &[1, 1]);
+}
+
+#[test]
+fn progress_isnt_a_cycle() {
+    // Counters and input keep changing the state, these all finish
+    let mut comp = IntcodeComp::new(&assemble(COUNTDOWN).unwrap());
+    comp.set_loop_detection(true);
+    comp.input(1000);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output_available(), 1000);
+
+    // Reads the same value into the same cell every time round, each input is a new state
+    let prog = assemble("loop: IN [x]\nOUT [x]\nJZ #0, #loop\nx: .data 0").unwrap();
+    let mut comp = IntcodeComp::new(&prog);
+    comp.set_loop_detection(true);
+    for _ in 0..5 {
+        comp.input(0);
+    }
+    assert_eq!(comp.eval_async(), RunState::AwaitingInput);
+    assert_eq!(comp.output_available(), 5);
+
+    // Moving the relative base is progress too, the budget catches this one
+    let mut comp = IntcodeComp::new(&assemble("loop: RBO #1\nJZ #0, #loop").unwrap());
+    comp.set_loop_detection(true);
+    comp.set_instruction_budget(Some(1_000_000));
+    assert_eq!(
+        comp.eval_async(),
+        RunState::BudgetExhausted(Budget::Instructions)
+    );
+}
+
+#[test]
+fn restore_forgets_states() {
+    // Spins at 0 until flag is set
+    let prog = assemble("spin: JZ [flag], #spin\nHLT\nJZ #0, #spin\nflag: .data 0").unwrap();
+    let mut comp = IntcodeComp::new(&prog);
+    comp.set_loop_detection(true);
+    comp.set_instruction_budget(Some(1));
+    assert_eq!(
+        comp.eval_async(),
+        RunState::BudgetExhausted(Budget::Instructions)
+    );
+
+    // Come back to 0 with the flag set, that's the same pc and relative base but not the same state
+    let mut snap = comp.snapshot();
+    snap.program_counter = 4;
+    snap.mem_space[7] = 1;
+    comp.restore(&snap);
+    comp.set_instruction_budget(None);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+}
@@ -0,0 +1,140 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::{prog_from_file, IntcodeComp, RunState};
+
+// The cache only starts filling once a machine has been running for a while, burn a few hundred
+//  instructions first so the code under test actually goes through it
+fn warmed(src: &str) -> Vec<i64> {
+    assemble(&format!(
+        "
+            JZ #0, #warm
+        spin: .data 150
+        warm:
+            ADD [spin], #-1, [spin]
+            JNZ [spin], #warm
+        {}",
+        src
+    ))
+    .unwrap()
+}
+
+// Run a program with and without the decode cache, everything observable has to match
+fn run_both(prog: &[i64], inputs: &[i64]) -> (RunState, Vec<i64>, IntcodeComp) {
+    let mut results = Vec::new();
+    for cached in &[true, false] {
+        let mut comp = IntcodeComp::new(prog);
+        comp.set_decode_cache(*cached);
+        for &i in inputs {
+            comp.input(i);
+        }
+        let state = comp.eval_async();
+        let mut out = Vec::new();
+        while let Some(v) = comp.output() {
+            out.push(v);
+        }
+        results.push((state, out, comp));
+    }
+    let uncached = results.pop().unwrap();
+    let cached = results.pop().unwrap();
+    assert_eq!(cached.0, uncached.0);
+    assert_eq!(cached.1, uncached.1);
+    assert_eq!(cached.2.snapshot(), uncached.2.snapshot());
+    cached
+}
+
+#[test]
+fn patched_operand() {
+    // The OUT operand is bumped every time round so the same instruction prints 3 different cells
+    let prog = warmed(
+        "
+        loop:
+            OUT [a]
+            ADD [loop+1], #1, [loop+1]
+            ADD [n], #-1, [n]
+            JNZ [n], #loop
+            HLT
+        a: .data 10, 20, 30
+        n: .data 3
+        ",
+    );
+    let (state, out, _) = run_both(&prog, &[]);
+    assert_eq!(state, RunState::Halted);
+    assert_eq!(out, vec![10, 20, 30]);
+}
+
+#[test]
+fn patched_opcode() {
+    // First pass adds, then the instruction word is rewritten to multiply
+    let prog = warmed(
+        "
+        loop:
+        op: ADD [x], [x], [x]
+            OUT [x]
+            ADD #2, #0, [op]
+            ADD [n], #-1, [n]
+            JNZ [n], #loop
+            HLT
+        x: .data 3
+        n: .data 3
+        ",
+    );
+    // 3+3, 6*6, 36*36
+    let (_, out, _) = run_both(&prog, &[]);
+    assert_eq!(out, vec![6, 36, 1296]);
+}
+
+#[test]
+fn written_past_the_end() {
+    // The last word is OUT #p where p is past the end and reads as 0, once it has run (and been
+    //  cached) p is written, the next run has to see the new value
+    let prog = warmed(
+        "
+            ADD #1106, #0, [last+2]     ; JZ #0, #back after the OUT
+            ADD #0, #0, [last+3]
+            ADD #back, #0, [last+4]
+            JZ #0, #last
+        back:
+            JNZ [done], #end
+            ADD #5, #0, [last+1]
+            ADD #1, #0, [done]
+            JZ #0, #last
+        end:
+            HLT
+        done: .data 0
+        last: .data 104
+        ",
+    );
+    let (state, out, _) = run_both(&prog, &[]);
+    assert_eq!(state, RunState::Halted);
+    assert_eq!(out, vec![0, 5]);
+}
+
+#[test]
+fn real_programs() {
+    let boost = prog_from_file("../day_09/BOOST.txt");
+    assert_eq!(run_both(&boost, &[2]).1, vec![82760]);
+    assert_eq!(run_both(&boost, &[1]).1, vec![3409270027]);
+
+    let painter = prog_from_file("../day_11/painter.txt");
+    let (state, out, _) = run_both(&painter, &[0, 1, 0, 0]);
+    assert_eq!(state, RunState::AwaitingInput);
+    assert_eq!(out.len(), 8);
+}
+
+#[test]
+fn restore_drops_cache() {
+    let prog = warmed("IN [x]\nOUT [x]\nHLT\nx: .data 0");
+    let mut comp = IntcodeComp::new(&prog);
+    let start = comp.snapshot();
+    comp.input(1);
+    comp.eval_async();
+
+    // Same addresses, different code
+    let mut snap = start.clone();
+    snap.mem_space = warmed("IN [x]\nMUL [x], #2, [x]\nOUT [x]\nHLT\nx: .data 0");
+    comp.restore(&snap);
+    comp.output();
+    comp.input(5);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output(), Some(10));
+}
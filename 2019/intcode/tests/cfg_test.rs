@@ -0,0 +1,156 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::cfg::{Cfg, Edge, EdgeKind, SelfWrite};
+use intcode::{prog_from_file, IntcodeError};
+
+fn edge(from: usize, to: Option<usize>, kind: EdgeKind) -> Edge {
+    Edge { from, to, kind }
+}
+
+#[test]
+fn loop_blocks() {
+    let prog = assemble(
+        "
+            IN [n]
+        loop:
+            OUT [n]
+            ADD [n], #-1, [n]
+            JNZ [n], #loop
+            HLT
+        n: .data 0
+        ",
+    )
+    .unwrap();
+    let cfg = Cfg::build(&prog);
+    assert_eq!(
+        cfg.blocks.keys().copied().collect::<Vec<_>>(),
+        vec![0, 2, 11]
+    );
+    assert_eq!(cfg.blocks[&2].insts.len(), 3);
+    assert_eq!(cfg.blocks[&2].end(), 11);
+    assert_eq!(
+        cfg.edges,
+        vec![
+            edge(0, Some(2), EdgeKind::Fallthrough),
+            edge(2, Some(2), EdgeKind::Taken),
+            edge(2, Some(11), EdgeKind::Fallthrough),
+        ]
+    );
+    assert!(cfg.self_writes.is_empty());
+    assert_eq!(cfg.block_at(6).unwrap().start, 2);
+    assert!(cfg.block_at(12).is_none()); // Data
+}
+
+#[test]
+fn calls_and_returns() {
+    // Return addresses are pushed as constants and the subroutine returns through memory
+    let prog = assemble(
+        "
+            ADD #ret1, #0, [retaddr]
+            JZ #0, #double
+        ret1:
+            OUT [x]
+            ADD #ret2, #0, [retaddr]
+            JNZ #1, #double
+        ret2:
+            OUT [x]
+            HLT
+        double:
+            MUL [x], #2, [x]
+            JZ #0, [retaddr]
+        x: .data 3
+        retaddr: .data 0
+        ",
+    )
+    .unwrap();
+    let cfg = Cfg::build(&prog);
+    let double = 19;
+    assert_eq!(
+        cfg.blocks.keys().copied().collect::<Vec<_>>(),
+        vec![0, 7, 16, double]
+    );
+    assert!(cfg.edges.contains(&edge(0, Some(double), EdgeKind::Taken)));
+    assert!(cfg.edges.contains(&edge(7, Some(double), EdgeKind::Taken)));
+    assert!(cfg
+        .edges
+        .contains(&edge(double, None, EdgeKind::Unresolved)));
+    // Unconditional jumps don't fall through
+    assert!(!cfg.edges.contains(&edge(0, Some(7), EdgeKind::Fallthrough)));
+}
+
+#[test]
+fn never_taken_jump() {
+    let prog = assemble("JNZ #0, #5\nHLT\nHLT\nHLT").unwrap();
+    let cfg = Cfg::build(&prog);
+    assert_eq!(cfg.edges, vec![edge(0, Some(3), EdgeKind::Fallthrough)]);
+}
+
+#[test]
+fn self_writes() {
+    // Patches the operand of the OUT that follows it
+    let prog = assemble(
+        "
+            IN [patch+1]
+        patch:
+            OUT #0
+            HLT
+        ",
+    )
+    .unwrap();
+    let cfg = Cfg::build(&prog);
+    assert_eq!(
+        cfg.self_writes,
+        vec![SelfWrite {
+            pc: 0,
+            addr: 3,
+            target: 2
+        }]
+    );
+    assert!(cfg.to_dot().contains("    2: OUT #0  ; written\\l"));
+}
+
+#[test]
+fn faults() {
+    // Runs straight off into a word that doesn't decode
+    let cfg = Cfg::build(&[1101, 1, 2, 5, 0]);
+    assert_eq!(
+        cfg.blocks[&4].fault,
+        Some(IntcodeError::UnknownOpcode { pc: 4, word: 0 })
+    );
+    assert_eq!(cfg.edges, vec![edge(0, Some(4), EdgeKind::Fallthrough)]);
+}
+
+#[test]
+fn dot() {
+    let prog = assemble("IN [x]\nJZ [x], #end\nJZ #0, [x]\nend: HLT\nx: .data 0").unwrap();
+    let dot = Cfg::build(&prog).to_dot();
+    assert_eq!(
+        dot,
+        "digraph intcode {
+    node [shape=box, fontname=\"monospace\"];
+    b0 [label=\"    0: IN [9]\\l    2: JZ [9], #8\\l\"];
+    b5 [label=\"    5: JZ #0, [9]\\l\"];
+    b8 [label=\"    8: HLT\\l\"];
+    unresolved [shape=diamond, label=\"?\"];
+    b0 -> b8 [label=\"taken\"];
+    b0 -> b5;
+    b5 -> unresolved [style=dashed];
+}
+"
+    );
+}
+
+#[test]
+fn painter() {
+    let cfg = Cfg::build(&prog_from_file("../day_11/painter.txt"));
+    // The main loop and the subroutine called through the relative base
+    assert!(cfg.blocks.contains_key(&315));
+    assert!(cfg.edges.iter().any(|e| e.kind == EdgeKind::Unresolved));
+    // Its start up code patches the operands of its own compares
+    assert!(cfg.self_writes.contains(&SelfWrite {
+        pc: 33,
+        addr: 29,
+        target: 27
+    }));
+    assert!(cfg.to_dot().starts_with("digraph intcode {\n"));
+}
@@ -0,0 +1,78 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Run the intcode binary with prog on stdin, returns the exit status and stdout
+fn run(prog: &str, args: &[&str]) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_intcode"))
+        .arg("-")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(prog.as_bytes())
+        .unwrap();
+    let out = child.wait_with_output().unwrap();
+    (
+        out.status.code().unwrap(),
+        String::from_utf8(out.stdout).unwrap(),
+    )
+}
+
+// Adds its two inputs and outputs the sum
+const ADD: &str = "3,11,3,12,1,11,12,13,4,13,99,0,0,0";
+
+#[test]
+fn inputs_and_status() {
+    assert_eq!(run(ADD, &["4", "-7"]), (0, "-3\n".to_string()));
+    assert_eq!(run(ADD, &["4"]), (3, String::new()));
+    assert_eq!(run("1,0,0,0,77", &[]).0, 1);
+    assert_eq!(run("1,x", &[]).0, 2);
+    assert_eq!(run(ADD, &["4", "--bogus"]).0, 2);
+
+    let path = std::env::temp_dir().join("intcode_cli_test.in");
+    std::fs::write(&path, "4\n5\n").unwrap();
+    let (status, out) = run(ADD, &["-f", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).ok();
+    assert_eq!((status, out.as_str()), (0, "9\n"));
+}
+
+#[test]
+fn patch_and_formats() {
+    // Day 2 style, the answer is left in cell 0
+    let (status, out) = run("1,0,0,0,99", &["-s", "1=4", "-p", "0", "-o", "json"]);
+    assert_eq!(status, 0);
+    assert_eq!(
+        out,
+        "{\"state\":\"halted\",\"outputs\":[],\"memory\":{\"0\":100}}\n"
+    );
+    let (status, out) = run("3,0,4,0,99", &["-o", "json"]);
+    assert_eq!(status, 3);
+    assert_eq!(out, "{\"state\":\"awaiting_input\",\"outputs\":[]}\n");
+
+    // Echoes two characters then outputs 500
+    let echo = "3,0,4,0,3,0,4,0,104,500,99";
+    assert_eq!(
+        run(echo, &["-t", "H", "-o", "ascii"]),
+        (0, "H\n500\n".to_string())
+    );
+    assert_eq!(run(echo, &["-t", "H"]), (0, "72\n10\n500\n".to_string()));
+}
+
+#[test]
+fn far_patches() {
+    // A page for the one cell, not a terabyte of program
+    let (status, out) = run("99", &["-s", "1000000000000=5", "-p", "1000000000000"]);
+    assert_eq!((status, out.as_str()), (0, "[1000000000000] = 5\n"));
+
+    // Past the limit is a bad argument, not a fault
+    assert_eq!(run("99", &["-m", "300", "-s", "100000=1"]).0, 2);
+    assert_eq!(run("99", &["-m", "300", "-s", "100=1"]).0, 0);
+    // The program's own writes are held to the same limit
+    assert_eq!(run("1101,1,1,100000,99", &["-m", "300"]).0, 1);
+}
@@ -0,0 +1,79 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::{IntcodeComp, RunState};
+
+// Counts down from the input, outputting each value
+fn countdown() -> Vec<i64> {
+    assemble(
+        "
+            IN [n]
+        loop:
+            OUT [n]
+            ADD [n], #-1, [n]
+            JNZ [n], #loop
+            HLT
+        n: .data 0
+        ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn single_step() {
+    let mut comp = IntcodeComp::new(&countdown());
+    comp.input(2);
+    assert_eq!(comp.step(), RunState::Running);
+    assert_eq!(comp.program_counter(), 2);
+    assert!(comp.input_queue().is_empty());
+    assert_eq!(comp.step(), RunState::Running);
+    assert_eq!(comp.output_queue().iter().collect::<Vec<_>>(), vec![&2]);
+    assert_eq!(comp.rel_base(), 0);
+}
+#[test]
+fn breakpoints() {
+    let mut comp = IntcodeComp::new(&countdown());
+    comp.input(3);
+    comp.add_breakpoint(2);
+    comp.add_breakpoint(0); // first instruction of a run is never a stop
+    assert_eq!(comp.breakpoints(), vec![0, 2]);
+    assert_eq!(comp.eval_async(), RunState::Breakpoint(2));
+    assert_eq!(comp.program_counter(), 2);
+
+    // Resuming executes the instruction at the breakpoint and stops the next time round the loop
+    assert_eq!(comp.eval_async(), RunState::Breakpoint(2));
+    assert_eq!(comp.output_queue().len(), 1);
+
+    assert!(comp.remove_breakpoint(2));
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output_queue().len(), 3);
+}
+#[test]
+fn watchpoints() {
+    let prog = countdown();
+    let n = prog.len() - 1;
+    let mut comp = IntcodeComp::new(&prog);
+    comp.input(2);
+    comp.add_watchpoint(n);
+    assert_eq!(
+        comp.eval_async(),
+        RunState::Watchpoint {
+            addr: n,
+            old: 0,
+            new: 2
+        }
+    );
+    // Stops after the writing instruction has completed
+    assert_eq!(comp.program_counter(), 2);
+    assert_eq!(comp.read_mem(n), 2);
+    assert_eq!(
+        comp.eval_async(),
+        RunState::Watchpoint {
+            addr: n,
+            old: 2,
+            new: 1
+        }
+    );
+    assert_eq!(comp.watchpoints(), vec![n]);
+    assert!(comp.remove_watchpoint(n));
+    assert_eq!(comp.eval_async(), RunState::Halted);
+}
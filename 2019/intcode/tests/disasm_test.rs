@@ -0,0 +1,84 @@
+extern crate intcode;
+use intcode::disasm::{disassemble, listing, Listing};
+use intcode::{AddressMode, IntcodeComp, IntcodeError, Opcodes};
+
+#[test]
+fn decode_fields() {
+    let comp = IntcodeComp::new(&[21101, 12, 5, 3, 99]);
+    let inst = comp.decode(0).unwrap();
+    assert_eq!(inst.opcode, Opcodes::Add);
+    assert_eq!(
+        inst.modes,
+        [
+            AddressMode::Immediate,
+            AddressMode::Immediate,
+            AddressMode::Relative
+        ]
+    );
+    assert_eq!(inst.operands(), &[12, 5, 3]);
+    assert_eq!(inst.width(), 4);
+    assert_eq!(comp.decode(4).unwrap().opcode, Opcodes::Halt);
+}
+#[test]
+fn decode_errors() {
+    let comp = IntcodeComp::new(&[42, 11101, 1, 1, 1]);
+    assert_eq!(
+        comp.decode(0),
+        Err(IntcodeError::UnknownOpcode { pc: 0, word: 42 })
+    );
+    assert_eq!(
+        comp.decode(1),
+        Err(IntcodeError::ImmediateWrite {
+            pc: 1,
+            word: 11101,
+            operand: 3
+        })
+    );
+}
+#[test]
+fn render_operands() {
+    let comp = IntcodeComp::new(&[21001, 12, 5, -3, 99]);
+    assert_eq!(comp.decode(0).unwrap().to_string(), "ADD [12], #5, rb-3");
+    let comp = IntcodeComp::new(&[1101, 12, 5, 3]);
+    assert_eq!(comp.decode(0).unwrap().to_string(), "ADD #12, #5, [3]");
+    let comp = IntcodeComp::new(&[204, 7]);
+    assert_eq!(comp.decode(0).unwrap().to_string(), "OUT rb+7");
+}
+#[test]
+fn data_regions() {
+    // The equals-8 test program, code followed by two data words
+    let prog = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+    let lines = listing(&prog);
+    assert_eq!(lines.len(), 5);
+    assert_eq!(
+        lines[4],
+        Listing::Data {
+            addr: 9,
+            words: vec![-1, 8]
+        }
+    );
+    assert_eq!(
+        disassemble(&prog),
+        "IN [9]                          ; 0\n\
+         EQ [9], [10], [9]               ; 2\n\
+         OUT [9]                         ; 6\n\
+         HLT                             ; 8\n\
+         .data -1, 8                     ; 9\n"
+    );
+}
+#[test]
+fn non_canonical_is_data() {
+    // Mode digit on OUT's missing second parameter would be lost by reassembly
+    let lines = listing(&[1104, 5, 99]);
+    assert_eq!(
+        lines[0],
+        Listing::Data {
+            addr: 0,
+            words: vec![1104, 5]
+        }
+    );
+    // Truncated instruction at the end of the image
+    let lines = listing(&[99, 1, 0]);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].addr(), 1);
+}
@@ -0,0 +1,183 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::display::{Display, Palette, Rect, Update, MAX_RENDER};
+use intcode::IntcodeComp;
+use std::collections::VecDeque;
+
+fn feed(d: &mut Display, vals: &[i64]) -> Vec<Update> {
+    vals.iter().filter_map(|&v| d.push(v)).collect()
+}
+
+#[test]
+fn triples() {
+    let mut d = Display::new();
+    d.add_channel("score", -1, 0);
+    assert_eq!(d.channel("score"), None);
+    assert!(d.is_empty());
+
+    let updates = feed(&mut d, &[1, 2, 3, -1, 0, 500, 1, 2, 4, 7]);
+    assert_eq!(
+        updates,
+        vec![
+            Update::Pixel {
+                x: 1,
+                y: 2,
+                old: 0,
+                new: 3
+            },
+            Update::Channel {
+                x: -1,
+                y: 0,
+                value: 500
+            },
+            Update::Pixel {
+                x: 1,
+                y: 2,
+                old: 3,
+                new: 4
+            },
+        ]
+    );
+    // Half a triple is held back until the rest arrives
+    assert_eq!(d.get(7, 0), 0);
+    assert_eq!(feed(&mut d, &[0]), vec![]);
+    assert_eq!(feed(&mut d, &[9]).len(), 1);
+    assert_eq!(d.get(7, 0), 9);
+
+    assert_eq!(d.channel("score"), Some(500));
+    assert_eq!(d.len(), 2);
+    assert_eq!(d.count(4), 1);
+    assert_eq!(d.find(9), Some((7, 0)));
+    assert_eq!(d.find(5), None);
+}
+
+#[test]
+fn canvas_grows() {
+    let mut d = Display::new();
+    d.set_background(1);
+    d.set_palette(Palette::new(&[(1, '.'), (2, '#')]));
+    assert_eq!(d.bounds(), None);
+    assert_eq!(d.to_string(), "");
+
+    d.set(0, 0, 2);
+    d.set(-2, 1, 2);
+    d.set(1, -1, 42);
+    assert_eq!(
+        d.bounds(),
+        Some(Rect {
+            min_x: -2,
+            min_y: -1,
+            max_x: 1,
+            max_y: 1
+        })
+    );
+    assert_eq!(d.get(-1, 0), 1);
+    assert_eq!(d.to_string(), "...?\n..#.\n#...\n");
+    d.set_y_up(true);
+    assert_eq!(d.to_string(), "#...\n..#.\n...?\n");
+
+    // Digits draw themselves without a palette entry
+    let mut p = Palette::default();
+    p.set_unknown('*');
+    assert_eq!(p.glyph(7), '7');
+    assert_eq!(p.glyph(10), '*');
+}
+
+#[test]
+fn far_apart() {
+    // Pixels at opposite ends of i64 and far enough apart to run out of memory drawing everything
+    let mut d = Display::new();
+    feed(&mut d, &[0, 0, 1, i64::MAX, 0, 1]);
+    let r = d.bounds().unwrap();
+    assert_eq!((r.width(), r.height()), (1 << 63, 1));
+    let text = d.to_string();
+    assert_eq!(text.len(), MAX_RENDER + 1);
+    assert!(text.starts_with("100"));
+
+    let mut d = Display::new();
+    d.set(i64::MIN, i64::MIN, 1);
+    d.set(i64::MAX, i64::MAX, 1);
+    d.set(0, 1_000_000_000_000, 1);
+    let r = d.bounds().unwrap();
+    assert_eq!((r.width(), r.height()), (usize::MAX, usize::MAX));
+    assert_eq!(d.to_string().lines().count(), MAX_RENDER);
+    d.set_y_up(true);
+    assert_eq!(d.take_dirty()[0], (i64::MAX, i64::MAX));
+    assert!(d
+        .to_string()
+        .starts_with(&format!("{}\n", "0".repeat(MAX_RENDER))));
+}
+
+#[test]
+fn dirty_regions() {
+    let mut d = Display::new();
+    feed(&mut d, &[0, 0, 1, 3, 2, 1, 1, 1, 0]);
+    // Drawing the background over a cell that never held anything else changes nothing
+    assert_eq!(
+        d.dirty(),
+        Some(Rect {
+            min_x: 0,
+            min_y: 0,
+            max_x: 3,
+            max_y: 2
+        })
+    );
+    assert_eq!(d.take_dirty(), vec![(0, 0), (3, 2)]);
+    assert_eq!(d.dirty(), None);
+
+    // Next frame, only what moved
+    feed(&mut d, &[0, 0, 0, 1, 0, 1, 3, 2, 1]);
+    assert_eq!(d.take_dirty(), vec![(0, 0), (1, 0)]);
+    let r = Rect {
+        min_x: 0,
+        min_y: 0,
+        max_x: 1,
+        max_y: 0,
+    };
+    assert!(r.contains(1, 0) && !r.contains(2, 0));
+    assert_eq!((r.width(), r.height()), (2, 1));
+    assert_eq!(d.render_rect(r), "01\n");
+}
+
+// Draws a 3 wide box with 2 in the middle, then a score of 77
+const BOX: &str = "
+        OUT [x]
+        OUT [y]
+        OUT [t]
+        ADD [x], #1, [x]
+        LT [x], #3, [more]
+        JNZ [more], #0
+        ADD [y], #1, [y]
+        ADD #0, #0, [x]
+        LT [y], #3, [more]
+        JNZ [more], #0
+        OUT #-1
+        OUT #0
+        OUT #77
+        HLT
+    x:    .data 0
+    y:    .data 0
+    t:    .data 1
+    more: .data 0
+";
+
+#[test]
+fn as_a_device() {
+    let mut prog = assemble(BOX).unwrap();
+    let mut d = Display::new();
+    d.add_channel("score", -1, 0);
+    d.set_palette(Palette::new(&[(1, '#')]));
+    let mut comp = IntcodeComp::new(&prog);
+    comp.run_with(&mut VecDeque::new(), &mut d);
+    assert_eq!(d.to_string(), "###\n###\n###\n");
+    assert_eq!(d.channel("score"), Some(77));
+
+    // Same program writing 2s, only the cells that changed need drawing again
+    let t = prog.len() - 2;
+    prog[t] = 2;
+    d.set(1, 1, 2);
+    d.take_dirty();
+    IntcodeComp::new(&prog).run_with(&mut VecDeque::new(), &mut d);
+    assert_eq!(d.to_string(), "222\n222\n222\n");
+    assert_eq!(d.take_dirty().len(), 8);
+}
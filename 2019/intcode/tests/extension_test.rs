@@ -0,0 +1,186 @@
+extern crate intcode;
+use intcode::extension::{ExtensionError, Flow, Param, Registry};
+use intcode::{IntcodeComp, IntcodeError, RunState, StopOn};
+use std::sync::{Arc, Mutex};
+
+fn comp_with(prog: &[i64], ext: Registry) -> IntcodeComp {
+    let mut comp = IntcodeComp::new(prog);
+    comp.set_extensions(ext);
+    comp
+}
+
+#[test]
+fn debug_print() {
+    let printed = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&printed);
+    let mut ext: Registry = Registry::new();
+    ext.define(42, "PRT", &[Param::Read], move |op| {
+        log.lock().unwrap().push(op.arg(1)?);
+        Ok(Flow::Next)
+    })
+    .unwrap();
+    assert_eq!(ext.name(42), Some("PRT"));
+    assert_eq!(ext.arity(42), Some(1));
+
+    // PRT [9], PRT #-3, PRT rb+7 with the relative base moved to 1
+    let mut comp = comp_with(&[109, 1, 42, 9, 142, -3, 242, 7, 99, 11], ext);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(*printed.lock().unwrap(), vec![11, -3, 99]);
+}
+
+#[test]
+fn writes_can_be_undone() {
+    let mut ext: Registry = Registry::new();
+    ext.define(50, "SWAP", &[Param::Write, Param::Write], |op| {
+        let (a, b) = (op.arg(1)?, op.arg(2)?);
+        op.set(1, b)?;
+        op.set(2, a)?;
+        Ok(Flow::Next)
+    })
+    .unwrap();
+    let mut comp = comp_with(&[50, 4, 5, 99, 7, 8], ext);
+    comp.enable_journal(10);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp._int_mem(), &vec![50, 4, 5, 99, 8, 7]);
+    // Both writes belong to one instruction
+    assert_eq!(comp.step_back(5), 1);
+    assert_eq!(comp._int_mem(), &vec![50, 4, 5, 99, 7, 8]);
+    assert_eq!(comp.program_counter(), 0);
+
+    // The second write is found too
+    comp.eval_async();
+    assert_eq!(comp.back_to_write(5), Some(0));
+    assert_eq!(comp.read_mem(5), 8);
+}
+
+#[test]
+fn io() {
+    // Reads x and outputs x then x times the parameter
+    let mut ext: Registry = Registry::new();
+    ext.define(60, "SPREAD", &[Param::Read], |op| {
+        let x = match op.input() {
+            Some(x) => x,
+            None => return Ok(Flow::Blocked),
+        };
+        op.output(x);
+        op.output(x * op.arg(1)?);
+        Ok(Flow::Next)
+    })
+    .unwrap();
+    let mut comp = comp_with(&[160, 10, 99], ext);
+    comp.enable_journal(10);
+    assert_eq!(comp.eval_async(), RunState::AwaitingInput);
+    assert_eq!(comp.program_counter(), 0);
+
+    comp.input(4);
+    assert_eq!(comp.run_until(StopOn::Output), RunState::OutputReady(2));
+    assert_eq!(comp.output_queue(), &[4, 40]);
+
+    // Stepping back puts the input back and takes both outputs away again
+    assert_eq!(comp.step_back(1), 1);
+    assert!(comp.output_queue().is_empty());
+    assert_eq!(comp.input_queue(), &[4]);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output(), Some(4));
+    assert_eq!(comp.output(), Some(40));
+}
+
+#[test]
+fn control_flow() {
+    let mut ext: Registry = Registry::new();
+    ext.define(70, "JMP", &[Param::Read], |op| {
+        Ok(Flow::Jump(op.arg(1)? as usize))
+    })
+    .unwrap()
+    .define(0, "STOP", &[], |_| Ok(Flow::Halt))
+    .unwrap();
+    // JMP #4 over OUT #1, OUT #2, then the zero word stops it
+    let mut comp = comp_with(&[170, 4, 104, 1, 104, 2, 0], ext);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output(), Some(2));
+    assert_eq!(comp.output(), None);
+    assert_eq!(comp.program_counter(), 6);
+}
+
+// Zeroes the cell its parameter points at
+fn clear() -> Registry {
+    let mut ext = Registry::new();
+    ext.define(50, "CLR", &[Param::Write], |op| {
+        op.set(1, 0)?;
+        Ok(Flow::Next)
+    })
+    .unwrap();
+    ext
+}
+
+#[test]
+fn errors() {
+    // Codes nobody defined are still unknown
+    let mut comp = comp_with(&[77, 99], clear());
+    assert_eq!(
+        comp.eval_async(),
+        RunState::Error(IntcodeError::UnknownOpcode { pc: 0, word: 77 })
+    );
+
+    let mut ext: Registry = Registry::new();
+    ext.define(43, "FAIL", &[], |op| Err(op.fail("no host")))
+        .unwrap();
+    let mut comp = comp_with(&[43], ext);
+    match comp.eval_async() {
+        RunState::Error(e) => {
+            assert_eq!(e.pc(), 0);
+            assert_eq!(e.to_string(), "no host at pc 0 (word 43)");
+        }
+        other => panic!("expected an error, got {:?}", other),
+    }
+
+    // Parameters are checked the same way built in ones are
+    let mut comp = comp_with(&[150, 0, 99], clear());
+    assert_eq!(
+        comp.eval_async(),
+        RunState::Error(IntcodeError::ImmediateWrite {
+            pc: 0,
+            word: 150,
+            operand: 1
+        })
+    );
+    let mut comp = comp_with(&[350, 0, 99], clear());
+    assert_eq!(
+        comp.eval_async(),
+        RunState::Error(IntcodeError::BadMode {
+            pc: 0,
+            word: 350,
+            operand: 1,
+            mode: 3
+        })
+    );
+    let mut comp = comp_with(&[50, -5, 99], clear());
+    match comp.eval_async() {
+        RunState::Error(IntcodeError::NegativeAddress { addr, .. }) => assert_eq!(addr, -5),
+        other => panic!("expected a negative address, got {:?}", other),
+    }
+}
+
+#[test]
+fn builtin_codes_are_taken() {
+    let mut ext: Registry = Registry::new();
+    assert_eq!(
+        ext.define(2, "MUL", &[], |_| Ok(Flow::Next)).err(),
+        Some(ExtensionError::Reserved(2))
+    );
+    assert_eq!(
+        ext.define(100, "BIG", &[], |_| Ok(Flow::Next)).err(),
+        Some(ExtensionError::Reserved(100))
+    );
+    let err = ext
+        .define(44, "MANY", &[Param::Read; 4], |_| Ok(Flow::Next))
+        .err()
+        .unwrap();
+    assert_eq!(err, ExtensionError::TooManyParams(4));
+    assert_eq!(
+        err.to_string(),
+        "extensions take at most 3 parameters, not 4"
+    );
+    // Nothing was defined
+    assert_eq!(ext.name(44), None);
+}
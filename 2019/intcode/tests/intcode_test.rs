@@ -1,5 +1,8 @@
 extern crate intcode;
 use intcode::IntcodeComp;
+use intcode::IntcodeError;
+use intcode::RunState;
+use intcode::StopOn;
 
 // Convenience structure for executing tests
 struct IntcodeTest<'a> {
@@ -12,37 +15,32 @@ struct IntcodeTest<'a> {
 // Helper to execute tests
 fn execute_test(to_run: IntcodeTest) {
     let mut comp = IntcodeComp::new(to_run.program);
+    // None of the samples need anywhere near this many, a broken jump fails the test instead of hanging it
+    comp.set_instruction_budget(Some(100_000));
 
     // Apply inputs and run the core until done
-    match &to_run.input {
-        Some(vec) => {
-            for i in vec {
-                comp.input(*i)
-            }
+    if let Some(vec) = &to_run.input {
+        for i in vec {
+            comp.input(*i)
         }
-        None => (),
     }
-    comp.run_all();
+    assert_eq!(comp.run_all(), Ok(RunState::Halted));
 
     // Check final state is what was provided (if final state is provided)
-    match &to_run.final_state {
-        Some(vec) => assert_eq!(&comp._int_mem(), &vec),
-        None => (), //pass
-    };
+    if let Some(vec) = &to_run.final_state {
+        assert_eq!(&comp._int_mem(), &vec);
+    }
 
     // Check output data stream is what was expected
     let mut test_out: Vec<i64> = vec![];
-    loop {
-        match comp.output() {
-            Some(val) => test_out.push(val),
-            None => break,
-        }
+    while let Some(val) = comp.output() {
+        test_out.push(val);
     }
 
-    match &to_run.output {
-        Some(vec) => assert_eq!(&test_out, vec),
-        None => (), // Don't check
-    };
+    // Don't check if no output was provided
+    if let Some(vec) = &to_run.output {
+        assert_eq!(&test_out, vec);
+    }
 }
 
 // Run some sample initial states against known final states to verify core instructions
@@ -158,3 +156,133 @@ fn output_middle_number() {
         output: Some(vec![1125899906842624]),
     });
 }
+
+// Malformed programs should surface as errors carrying the pc, the instruction word and the operand
+#[test]
+fn unknown_opcode() {
+    let mut comp = IntcodeComp::new(&[1101, 1, 1, 5, 42, 0]);
+    assert_eq!(
+        comp.run_all(),
+        Err(IntcodeError::UnknownOpcode { pc: 4, word: 42 })
+    );
+}
+#[test]
+fn negative_opcode() {
+    let mut comp = IntcodeComp::new(&[-1]);
+    assert_eq!(
+        comp.run_all(),
+        Err(IntcodeError::UnknownOpcode { pc: 0, word: -1 })
+    );
+}
+#[test]
+fn bad_mode() {
+    let mut comp = IntcodeComp::new(&[3001, 0, 0, 0, 99]);
+    assert_eq!(
+        comp.run_all(),
+        Err(IntcodeError::BadMode {
+            pc: 0,
+            word: 3001,
+            operand: 2,
+            mode: 3
+        })
+    );
+}
+#[test]
+fn immediate_write() {
+    let mut comp = IntcodeComp::new(&[11101, 1, 1, 0, 99]);
+    assert_eq!(
+        comp.run_all(),
+        Err(IntcodeError::ImmediateWrite {
+            pc: 0,
+            word: 11101,
+            operand: 3
+        })
+    );
+
+    // Input with an immediate destination must not consume the input
+    let mut comp = IntcodeComp::new(&[103, 0, 99]);
+    comp.input(7);
+    let err = comp.run_all().unwrap_err();
+    assert_eq!(err.operand(), Some(1));
+    assert_eq!(comp.output(), None);
+    assert_eq!(comp.input_queue(), &[7]);
+}
+#[test]
+fn negative_address() {
+    // Relative base moved below zero before a relative read
+    let mut comp = IntcodeComp::new(&[109, -10, 204, 0, 99]);
+    assert_eq!(
+        comp.run_all(),
+        Err(IntcodeError::NegativeAddress {
+            pc: 2,
+            word: 204,
+            operand: 1,
+            addr: -10
+        })
+    );
+
+    // Same for an input's destination, which leaves the input where it was
+    let mut comp = IntcodeComp::new(&[109, -10, 203, 0, 99]);
+    comp.input(7);
+    assert_eq!(
+        comp.run_all(),
+        Err(IntcodeError::NegativeAddress {
+            pc: 2,
+            word: 203,
+            operand: 1,
+            addr: -10
+        })
+    );
+    assert_eq!(comp.input_queue(), &[7]);
+
+    // Jump to a negative target
+    let mut comp = IntcodeComp::new(&[1105, 1, -3, 99]);
+    let err = comp.run_all().unwrap_err();
+    assert_eq!((err.pc(), err.word(), err.operand()), (0, 1105, Some(2)));
+}
+#[test]
+fn error_keeps_machine_usable() {
+    // Output produced before the fault is still available to the caller
+    let mut comp = IntcodeComp::new(&[104, 5, 0]);
+    let err = match comp.eval_async() {
+        RunState::Error(e) => e,
+        s => panic!("Expected an error, got {:?}", s),
+    };
+    assert_eq!(comp.output(), Some(5));
+    assert_eq!(err.to_string(), "unknown opcode 0 at pc 2 (word 0)");
+}
+
+// Run states tell the caller why execution stopped without inspecting the buffers
+#[test]
+fn halt_and_starve() {
+    // Echo one value then halt
+    let mut comp = IntcodeComp::new(&[3, 5, 4, 5, 99, 0]);
+    assert_eq!(comp.eval_async(), RunState::AwaitingInput);
+    assert_eq!(comp.run_all(), Ok(RunState::AwaitingInput));
+    comp.input(42);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output(), Some(42));
+
+    // Halted machines stay halted
+    assert_eq!(comp.eval_async(), RunState::Halted);
+}
+#[test]
+fn run_until_output() {
+    let mut comp = IntcodeComp::new(&[104, 1, 104, 2, 104, 3, 99]);
+    assert_eq!(comp.run_until(StopOn::Output), RunState::OutputReady(1));
+    assert_eq!(comp.run_until(StopOn::Output), RunState::OutputReady(2));
+    assert_eq!(comp.output(), Some(1));
+    assert_eq!(comp.run_until(StopOn::Output), RunState::OutputReady(2));
+    assert_eq!(comp.run_until(StopOn::Output), RunState::Halted);
+}
+#[test]
+fn run_until_instructions() {
+    // Loops forever incrementing address 7
+    let mut comp = IntcodeComp::new(&[1001, 7, 1, 7, 1105, 1, 0, 0]);
+    assert_eq!(comp.run_until(StopOn::Instructions(10)), RunState::Running);
+    assert_eq!(comp._int_mem()[7], 5);
+    assert_eq!(comp.run_until(StopOn::Instructions(1)), RunState::Running);
+    assert_eq!(comp._int_mem()[7], 6);
+    assert_eq!(comp.run_until(StopOn::Instructions(0)), RunState::Running);
+    assert_eq!(comp._int_mem()[7], 6);
+}
@@ -0,0 +1,101 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::{InputSource, IntcodeComp, OutputSink, RunState, StopOn};
+use std::sync::mpsc;
+use std::thread;
+
+// Doubles every input until it reads a 0
+fn doubler() -> Vec<i64> {
+    assemble(
+        "
+        loop:
+            IN [x]
+            JZ [x], #done
+            MUL [x], #2, [x]
+            OUT [x]
+            JZ #0, #loop
+        done:
+            HLT
+        x: .data 0
+        ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn closures() {
+    let mut inputs = vec![3, 2, 1].into_iter();
+    let mut seen = Vec::new();
+    let mut comp = IntcodeComp::new(&doubler());
+    let state = comp.run_with(&mut || inputs.next(), &mut |v| seen.push(v));
+    // Ran out of input before the terminating 0
+    assert_eq!(state, RunState::AwaitingInput);
+    assert_eq!(seen, vec![6, 4, 2]);
+    assert_eq!(comp.output(), None);
+}
+#[test]
+fn queued_input_first() {
+    let mut comp = IntcodeComp::new(&doubler());
+    comp.input(5);
+    let mut out: Vec<i64> = Vec::new();
+    let mut rest = vec![7, 0].into_iter();
+    assert_eq!(
+        comp.run_with(&mut || rest.next(), &mut out),
+        RunState::Halted
+    );
+    assert_eq!(out, vec![10, 14]);
+}
+#[test]
+fn channels() {
+    let (in_tx, in_rx) = mpsc::channel();
+    let (out_tx, out_rx) = mpsc::channel();
+    let prog = doubler();
+    let handle = thread::spawn(move || {
+        let (mut rx, mut tx) = (in_rx, out_tx);
+        IntcodeComp::new(&prog).run_with(&mut rx, &mut tx)
+    });
+    for v in 1..=3 {
+        in_tx.send(v).unwrap();
+        assert_eq!(out_rx.recv().unwrap(), v * 2);
+    }
+    // Hanging up reads as starvation
+    drop(in_tx);
+    assert_eq!(handle.join().unwrap(), RunState::AwaitingInput);
+}
+
+// A device that feeds back its own output, plus a countdown so the program terminates
+struct Echo {
+    last: i64,
+    left: usize,
+}
+impl InputSource for Echo {
+    fn next_input(&mut self) -> Option<i64> {
+        if self.left == 0 {
+            return Some(0);
+        }
+        self.left -= 1;
+        Some(self.last)
+    }
+}
+impl OutputSink for Echo {
+    fn push_output(&mut self, val: i64) {
+        self.last = val;
+    }
+}
+#[test]
+fn attach_device() {
+    let mut echo = Echo { last: 1, left: 10 };
+    let mut comp = IntcodeComp::new(&doubler());
+    assert_eq!(comp.attach(&mut echo), RunState::Halted);
+    assert_eq!(echo.last, 1024);
+}
+#[test]
+fn device_stops() {
+    let mut echo = Echo { last: 1, left: 10 };
+    let mut comp = IntcodeComp::new(&doubler());
+    assert_eq!(
+        comp.run_device_until(&mut echo, StopOn::Output),
+        RunState::OutputReady(1)
+    );
+    assert_eq!(echo.last, 2);
+}
@@ -0,0 +1,219 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::{prog_from_file, IntcodeComp, RunState, StopOn};
+use std::collections::VecDeque;
+
+const COUNTDOWN: &str = "
+        IN [n]
+    loop:
+        OUT [n]
+        ADD [n], #-1, [n]
+        JNZ [n], #loop
+        HLT
+    n: .data 0
+";
+
+// Same contents and same layout, undoing a write gives back whatever it allocated
+fn same_memory(a: &IntcodeComp, b: &IntcodeComp) -> bool {
+    a._int_mem() == b._int_mem() && a.memory_used() == b.memory_used()
+}
+
+#[test]
+fn step_back() {
+    let prog = assemble(COUNTDOWN).unwrap();
+    let mut comp = IntcodeComp::new(&prog);
+    comp.enable_journal(100);
+    comp.input(3);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output_queue(), &[3, 2, 1]);
+    // The halt isn't recorded, there's nothing to undo
+    assert_eq!(comp.journal_len(), 10);
+
+    // Back over the last JNZ and ADD
+    assert_eq!(comp.step_back(2), 2);
+    assert_eq!(comp.program_counter(), 4);
+    assert_eq!(comp.read_mem(12), 1);
+    // and the OUT
+    assert_eq!(comp.step_back(1), 1);
+    assert_eq!(comp.output_queue(), &[3, 2]);
+
+    // All the way back, the input is queued again
+    assert_eq!(comp.step_back(100), 7);
+    assert_eq!(comp.program_counter(), 0);
+    assert_eq!(comp.input_queue(), &[3]);
+    assert!(comp.output_queue().is_empty());
+    assert!(same_memory(&comp, &IntcodeComp::new(&prog)));
+    assert_eq!(comp.step_back(1), 0);
+
+    // Forward again gives the same results
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output_queue(), &[3, 2, 1]);
+}
+
+#[test]
+fn back_to_write() {
+    let prog = assemble(COUNTDOWN).unwrap();
+    let mut comp = IntcodeComp::new(&prog);
+    comp.enable_journal(100);
+    comp.input(3);
+    comp.eval_async();
+
+    // The last write to n was the ADD that took it to 0
+    assert_eq!(comp.back_to_write(12), Some(4));
+    assert_eq!(comp.read_mem(12), 1);
+    assert_eq!(comp.step(), RunState::Running);
+    assert_eq!(comp.read_mem(12), 0);
+    comp.step_back(1);
+
+    // Going again finds the one before, the IN finds the very start
+    assert_eq!(comp.back_to_write(12), Some(4));
+    assert_eq!(comp.read_mem(12), 2);
+    assert_eq!(comp.back_to_write(12), Some(4));
+    assert_eq!(comp.back_to_write(12), Some(0));
+    assert_eq!(comp.read_mem(12), 0);
+
+    // Nothing earlier, and nothing ever wrote to 0
+    assert_eq!(comp.back_to_write(12), None);
+    assert_eq!(comp.back_to_write(0), None);
+    assert_eq!(comp.program_counter(), 0);
+}
+
+#[test]
+fn capacity() {
+    let mut comp = IntcodeComp::new(&assemble(COUNTDOWN).unwrap());
+    comp.enable_journal(4);
+    comp.input(3);
+    comp.eval_async();
+    assert_eq!(comp.journal_len(), 4);
+    assert_eq!(comp.step_back(10), 4);
+    assert_eq!(comp.program_counter(), 8);
+
+    // Off by default and off again once disabled
+    comp.disable_journal();
+    assert!(!comp.journaling());
+    assert_eq!(comp.step_back(1), 0);
+    assert_eq!(IntcodeComp::new(&[99]).journal_len(), 0);
+}
+
+#[test]
+fn blocked_and_faulting_not_recorded() {
+    let mut comp = IntcodeComp::new(&[3, 0, 99]);
+    comp.enable_journal(10);
+    assert_eq!(comp.eval_async(), RunState::AwaitingInput);
+    assert_eq!(comp.journal_len(), 0);
+
+    let mut comp = IntcodeComp::new(&[1101, 1, 1, 5, 42, 0]);
+    comp.enable_journal(10);
+    assert!(matches!(comp.eval_async(), RunState::Error(_)));
+    assert_eq!(comp.journal_len(), 1);
+    assert_eq!(comp.step_back(1), 1);
+    assert_eq!(comp.read_mem(5), 0);
+}
+
+#[test]
+fn device_outputs_stay_put() {
+    // The first 5 goes on the output queue and the second to a device, stepping back over the second
+    //  must leave the first alone
+    let mut comp = IntcodeComp::new(&[104, 5, 104, 5, 99]);
+    comp.enable_journal(10);
+    comp.step();
+    let mut screen = Vec::new();
+    comp.run_with(&mut VecDeque::new(), &mut screen);
+    assert_eq!(screen, vec![5]);
+    assert_eq!(comp.step_back(1), 1);
+    assert_eq!(comp.output_queue(), &[5]);
+    assert_eq!(comp.step_back(1), 1);
+    assert!(comp.output_queue().is_empty());
+}
+
+#[test]
+fn undo_releases_memory() {
+    // A page of its own, then dense growth, then more dense growth that takes the page in
+    let prog = assemble("ADD #1, #0, [2600]\nADD #2, #0, [2000]\nADD #3, #0, [4000]\nHLT").unwrap();
+    let start = IntcodeComp::new(&prog);
+    let mut comp = start.clone();
+    comp.enable_journal(10);
+    let mut used = vec![comp.memory_used()];
+    for _ in 0..3 {
+        comp.step();
+        used.push(comp.memory_used());
+    }
+    assert!(used.windows(2).all(|w| w[0] < w[1]));
+
+    // The page comes back out of the dense region with its cell intact
+    comp.step_back(1);
+    assert_eq!(comp.memory_used(), used[2]);
+    assert_eq!(comp._int_mem().len(), 2001);
+    assert_eq!((comp.read_mem(2600), comp.read_mem(4000)), (1, 0));
+    comp.step_back(2);
+    assert_eq!(comp.memory_used(), used[0]);
+    assert!(same_memory(&comp, &start));
+    assert_eq!(comp.read_mem(2600), 0);
+
+    // Forward again allocates the same way
+    comp.eval_async();
+    assert_eq!(comp.memory_used(), used[3]);
+    assert_eq!(comp.read_mem(2600), 1);
+}
+
+#[test]
+fn self_modifying() {
+    // Patches the OUT operand on the first pass, stepping back has to undo the patch and the
+    //  decoded copy of it
+    let prog = assemble(
+        "
+            ADD [count], #1, [count]
+            ADD [patch+1], #1, [patch+1]
+        patch:
+            OUT #0
+            JNZ [count], #0
+            HLT
+        count: .data -300
+        ",
+    )
+    .unwrap();
+    let mut comp = IntcodeComp::new(&prog);
+    comp.enable_journal(10_000);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    let out: Vec<i64> = comp.output_queue().iter().copied().collect();
+    assert_eq!(out.len(), 300);
+    // Stop between a patch and the OUT it patched
+    assert_eq!(comp.step_back(4 * 150 + 2), 4 * 150 + 2);
+    assert_eq!(comp.program_counter(), 8);
+    comp.eval_async();
+    assert_eq!(comp.output_queue().iter().copied().collect::<Vec<_>>(), out);
+}
+
+#[test]
+fn painter_round_trip() {
+    // Thousands of steps into a real program and back again, with the panel colours coming in as input
+    let prog = prog_from_file("../day_11/painter.txt");
+    let start = IntcodeComp::new(&prog);
+    let mut comp = start.clone();
+    comp.enable_journal(1 << 20);
+    let colours: Vec<i64> = (0..5000).map(|i| i % 3 % 2).collect();
+    for &c in &colours {
+        comp.input(c);
+    }
+    assert_eq!(
+        comp.run_until(StopOn::Instructions(20_000)),
+        RunState::Running
+    );
+    let later = comp.snapshot();
+    let steps = comp.journal_len();
+    assert_eq!(steps, 20_000);
+
+    assert_eq!(comp.step_back(steps), steps);
+    assert_eq!(comp.program_counter(), 0);
+    assert_eq!(comp.rel_base(), 0);
+    assert_eq!(
+        comp.input_queue().iter().copied().collect::<Vec<_>>(),
+        colours
+    );
+    assert!(comp.output_queue().is_empty());
+    assert!(same_memory(&comp, &start));
+
+    // And forward again to exactly where it was
+    comp.run_until(StopOn::Instructions(20_000));
+    assert_eq!(comp.snapshot(), later);
+}
@@ -0,0 +1,120 @@
+extern crate intcode;
+use intcode::load::{self, ImageError, LoadError, TextError};
+use intcode::prog_from_file;
+
+// Position and kind of a text error, panics on anything else
+fn text_err(src: &str) -> (usize, usize, TextError) {
+    match load::parse(src) {
+        Err(LoadError::Text { line, col, kind }) => (line, col, kind),
+        other => panic!("expected a text error, got {:?}", other),
+    }
+}
+
+#[test]
+fn layout() {
+    assert_eq!(load::parse("1,0,0,3,99\n").unwrap(), vec![1, 0, 0, 3, 99]);
+    let src = "; patched
+        1, 12, 2, 3,
+        1,1,2,3,   # same thing
+        -99 ,
+    ";
+    assert_eq!(
+        load::parse(src).unwrap(),
+        vec![1, 12, 2, 3, 1, 1, 2, 3, -99]
+    );
+    assert_eq!(load::parse("1,2\r\n,3").unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn positions() {
+    assert_eq!(
+        text_err("1,0,0,\n3, 9x9,99"),
+        (2, 4, TextError::BadNumber("9x9".to_string()))
+    );
+    assert_eq!(text_err("1,,2"), (1, 3, TextError::MissingValue));
+    assert_eq!(text_err("\n  ,1"), (2, 3, TextError::MissingValue));
+    assert_eq!(text_err("1,2,\n3 4"), (2, 3, TextError::MissingComma));
+    // A line break doesn't stand in for a comma
+    assert_eq!(text_err("1,2\n3"), (2, 1, TextError::MissingComma));
+    // Columns count characters, not bytes
+    assert_eq!(
+        text_err("1, ü"),
+        (1, 4, TextError::BadNumber("ü".to_string()))
+    );
+    assert_eq!(
+        text_err("1,99999999999999999999"),
+        (
+            1,
+            3,
+            TextError::BadNumber("99999999999999999999".to_string())
+        )
+    );
+    assert_eq!(text_err(" ; nothing\n\n"), (1, 1, TextError::Empty));
+
+    match load::from_bytes(b"1,2,\n3,\xff") {
+        Err(LoadError::Text { line, col, kind }) => {
+            assert_eq!((line, col, kind), (2, 3, TextError::NotUtf8))
+        }
+        other => panic!("expected a text error, got {:?}", other),
+    }
+    let e = load::parse("1,2,\n 3 x").unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "line 2, col 4: expected a comma before the value"
+    );
+}
+
+#[test]
+fn image() {
+    let prog = vec![1, 0, -1, 63, -64, 64, i64::MAX, i64::MIN, 99];
+    let bytes = load::to_image(&prog);
+    assert_eq!(load::from_image(&bytes).unwrap(), prog);
+    // The readers spot images on their own
+    assert_eq!(load::from_bytes(&bytes).unwrap(), prog);
+    assert_eq!(load::from_reader(&bytes[..]).unwrap(), prog);
+
+    let puzzle = prog_from_file("../day_09/BOOST.txt");
+    let bytes = load::to_image(&puzzle);
+    assert!(bytes.len() < std::fs::read("../day_09/BOOST.txt").unwrap().len() / 2);
+    assert_eq!(load::from_bytes(&bytes).unwrap(), puzzle);
+
+    let path = std::env::temp_dir().join("intcode_load_test.img");
+    let path = path.to_str().unwrap();
+    load::save_image(path, &puzzle).unwrap();
+    assert_eq!(load::from_file(path).unwrap(), puzzle);
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn bad_images() {
+    let image_err = |bytes: &[u8]| match load::from_image(bytes) {
+        Err(LoadError::Image { offset, kind }) => (offset, kind),
+        other => panic!("expected an image error, got {:?}", other),
+    };
+    let bytes = load::to_image(&[1, 2, 300]);
+    assert_eq!(image_err(b"1,2,3"), (0, ImageError::BadMagic));
+    assert_eq!(image_err(&bytes[..4]), (4, ImageError::Truncated));
+    assert_eq!(
+        image_err(&bytes[..bytes.len() - 1]),
+        (9, ImageError::Truncated)
+    );
+    let mut longer = bytes.clone();
+    longer.push(0);
+    assert_eq!(image_err(&longer), (10, ImageError::TrailingBytes));
+    let mut newer = bytes;
+    newer[4] = 2;
+    assert_eq!(image_err(&newer), (4, ImageError::Version(2)));
+
+    let mut huge = b"\0ICI\x01\x01".to_vec();
+    huge.extend_from_slice(&[0xff; 9]);
+    huge.push(0x02);
+    assert_eq!(image_err(&huge), (6, ImageError::Overlong));
+}
+
+#[test]
+fn io_errors() {
+    match load::from_file("no/such/program.txt") {
+        Err(LoadError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+        other => panic!("expected an io error, got {:?}", other),
+    }
+}
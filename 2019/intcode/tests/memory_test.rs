@@ -0,0 +1,131 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::{IntcodeComp, IntcodeError, RunState, DEFAULT_MEMORY_LIMIT, PAGE_SIZE};
+
+#[test]
+fn near_writes_grow_dense() {
+    let mut comp = IntcodeComp::new(&assemble("ADD #1, #2, [100]\nHLT").unwrap());
+    assert_eq!(comp.run_all(), Ok(RunState::Halted));
+    assert_eq!(comp._int_mem().len(), 101);
+    assert_eq!(comp._int_mem()[100], 3);
+    assert_eq!(comp.memory_used(), 101);
+}
+
+#[test]
+fn far_writes_use_pages() {
+    let far: usize = 1_000_000_000_000;
+    let prog = assemble(&format!(
+        "ADD #7, #0, [{}]\nADD [{}], #1, [{}]\nOUT [{}]\nHLT",
+        far,
+        far,
+        far + 1,
+        far + 1
+    ))
+    .unwrap();
+    let mut comp = IntcodeComp::new(&prog);
+    assert_eq!(comp.run_all(), Ok(RunState::Halted));
+    assert_eq!(comp.output(), Some(8));
+    assert_eq!(comp.read_mem(far), 7);
+    assert_eq!(comp.read_mem(far + 2), 0);
+    assert_eq!(comp._int_mem().len(), prog.len());
+    assert_eq!(comp.memory_used(), prog.len() + PAGE_SIZE);
+}
+
+#[test]
+fn reads_and_zero_writes_dont_allocate() {
+    let prog = assemble("ADD [5000000], #0, [9000000]\nHLT").unwrap();
+    let mut comp = IntcodeComp::new(&prog);
+    assert_eq!(comp.run_all(), Ok(RunState::Halted));
+    assert_eq!(comp.memory_used(), prog.len());
+}
+
+#[test]
+fn limit() {
+    assert_eq!(IntcodeComp::new(&[99]).memory_limit(), DEFAULT_MEMORY_LIMIT);
+
+    // Enough for the program and one page, the second far write fails cleanly
+    let prog = assemble("ADD #1, #0, [100000]\nADD #1, #0, [200000]\nHLT").unwrap();
+    let mut comp = IntcodeComp::new(&prog);
+    comp.set_memory_limit(prog.len() + PAGE_SIZE);
+    assert_eq!(
+        comp.run_all(),
+        Err(IntcodeError::OutOfMemory {
+            pc: 4,
+            word: 1101,
+            operand: 3,
+            addr: 200000,
+            limit: prog.len() + PAGE_SIZE,
+        })
+    );
+    assert_eq!(comp.program_counter(), 4);
+    assert_eq!(comp.read_mem(100000), 1);
+    assert_eq!(comp.read_mem(200000), 0);
+
+    // Dense growth counts too
+    let mut comp = IntcodeComp::new(&assemble("ADD #1, #0, [1000]\nHLT").unwrap());
+    comp.set_memory_limit(500);
+    assert!(matches!(
+        comp.run_all(),
+        Err(IntcodeError::OutOfMemory { addr: 1000, .. })
+    ));
+
+    // An input that can't be stored isn't lost, even one the device handed over
+    let mut comp = IntcodeComp::new(&assemble("IN [1000]\nHLT").unwrap());
+    comp.set_memory_limit(500);
+    let mut keys = std::collections::VecDeque::from(vec![7]);
+    assert!(matches!(
+        comp.attach(&mut intcode::io::Split(&mut keys, &mut Vec::new())),
+        RunState::Error(IntcodeError::OutOfMemory { addr: 1000, .. })
+    ));
+    assert_eq!(comp.input_queue(), &[7]);
+    assert!(keys.is_empty());
+}
+
+#[test]
+fn dense_swallows_pages() {
+    // Write a far page first, then walk the dense region up through it one slack at a time
+    let prog = assemble(
+        "
+            ADD #42, #0, [9000]
+            ADD #1, #0, [2000]
+            ADD #1, #0, [4000]
+            ADD #1, #0, [6000]
+            ADD #1, #0, [8000]
+            ADD #1, #0, [9500]
+            HLT
+        ",
+    )
+    .unwrap();
+    let mut comp = IntcodeComp::new(&prog);
+    assert_eq!(comp.run_all(), Ok(RunState::Halted));
+    assert_eq!(comp._int_mem().len(), 9501);
+    assert_eq!(comp._int_mem()[9000], 42);
+    assert_eq!(comp.memory_used(), 9501);
+}
+
+#[test]
+fn snapshot_keeps_pages() {
+    let mut comp = IntcodeComp::new(&assemble("ADD #5, #0, [123456789]\nHLT").unwrap());
+    comp.run_all().unwrap();
+    let snap = comp.snapshot();
+    assert_eq!(snap.pages.len(), 1);
+    let copy = IntcodeComp::from_snapshot(&snap);
+    assert_eq!(copy.read_mem(123456789), 5);
+    assert_eq!(copy.memory_used(), comp.memory_used());
+}
+
+#[test]
+fn display() {
+    let e = IntcodeError::OutOfMemory {
+        pc: 4,
+        word: 1101,
+        operand: 3,
+        addr: 200000,
+        limit: 1000,
+    };
+    assert_eq!(
+        e.to_string(),
+        "out of memory writing address 200000 from operand 3 at pc 4 (word 1101), limit is 1000 words"
+    );
+    assert_eq!(e.operand(), Some(3));
+}
@@ -0,0 +1,135 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::network::{MonitorAction, MonitorEvent, NetState, Network, Packet, Topology};
+use intcode::IntcodeError;
+
+// Takes its address, then forwards every packet to the next address up with y incremented
+//  -1 means there is nothing to read
+fn relay() -> Vec<i64> {
+    assemble(
+        "
+            IN [addr]
+            ADD [addr], #1, [dest]
+        loop:
+            IN [x]
+            EQ [x], #-1, [t]
+            JNZ [t], #loop
+            IN [y]
+            ADD [y], #1, [y]
+            OUT [dest]
+            OUT [x]
+            OUT [y]
+            JZ #0, #loop
+        addr: .data 0
+        dest: .data 0
+        x:    .data 0
+        y:    .data 0
+        t:    .data 0
+        ",
+    )
+    .unwrap()
+}
+
+// Doubles one input and halts
+fn doubler() -> Vec<i64> {
+    assemble("IN [x]\nMUL [x], #2, [x]\nOUT [x]\nHLT\nx: .data 0").unwrap()
+}
+
+fn relay_net<'a>() -> Network<'a> {
+    let mut net = Network::new(&relay(), 3, Topology::Packets);
+    net.set_idle_input(Some(-1));
+    for i in 0..3 {
+        net.send(i, i as i64);
+    }
+    net
+}
+
+#[test]
+fn packets_reach_monitor() {
+    let mut seen = Vec::new();
+    {
+        let mut net = relay_net();
+        net.send(0, 7);
+        net.send(0, 0);
+        net.set_monitor(|e| {
+            seen.push(e);
+            MonitorAction::Stop
+        });
+        assert_eq!(net.run(), NetState::Stopped);
+    }
+    assert_eq!(
+        seen,
+        vec![MonitorEvent::Packet(Packet {
+            dest: 3,
+            x: 7,
+            y: 3
+        })]
+    );
+}
+#[test]
+fn idle_monitor_restarts() {
+    // Like a NAT, remember the last packet and send it back to 0 whenever the network goes quiet
+    let mut last = None;
+    let mut ys = Vec::new();
+    {
+        let mut net = relay_net();
+        net.set_monitor(|e| match e {
+            MonitorEvent::Packet(p) => {
+                ys.push(p.y);
+                last = Some(p);
+                MonitorAction::Continue
+            }
+            MonitorEvent::Idle if ys.len() >= 3 => MonitorAction::Stop,
+            MonitorEvent::Idle => MonitorAction::Send(Packet {
+                dest: 0,
+                ..last.unwrap_or(Packet {
+                    dest: 0,
+                    x: 1,
+                    y: 0,
+                })
+            }),
+        });
+        assert_eq!(net.run(), NetState::Stopped);
+    }
+    assert_eq!(ys, vec![3, 6, 9]);
+}
+#[test]
+fn idle_and_deadlock() {
+    let mut net = relay_net();
+    assert_eq!(net.run(), NetState::Idle);
+
+    // Two machines each waiting on the other
+    let mut net = Network::new(&doubler(), 2, Topology::ring(2));
+    assert_eq!(net.run(), NetState::Deadlock);
+    net.send(1, 4);
+    assert_eq!(net.run(), NetState::Halted);
+    assert_eq!(net.last_output(0), Some(16));
+}
+#[test]
+fn broadcast() {
+    let mut net = Network::new(&doubler(), 3, Topology::broadcast(3, 0));
+    net.send(0, 5);
+    assert_eq!(net.run(), NetState::Halted);
+    assert_eq!(net.last_output(0), Some(10));
+    for i in 1..3 {
+        assert_eq!(net.node_mut(i).output(), Some(20));
+    }
+}
+#[test]
+fn pipeline() {
+    let mut net = Network::new(&doubler(), 4, Topology::pipeline(4));
+    net.send(0, 1);
+    assert_eq!(net.run(), NetState::Halted);
+    assert_eq!(net.node_mut(3).output(), Some(16));
+    assert_eq!(net.len(), 4);
+}
+#[test]
+fn node_fault() {
+    let mut net = Network::new(&doubler(), 2, Topology::pipeline(2));
+    *net.node_mut(1) = intcode::IntcodeComp::new(&[3, 0, 42]);
+    net.send(0, 1);
+    assert_eq!(
+        net.run(),
+        NetState::Error(1, IntcodeError::UnknownOpcode { pc: 2, word: 42 })
+    );
+}
@@ -0,0 +1,114 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::{BranchStats, HotLoop, IntcodeComp, Opcodes, RunState};
+
+// Counts down from the input, outputting each value
+fn countdown() -> Vec<i64> {
+    assemble(
+        "
+            IN [n]
+        loop:
+            OUT [n]
+            ADD [n], #-1, [n]
+            JNZ [n], #loop
+            HLT
+        n: .data 0
+        ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn off_by_default() {
+    let mut comp = IntcodeComp::new(&countdown());
+    comp.input(3);
+    comp.eval_async();
+    assert!(comp.profile().is_none());
+    assert!(comp.disable_profiling().is_none());
+}
+
+#[test]
+fn counts() {
+    let mut comp = IntcodeComp::new(&countdown());
+    comp.enable_profiling();
+    comp.input(3);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    let p = comp.disable_profiling().unwrap();
+
+    assert_eq!(p.instructions, 11);
+    assert_eq!(p.opcodes[&Opcodes::Input], 1);
+    assert_eq!(p.opcodes[&Opcodes::Output], 3);
+    assert_eq!(p.opcodes[&Opcodes::Jnz], 3);
+    assert_eq!(p.opcodes[&Opcodes::Halt], 1);
+    assert_eq!(p.addresses[&4], 3);
+    assert_eq!(p.hot_addresses()[0], (2, 3));
+    assert_eq!(
+        p.branches[&8],
+        BranchStats {
+            taken: 2,
+            not_taken: 1
+        }
+    );
+    assert_eq!(
+        p.hot_loops(),
+        vec![HotLoop {
+            start: 2,
+            end: 10,
+            iterations: 2,
+            instructions: 9
+        }]
+    );
+    assert_eq!(p.mem_high_water, 13);
+    assert_eq!(p.mem_grows, 0);
+    assert!(comp.profile().is_none());
+}
+
+#[test]
+fn starved_input_counts_once() {
+    // Asked for input three times before there is any, then halted twice
+    let mut comp = IntcodeComp::new(&countdown());
+    comp.enable_profiling();
+    for _ in 0..3 {
+        assert_eq!(comp.eval_async(), RunState::AwaitingInput);
+    }
+    comp.input(1);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    let p = comp.profile().unwrap();
+    assert_eq!(p.instructions, 5);
+    assert_eq!(p.opcodes[&Opcodes::Input], 1);
+    assert_eq!(p.opcodes[&Opcodes::Halt], 1);
+    assert_eq!(p.addresses[&0], 1);
+
+    // A fault doesn't count either
+    let mut comp = IntcodeComp::new(&[109, -10, 203, 0, 99]);
+    comp.enable_profiling();
+    comp.input(7);
+    assert!(comp.run_all().is_err());
+    assert_eq!(comp.profile().unwrap().instructions, 1);
+}
+
+#[test]
+fn memory_growth() {
+    let mut comp = IntcodeComp::new(&assemble("ADD [50], #2, [100]\nHLT").unwrap());
+    comp.enable_profiling();
+    comp.eval_async();
+    let p = comp.profile().unwrap();
+    // Reading [50] past the end doesn't allocate, only the write does
+    assert_eq!(p.mem_high_water, 101);
+    assert_eq!(p.mem_grows, 1);
+}
+
+#[test]
+fn report() {
+    let mut comp = IntcodeComp::new(&countdown());
+    comp.enable_profiling();
+    comp.input(3);
+    comp.eval_async();
+    let report = comp.profile().unwrap().to_string();
+    assert!(report.starts_with("11 instructions executed\n"));
+    assert!(
+        report.contains("     2..10                9 instructions  81.82%          2 iterations")
+    );
+    assert!(report.contains("       8 taken          2 not taken          1"));
+}
@@ -0,0 +1,81 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::{IntcodeComp, RunState};
+
+// Adds up inputs forever, outputting the running total after each one
+fn accumulator() -> Vec<i64> {
+    assemble(
+        "
+        loop:
+            IN [x]
+            ADD [x], [total], [total]
+            OUT [total]
+            JZ #0, #loop
+        x:     .data 0
+        total: .data 0
+        ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn clone_branches() {
+    let mut comp = IntcodeComp::new(&accumulator());
+    comp.input(10);
+    assert_eq!(comp.eval_async(), RunState::AwaitingInput);
+
+    // Branch mid run, the two machines evolve independently
+    let mut branch = comp.clone();
+    comp.input(1);
+    branch.input(100);
+    comp.eval_async();
+    branch.eval_async();
+    assert_eq!(comp.output_queue().back(), Some(&11));
+    assert_eq!(branch.output_queue().back(), Some(&110));
+}
+#[test]
+fn snapshot_restore() {
+    let mut comp = IntcodeComp::new(&accumulator());
+    comp.input(5);
+    comp.eval_async();
+    let snap = comp.snapshot();
+
+    comp.input(7);
+    comp.eval_async();
+    assert_eq!(comp.output(), Some(5));
+    assert_eq!(comp.output(), Some(12));
+
+    // Everything including the I/O queues comes back
+    comp.restore(&snap);
+    assert_eq!(comp.snapshot(), snap);
+    assert_eq!(comp.output(), Some(5));
+    assert_eq!(comp.output(), None);
+    comp.input(1);
+    comp.eval_async();
+    assert_eq!(comp.output(), Some(6));
+
+    let mut other = IntcodeComp::from_snapshot(&snap);
+    other.input(2);
+    other.eval_async();
+    assert_eq!(other.output_queue().back(), Some(&7));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn save_and_load() {
+    use intcode::Snapshot;
+
+    let mut comp = IntcodeComp::new(&accumulator());
+    comp.input(3);
+    comp.eval_async();
+    let path = std::env::temp_dir().join("intcode_snapshot_test.json");
+    let path = path.to_str().unwrap();
+    comp.snapshot().save(path).unwrap();
+
+    let mut resumed = IntcodeComp::from_snapshot(&Snapshot::load(path).unwrap());
+    std::fs::remove_file(path).unwrap();
+    resumed.input(4);
+    resumed.eval_async();
+    assert_eq!(resumed.output(), Some(3));
+    assert_eq!(resumed.output(), Some(7));
+}
@@ -0,0 +1,193 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::prog_from_file;
+use intcode::symbolic::{solve_cells, Expr, Symbolic, SymbolicError, Unknown, Value};
+use intcode::IntcodeComp;
+use std::collections::BTreeMap;
+
+// Day 2 with noun and verb filled in, memory[0] once it halts
+fn day_02(prog: &[i64], noun: i64, verb: i64) -> i64 {
+    let mut mem = prog.to_vec();
+    mem[1] = noun;
+    mem[2] = verb;
+    let mut comp = IntcodeComp::new(&mem);
+    comp.run_all().unwrap();
+    comp.read_mem(0)
+}
+
+#[test]
+fn day_02_formula() {
+    let prog = prog_from_file("../day_02/input.txt");
+    let mut sym = Symbolic::new(&prog);
+    let noun = sym.unknown_cell(1, 0..=99);
+    let verb = sym.unknown_cell(2, 0..=99);
+    sym.run().unwrap();
+    let expr = match sym.read_mem(0) {
+        Value::Linear(e) => e,
+        v => panic!("expected a linear result, got {}", v),
+    };
+    assert_eq!(
+        expr.terms.keys().copied().collect::<Vec<_>>(),
+        vec![noun, verb]
+    );
+
+    // Agrees with really running it
+    for &(n, v) in &[(12, 2), (0, 0), (99, 99), (57, 41)] {
+        let values: BTreeMap<Unknown, i64> = [(noun, n), (verb, v)].iter().copied().collect();
+        assert_eq!(expr.eval(&values), Some(day_02(&prog, n, v)));
+    }
+}
+
+#[test]
+fn day_02_solve() {
+    let prog = prog_from_file("../day_02/input.txt");
+    let sol = solve_cells(&prog, &[(1, 0..=99), (2, 0..=99)], 0, 19_690_720)
+        .unwrap()
+        .unwrap();
+    assert_eq!(day_02(&prog, sol[0], sol[1]), 19_690_720);
+
+    // Out of reach
+    assert_eq!(
+        solve_cells(&prog, &[(1, 0..=99), (2, 0..=99)], 0, 1),
+        Ok(None)
+    );
+}
+
+#[test]
+fn inputs() {
+    // out = 3 * (in0 + 4) - in1
+    let prog = assemble(
+        "
+        IN [a]
+        IN [b]
+        ADD [a], #4, [a]
+        MUL #3, [a], [a]
+        MUL [b], #-1, [b]
+        ADD [a], [b], [a]
+        OUT [a]
+        HLT
+        a: .data 0
+        b: .data 0
+        ",
+    )
+    .unwrap();
+    let mut sym = Symbolic::new(&prog);
+    let x = sym.unknown_input(-100..=100);
+    let y = sym.unknown_input(0..=5);
+    sym.run().unwrap();
+    assert_eq!(sym.outputs()[0].to_string(), "3*in0 - in1 + 12");
+
+    let sol = sym.solve_output(0, 40).unwrap().unwrap();
+    assert_eq!(3 * (sol[&x] + 4) - sol[&y], 40);
+    assert!((0..=5).contains(&sol[&y]));
+    assert_eq!(
+        sym.solve_output(1, 40),
+        Err(SymbolicError::MissingOutput(1))
+    );
+
+    // Concrete inputs mixed in are just numbers
+    let mut sym = Symbolic::new(&prog);
+    sym.input(6);
+    let y = sym.unknown_input(0..=50);
+    assert_eq!(y, Unknown::Input(1));
+    let sol = sym.solve_output(0, 0).unwrap().unwrap();
+    assert_eq!(sol[&y], 30);
+}
+
+#[test]
+fn non_linear() {
+    // in0 * in1 can be computed but not solved for
+    let prog = assemble(
+        "IN [a]\nIN [b]\nMUL [a], [b], [a]\nOUT [a]\nOUT [b]\nHLT\na: .data 0\nb: .data 0",
+    )
+    .unwrap();
+    let mut sym = Symbolic::new(&prog);
+    sym.unknown_input(0..=9);
+    sym.unknown_input(0..=9);
+    assert_eq!(
+        sym.solve_output(0, 12),
+        Err(SymbolicError::NonLinear { pc: 4 })
+    );
+    // Other values are still fine
+    assert_eq!(
+        sym.outputs()[1],
+        Value::Linear(Expr::unknown(Unknown::Input(1)))
+    );
+}
+
+#[test]
+fn overflow() {
+    let big = 1 << 40;
+    // Two large immediates multiplied faults just like a real machine
+    let prog = [1102, big, big, 0, 99];
+    let fault = IntcodeComp::new(&prog).run_all().unwrap_err();
+    assert_eq!(Symbolic::new(&prog).run(), Err(SymbolicError::Fault(fault)));
+
+    // With an unknown involved it only might overflow, so the result is non-linear rather than an error
+    let prog = assemble(
+        "
+        IN [a]
+        MUL [a], #1099511627776, [a]
+        MUL [a], #1099511627776, [a]
+        OUT [a]
+        HLT
+        a: .data 0
+        ",
+    )
+    .unwrap();
+    let mut sym = Symbolic::new(&prog);
+    sym.unknown_input(0..=1);
+    assert_eq!(
+        sym.solve_output(0, 0),
+        Err(SymbolicError::NonLinear { pc: 6 })
+    );
+
+    // The relative base and addresses leaving i64 fault too
+    for prog in [
+        vec![109, i64::MAX, 109, 1, 99],
+        vec![109, 1, 204, i64::MAX, 99],
+    ] {
+        let fault = IntcodeComp::new(&prog).run_all().unwrap_err();
+        assert_eq!(Symbolic::new(&prog).run(), Err(SymbolicError::Fault(fault)));
+    }
+
+    // Solving and printing near the limits doesn't overflow either, out = -in0 can't reach MIN
+    let mut sym = Symbolic::new(&[3, 9, 1002, 9, -1, 9, 4, 9, 99, 0]);
+    sym.unknown_input(-10..=10);
+    assert_eq!(sym.solve_output(0, i64::MIN), Ok(None));
+    assert_eq!(
+        sym.solve_output(0, 7).unwrap().unwrap()[&Unknown::Input(0)],
+        -7
+    );
+    let e = Expr {
+        constant: i64::MIN,
+        terms: [(Unknown::Input(0), i64::MIN)].iter().copied().collect(),
+    };
+    assert_eq!(
+        e.to_string(),
+        "-9223372036854775808*in0 - 9223372036854775808"
+    );
+}
+
+#[test]
+fn undecidable() {
+    // Jumping on an unknown
+    let prog = assemble("IN [a]\nJZ [a], #end\nOUT #1\nend: HLT\na: .data 0").unwrap();
+    let mut sym = Symbolic::new(&prog);
+    sym.unknown_input(0..=1);
+    assert_eq!(sym.run(), Err(SymbolicError::Branch { pc: 2 }));
+
+    // Writing through an unknown address
+    let mut sym = Symbolic::new(&[1101, 1, 1, 0, 99]);
+    sym.unknown_cell(3, 0..=4);
+    assert_eq!(sym.run(), Err(SymbolicError::Address { pc: 0 }));
+
+    // Not enough input
+    let mut sym = Symbolic::new(&[3, 0, 99]);
+    assert_eq!(sym.run(), Err(SymbolicError::NeedsInput { pc: 0 }));
+
+    // Loops that never end
+    let mut sym = Symbolic::new(&[1105, 1, 0]);
+    sym.set_step_limit(1000);
+    assert_eq!(sym.run(), Err(SymbolicError::StepLimit));
+}
@@ -0,0 +1,177 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::network::{NetState, Topology};
+use intcode::threaded::{permutations, sweep, ThreadedNetwork};
+use intcode::{IntcodeError, RunState};
+
+// Feedback amplifier from day 7
+const AMP_FB: [i64; 29] = [
+    3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28, 1005,
+    28, 6, 99, 0, 0, 5,
+];
+
+fn amplifiers(phases: &[i64]) -> i64 {
+    let mut net = ThreadedNetwork::new(&AMP_FB, phases.len(), Topology::ring(phases.len()));
+    net.set_capacity(1);
+    for (i, &p) in phases.iter().enumerate() {
+        net.send(i, p);
+    }
+    net.send(0, 0);
+    let (state, reports) = net.run();
+    assert_eq!(state, NetState::Halted);
+    reports.last().unwrap().last.unwrap()
+}
+
+#[test]
+fn feedback_ring() {
+    assert_eq!(amplifiers(&[9, 8, 7, 6, 5]), 139629729);
+}
+
+#[test]
+fn sweep_feedback_ring() {
+    let best = sweep(permutations(&[5, 6, 7, 8, 9]), |p| amplifiers(&p))
+        .into_iter()
+        .max();
+    assert_eq!(best, Some(139629729));
+}
+
+#[test]
+fn pipeline_sink_keeps_outputs() {
+    let add_one =
+        assemble("loop: IN [x]\nADD [x], #1, [x]\nOUT [x]\nJZ #0, #loop\nx: .data 0").unwrap();
+    let mut net = ThreadedNetwork::new(&add_one, 3, Topology::pipeline(3));
+    for v in 0..5 {
+        net.send(0, v * 10);
+    }
+    let (state, reports) = net.run();
+    // Nothing halts, the first node runs dry and the rest starve after it
+    assert_eq!(state, NetState::Deadlock);
+    assert_eq!(reports[2].outputs, vec![3, 13, 23, 33, 43]);
+    assert!(reports[0].outputs.is_empty());
+    assert_eq!(reports[1].last, Some(42));
+    for r in &reports {
+        assert_eq!(r.state, RunState::AwaitingInput);
+    }
+}
+
+#[test]
+fn fault_stops_spinning_nodes() {
+    // Node 0 faults on its first instruction, node 1 would otherwise spin forever
+    let spin = assemble("loop: JZ #0, #loop").unwrap();
+    let nodes = vec![
+        intcode::IntcodeComp::new(&[0]),
+        intcode::IntcodeComp::new(&spin),
+    ];
+    let (state, reports) = ThreadedNetwork::from_nodes(nodes, Topology::pipeline(2)).run();
+    assert_eq!(
+        state,
+        NetState::Error(0, IntcodeError::UnknownOpcode { pc: 0, word: 0 })
+    );
+    assert_eq!(reports[1].state, RunState::AwaitingInput);
+}
+
+#[test]
+fn busy_node_outlives_a_halt() {
+    // Node 0 sends one value and halts, node 1 is still counting long after that
+    let count = assemble(
+        "IN [x]
+        loop:
+            ADD [x], #1, [x]
+            ADD [n], #-1, [n]
+            JNZ [n], #loop
+            OUT [x]
+            HLT
+        x: .data 0
+        n: .data 20000",
+    )
+    .unwrap();
+    let nodes = vec![
+        intcode::IntcodeComp::new(&[104, 7, 99]),
+        intcode::IntcodeComp::new(&count),
+    ];
+    let (state, reports) = ThreadedNetwork::from_nodes(nodes, Topology::pipeline(2)).run();
+    assert_eq!(state, NetState::Halted);
+    assert_eq!(reports[1].last, Some(20007));
+    assert_eq!(reports[1].outputs, vec![20007]);
+}
+
+#[test]
+fn starved_after_a_halt() {
+    // Node 1 wants a second value that node 0 is never going to send
+    let add = assemble("IN [x]\nIN [y]\nADD [x], [y], [x]\nOUT [x]\nHLT\nx: .data 0\ny: .data 0")
+        .unwrap();
+    let nodes = vec![
+        intcode::IntcodeComp::new(&[104, 7, 99]),
+        intcode::IntcodeComp::new(&add),
+    ];
+    let (state, reports) = ThreadedNetwork::from_nodes(nodes, Topology::Packets).run();
+    assert_eq!(state, NetState::Deadlock);
+    assert_eq!(reports[0].state, RunState::Halted);
+    assert_eq!(reports[1].state, RunState::AwaitingInput);
+}
+
+#[test]
+fn full_channels_deadlock() {
+    // Both nodes only ever send, once the one-value channels fill up neither can go on
+    let flood = assemble("loop: OUT #1\nJZ #0, #loop").unwrap();
+    let mut net = ThreadedNetwork::new(&flood, 2, Topology::ring(2));
+    net.set_capacity(1);
+    let (state, reports) = net.run();
+    assert_eq!(state, NetState::Deadlock);
+    for r in &reports {
+        assert_eq!(r.state, RunState::AwaitingInput);
+        assert_eq!(r.last, Some(1));
+    }
+
+    // A sender waiting on a node that's stuck sending is stuck too
+    let nodes = vec![
+        intcode::IntcodeComp::new(&flood),
+        intcode::IntcodeComp::new(&flood),
+        intcode::IntcodeComp::new(&flood),
+    ];
+    let mut net =
+        ThreadedNetwork::from_nodes(nodes, Topology::Links(vec![vec![1], vec![2], vec![1]]));
+    net.set_capacity(1);
+    assert_eq!(net.run().0, NetState::Deadlock);
+}
+
+#[test]
+fn ring_waiting_on_itself_deadlocks() {
+    let echo = assemble("loop: IN [x]\nOUT [x]\nJZ #0, #loop\nx: .data 0").unwrap();
+    let (state, _) = ThreadedNetwork::new(&echo, 4, Topology::ring(4)).run();
+    assert_eq!(state, NetState::Deadlock);
+}
+
+#[test]
+fn packets_route_by_address() {
+    // Node 0 sends (1, 5, 6), node 1 adds x and y and reports to address 99 which has no node
+    let sender = assemble("OUT #1\nOUT #5\nOUT #6\nHLT").unwrap();
+    let adder = assemble(
+        "IN [x]\nIN [y]\nADD [x], [y], [x]\nOUT #99\nOUT [x]\nOUT #0\nHLT\nx: .data 0\ny: .data 0",
+    )
+    .unwrap();
+    let nodes = vec![
+        intcode::IntcodeComp::new(&sender),
+        intcode::IntcodeComp::new(&adder),
+    ];
+    let (state, reports) = ThreadedNetwork::from_nodes(nodes, Topology::Packets).run();
+    assert_eq!(state, NetState::Halted);
+    assert_eq!(reports[1].outputs, vec![99, 11, 0]);
+}
+
+#[test]
+fn sweep_keeps_order() {
+    let squares = sweep((0..100).collect(), |x: i64| x * x);
+    assert_eq!(squares, (0..100).map(|x| x * x).collect::<Vec<_>>());
+}
+
+#[test]
+fn all_permutations() {
+    let perms = permutations(&[1, 2, 3, 4]);
+    assert_eq!(perms.len(), 24);
+    let mut sorted = perms.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(sorted.len(), 24);
+    assert_eq!(perms[0], vec![1, 2, 3, 4]);
+}
@@ -0,0 +1,172 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::io::Split;
+use intcode::trace::{Divergence, End, Trace};
+use intcode::{Budget, IntcodeComp, IntcodeError, RunState};
+use std::collections::VecDeque;
+
+// Outputs the running total after every input, and once more when a 0 ends it
+const TOTALS: &str = "
+    loop:
+        IN [x]
+        JZ [x], #end
+        ADD [x], [total], [total]
+        OUT [total]
+        JZ #0, #loop
+    end:
+        OUT [total]
+        HLT
+    x:     .data 0
+    total: .data 0
+";
+
+fn session(inputs: &[i64]) -> (RunState, Trace, Vec<i64>) {
+    let mut comp = IntcodeComp::new(&assemble(TOTALS).unwrap());
+    let mut keys: VecDeque<i64> = inputs.iter().copied().collect();
+    let mut seen = Vec::new();
+    let (state, trace) = comp.record(&mut Split(&mut keys, &mut seen));
+    (state, trace, seen)
+}
+
+#[test]
+fn record() {
+    let (state, trace, seen) = session(&[3, 4, 0]);
+    assert_eq!(state, RunState::Halted);
+    // The device still sees everything
+    assert_eq!(seen, vec![3, 7, 7]);
+    assert_eq!(trace.inputs, vec![3, 4, 0]);
+    let counts: Vec<u64> = trace.steps.iter().map(|s| s.count).collect();
+    assert_eq!(counts, vec![0, 1, 1, 1]);
+    // Same outputs hash the same
+    assert_eq!(trace.steps[2], trace.steps[3]);
+    assert_ne!(trace.steps[1], trace.steps[2]);
+    assert_eq!(trace.end, End::Halted);
+}
+
+#[test]
+fn replay() {
+    let (_, trace, seen) = session(&[3, 4, 0]);
+    let mut comp = IntcodeComp::new(&assemble(TOTALS).unwrap());
+    let mut out = Vec::new();
+    assert_eq!(comp.replay(&trace, &mut out), Ok(RunState::Halted));
+    assert_eq!(out, seen);
+
+    // Running out of input is a way to end too
+    let (state, trace, _) = session(&[5, 6]);
+    assert_eq!(state, RunState::AwaitingInput);
+    let mut comp = IntcodeComp::new(&assemble(TOTALS).unwrap());
+    assert_eq!(
+        comp.replay(&trace, &mut Vec::new()),
+        Ok(RunState::AwaitingInput)
+    );
+}
+
+#[test]
+fn divergence() {
+    let (_, trace, _) = session(&[3, 4, 0]);
+    let prog = assemble(TOTALS).unwrap();
+
+    // Multiplying instead of adding gets the second step wrong and stops right there
+    let mut broken = prog.clone();
+    broken[5] = 2;
+    let mut comp = IntcodeComp::new(&broken);
+    match comp.replay(&trace, &mut Vec::new()) {
+        Err(Divergence::Outputs {
+            step,
+            expected,
+            found,
+        }) => {
+            assert_eq!(step, 1);
+            assert_eq!(expected.count, found.count);
+            assert_ne!(expected.hash, found.hash);
+        }
+        other => panic!("expected an output divergence, got {:?}", other),
+    }
+    assert_eq!(comp.input_queue().len(), 0);
+
+    // Halting on the first input
+    let mut broken = prog.clone();
+    broken[0] = 99;
+    let mut comp = IntcodeComp::new(&broken);
+    assert_eq!(
+        comp.replay(&trace, &mut Vec::new()),
+        Err(Divergence::Inputs {
+            read: 0,
+            recorded: 3
+        })
+    );
+
+    // Starving instead of halting after the 0
+    let mut broken = prog;
+    let end = broken.len() - 3;
+    broken[end] = 3;
+    let mut comp = IntcodeComp::new(&broken);
+    assert_eq!(
+        comp.replay(&trace, &mut Vec::new()),
+        Err(Divergence::End {
+            expected: End::Halted,
+            found: End::Blocked
+        })
+    );
+}
+
+#[test]
+fn text_round_trip() {
+    let (_, trace, _) = session(&[3, -4, 0]);
+    let text = trace.to_string();
+    assert!(text.starts_with("intcode trace\nout 0 "));
+    assert!(text.contains("\nin -4\n"));
+    assert!(text.ends_with("\nend halted\n"));
+    assert_eq!(text.parse::<Trace>(), Ok(trace.clone()));
+
+    let path = std::env::temp_dir().join("intcode_trace_test.trace");
+    let path = path.to_str().unwrap();
+    trace.save(path).unwrap();
+    assert_eq!(Trace::load(path).unwrap(), trace);
+    std::fs::remove_file(path).ok();
+
+    // Lines out of order or left over
+    let bad = text.replacen("in 3\n", "", 1);
+    assert_eq!(bad.parse::<Trace>().unwrap_err().line, 3);
+    assert!(format!("{}trailing\n", text).parse::<Trace>().is_err());
+    assert!("out 0 0\nend halted".parse::<Trace>().is_err());
+    assert!(text
+        .replace("end halted", "end Halted")
+        .parse::<Trace>()
+        .is_err());
+    assert!(format!("{}\n\n", text).parse::<Trace>().is_ok());
+}
+
+#[test]
+fn end_tokens() {
+    // Stable names that don't follow RunState's Debug output or the error messages
+    let fault = RunState::Error(IntcodeError::UnknownOpcode { pc: 4, word: 42 });
+    let ends = [
+        (RunState::Halted, "halted"),
+        (RunState::AwaitingInput, "blocked"),
+        (fault, "fault:unknown-opcode:4"),
+        (RunState::Breakpoint(7), "breakpoint:7"),
+        (
+            RunState::BudgetExhausted(Budget::WallClock),
+            "budget:wall-clock",
+        ),
+        (RunState::Cycling(3), "cycling:3"),
+    ];
+    for (state, token) in ends.iter() {
+        let end = End::of(state);
+        assert_eq!(end.to_string(), *token);
+        assert_eq!(token.parse::<End>(), Ok(end));
+    }
+    for bad in [
+        "Halted",
+        "fault:4",
+        "fault::4",
+        "breakpoint:x",
+        "cycling:1:2",
+        "",
+    ]
+    .iter()
+    {
+        assert!(bad.parse::<End>().is_err(), "{}", bad);
+    }
+}
@@ -0,0 +1,151 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::display::{Display, Palette, MAX_RENDER};
+use intcode::tui::{Key, MemoryBackend, Tui};
+use intcode::{IntcodeComp, RunState};
+use std::collections::VecDeque;
+use std::io;
+
+// A dot between two walls moving by whatever it's given each frame, it also reports where it is
+const TRACK: &str = "
+        OUT #0
+        OUT #0
+        OUT #1
+        OUT #6
+        OUT #0
+        OUT #1
+    loop:
+        OUT [x]
+        OUT #0
+        OUT #2
+        OUT #-1
+        OUT #0
+        OUT [x]
+        IN [d]
+        OUT [x]
+        OUT #0
+        OUT #0
+        ADD [x], [d], [x]
+        JZ #0, #loop
+    x: .data 3
+    d: .data 0
+";
+
+fn track() -> (IntcodeComp, Display) {
+    let mut display = Display::new();
+    display.set_palette(Palette::new(&[(0, ' '), (1, '|'), (2, 'o')]));
+    display.add_channel("pos", -1, 0);
+    (IntcodeComp::new(&assemble(TRACK).unwrap()), display)
+}
+
+#[test]
+fn keys_and_frames() {
+    let (mut comp, mut display) = track();
+    let mut backend = MemoryBackend::new();
+    backend
+        .press(Key::Right)
+        .wait()
+        // Paused, a bound key still moves one frame and so does n
+        .press(Key::Char('p'))
+        .press(Key::Left)
+        .press(Key::Left)
+        .type_str("n-++q");
+    let mut tui = Tui::new(backend);
+    tui.bind(Key::Left, -1).bind(Key::Right, 1);
+    tui.set_idle_input(Some(0));
+
+    let state = tui.run(&mut comp, &mut display).unwrap();
+    assert_eq!(state, RunState::AwaitingInput);
+    assert_eq!(tui.frames(), 6);
+    assert!(tui.paused());
+    let screen = tui.backend();
+    assert_eq!(screen.row(0), "| o   |");
+    assert_eq!(screen.row(1), "pos 2  stopped  frame 6  50ms/frame");
+    assert!(screen.row(2).starts_with("p pause"));
+}
+
+#[test]
+fn redraws_only_changes() {
+    let (mut comp, mut display) = track();
+    comp.run_with(&mut VecDeque::new(), &mut display);
+    let mut tui = Tui::new(MemoryBackend::new());
+    tui.render(&mut display, "x").unwrap();
+    assert_eq!(
+        tui.backend().to_string(),
+        "|  o  |\npos 3  x  frame 0  100ms/frame\np pause  n step  + faster  - slower  q quit\n"
+    );
+
+    // Moving the dot is two cells and the status line
+    let status = "pos 2  x  frame 0  100ms/frame".len();
+    tui.backend_mut().take_written();
+    comp.run_with(&mut VecDeque::from(vec![-1]), &mut display);
+    tui.render(&mut display, "x").unwrap();
+    assert_eq!(tui.backend_mut().take_written(), 2 + status);
+    assert_eq!(tui.backend().row(0), "| o   |");
+
+    // Nothing changed, nothing but the status line
+    tui.render(&mut display, "x").unwrap();
+    assert_eq!(tui.backend_mut().take_written(), status);
+
+    // Growing the picture redraws all of it, with the status line moved down
+    display.set(8, 1, 1);
+    tui.render(&mut display, "x").unwrap();
+    assert_eq!(tui.backend().row(0), "| o   |");
+    assert_eq!(tui.backend().row(1), "        |");
+    assert!(tui.backend().row(2).starts_with("pos 2"));
+}
+
+#[test]
+fn y_up() {
+    let mut display = Display::new();
+    display.set_y_up(true);
+    display.set_palette(Palette::new(&[(0, ' ')]));
+    display.set(0, 0, 1);
+    display.set(1, 1, 2);
+    let mut tui = Tui::new(MemoryBackend::new());
+    tui.render(&mut display, "").unwrap();
+    assert_eq!(tui.backend().row(0), " 2");
+    assert_eq!(tui.backend().row(1), "1");
+    display.set(0, 0, 3);
+    tui.render(&mut display, "").unwrap();
+    assert_eq!(tui.backend().row(1), "3");
+}
+
+#[test]
+fn clipped_canvas() {
+    let mut display = Display::new();
+    display.set_palette(Palette::new(&[(0, '.')]));
+    display.set(0, 0, 1);
+    display.set(i64::MAX, 0, 1);
+    let mut tui = Tui::new(MemoryBackend::new());
+    tui.render(&mut display, "x").unwrap();
+    assert_eq!(tui.backend().row(0).len(), MAX_RENDER);
+    assert!(tui.backend().row(1).starts_with("x  frame 0"));
+
+    // Changes past the edge aren't drawn, the ones inside still are
+    tui.backend_mut().take_written();
+    display.set(i64::MAX, 0, 2);
+    display.set(1, 0, 2);
+    tui.render(&mut display, "x").unwrap();
+    assert!(tui.backend().row(0).starts_with("12."));
+    assert_eq!(tui.backend().row(0).len(), MAX_RENDER);
+    assert_eq!(
+        tui.backend_mut().take_written(),
+        1 + "x  frame 0  100ms/frame".len()
+    );
+}
+
+#[test]
+fn backend_errors() {
+    // With no idle input every frame waits for a key, and this script runs dry
+    let (mut comp, mut display) = track();
+    let mut backend = MemoryBackend::new();
+    backend.press(Key::Right).wait().press(Key::Up);
+    let mut tui = Tui::new(backend);
+    tui.bind(Key::Right, 1);
+    let err = tui.run(&mut comp, &mut display).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    // The last frame is still drawn
+    assert_eq!(tui.backend().row(0), "|   o |");
+    assert!(tui.backend().row(1).contains("stopped"));
+}
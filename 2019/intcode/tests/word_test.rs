@@ -0,0 +1,94 @@
+extern crate intcode;
+use intcode::asm::assemble;
+use intcode::{BigInt, IntcodeComp, IntcodeError, Overflow, RunState};
+
+// Squares n as many times as the input says then outputs it, 3 squared 6 times is past i64
+const SQUARES: &str = "
+        IN [k]
+    loop:
+        MUL [n], [n], [n]
+        ADD [k], #-1, [k]
+        JNZ [k], #loop
+        OUT [n]
+        HLT
+    n: .data 3
+    k: .data 0
+";
+
+fn pow3(exp: u32) -> BigInt {
+    BigInt::from(3).pow(exp)
+}
+
+#[test]
+fn trap_by_default() {
+    let mut comp = IntcodeComp::new(&assemble(SQUARES).unwrap());
+    assert_eq!(comp.overflow(), Overflow::Trap);
+    comp.input(5);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output(), Some(3i64.pow(32)));
+
+    let mut comp = IntcodeComp::new(&assemble(SQUARES).unwrap());
+    comp.input(6);
+    match comp.eval_async() {
+        RunState::Error(e @ IntcodeError::Overflow { .. }) => assert_eq!(e.pc(), 2),
+        other => panic!("expected an overflow, got {:?}", other),
+    }
+}
+
+#[test]
+fn wrap() {
+    let mut comp = IntcodeComp::new(&assemble(SQUARES).unwrap());
+    comp.set_overflow(Overflow::Wrap);
+    comp.input(6);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output(), Some(3i64.wrapping_pow(64)));
+}
+
+#[test]
+fn promote_and_resume() {
+    let prog = assemble(SQUARES).unwrap();
+    let n = prog.len() - 2;
+    let mut comp = IntcodeComp::new(&prog);
+    comp.set_overflow(Overflow::Promote);
+    comp.input(7);
+    // Stops on the multiply that doesn't fit, before anything is written
+    assert_eq!(comp.eval_async(), RunState::Overflowed(2));
+    assert_eq!(comp.read_mem(n), 3i64.pow(32));
+
+    let mut wide = comp.promote::<i128>();
+    assert_eq!(wide.overflow(), Overflow::Promote);
+    assert_eq!(wide.eval_async(), RunState::Overflowed(2));
+    assert_eq!(wide.read_mem(n), 3i128.pow(64));
+
+    let mut big = wide.promote::<BigInt>();
+    assert_eq!(big.eval_async(), RunState::Halted);
+    assert_eq!(big.output(), Some(pow3(128)));
+}
+
+#[test]
+fn bigint_machine() {
+    let mut comp = IntcodeComp::<BigInt>::from_prog(&assemble(SQUARES).unwrap());
+    comp.input(BigInt::from(10));
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output(), Some(pow3(1024)));
+}
+
+#[test]
+fn wide_immediates() {
+    // Adds 2^100 to the input and says whether the result is bigger than 2^100
+    let big = 1i128 << 100;
+    let prog = [3, 13, 1001, 13, big, 13, 1007, 13, big, 14, 4, 14, 99, 0, 0];
+    let mut comp = IntcodeComp::<i128>::from_words(&prog);
+    comp.input(1);
+    assert_eq!(comp.eval_async(), RunState::Halted);
+    assert_eq!(comp.output(), Some(0));
+    assert_eq!(comp.read_mem(13), big + 1);
+
+    // Addresses have to fit an i64
+    let mut comp = IntcodeComp::<i128>::from_words(&[3, big, 99]);
+    comp.input(1);
+    match comp.eval_async() {
+        RunState::Error(e @ IntcodeError::Overflow { .. }) => assert_eq!(e.pc(), 0),
+        other => panic!("expected an overflow, got {:?}", other),
+    }
+}